@@ -0,0 +1,18 @@
+OPENQASM 3.0;
+include "stdgates.inc";
+
+qubit[4] q;
+bit[4] c;
+
+// Rotate then entangle a qubit with its neighbour
+gate stage(theta) a, b {
+    rz(theta) a;
+    cx a, b;
+}
+
+h q[0];
+for uint i in [0:2] {
+    stage(pi/4) q[i], q[i+1];
+}
+
+c = measure q;
@@ -0,0 +1,91 @@
+use std::f64::consts::{PI, TAU};
+
+// Default epsilon for angle comparisons, in radians
+pub const DEFAULT_EPSILON: f64 = 1e-9;
+
+// Wrap an angle into (-π, π]. RX and RZ take half angles, so a full turn only
+// changes the global phase and the wrapped rotation is equivalent. Angles
+// already in range are returned unchanged.
+pub fn normalize(f: f64) -> f64 {
+    if !f.is_finite() || (f > -PI && f <= PI) {
+        return f;
+    }
+
+    let wrapped = f.rem_euclid(TAU);
+    if wrapped > PI {
+        wrapped - TAU
+    } else {
+        wrapped
+    }
+}
+
+// How close two angles have to be, modulo 2π, to be treated as equal. Used by
+// the passes that compare rotation angles, e.g. deadcode elimination and
+// native gate detection.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct Tolerance {
+    pub epsilon: f64,
+}
+
+impl Default for Tolerance {
+    fn default() -> Tolerance {
+        Tolerance {
+            epsilon: DEFAULT_EPSILON,
+        }
+    }
+}
+
+impl Tolerance {
+    pub fn new(epsilon: f64) -> Tolerance {
+        Tolerance { epsilon }
+    }
+
+    // True if a rotation by `f` is the identity up to global phase
+    pub fn is_zero(&self, f: f64) -> bool {
+        normalize(f).abs() <= self.epsilon
+    }
+
+    pub fn approx_eq(&self, a: f64, b: f64) -> bool {
+        self.is_zero(a - b)
+    }
+}
+
+#[rustfmt::skip]
+#[allow(clippy::approx_constant)]
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn normalize_angles() {
+        assert_eq!(normalize(0.45), 0.45);
+        assert_eq!(normalize(PI), PI);
+        assert_eq!(normalize(-PI), PI);
+        assert_eq!(normalize(TAU), 0.0);
+        assert_eq!(normalize(6.283185307179586), 0.0);
+        assert!((normalize(3.0 * PI / 2.0) + PI / 2.0).abs() < 1e-15);
+        assert!((normalize(-5.0 * PI / 2.0) + PI / 2.0).abs() < 1e-15);
+        assert!(normalize(f64::NAN).is_nan());
+    }
+
+    #[test]
+    fn compare_with_epsilon() {
+        let tolerance = Tolerance::default();
+        assert!(tolerance.is_zero(0.0));
+        assert!(tolerance.is_zero(1e-17));
+        assert!(tolerance.is_zero(6.283185307179586));
+        assert!(tolerance.is_zero(-TAU + 1e-12));
+        assert!(!tolerance.is_zero(1e-6));
+
+        assert!(tolerance.approx_eq(1.570796326794896, PI / 2.0));
+        assert!(tolerance.approx_eq(-PI, PI));
+        assert!(tolerance.approx_eq(3.0 * PI / 2.0, -PI / 2.0));
+        assert!(!tolerance.approx_eq(PI / 2.0, -PI / 2.0));
+
+        // Exact comparison still wraps whole turns
+        let exact = Tolerance::new(0.0);
+        assert!(exact.is_zero(TAU));
+        assert!(!exact.is_zero(1e-17));
+        assert!(!exact.approx_eq(1.570796326794896, PI / 2.0));
+    }
+}
@@ -0,0 +1,398 @@
+use std::f64::consts::TAU;
+
+use crate::error::CompileError;
+use crate::instruction::Instruction;
+use crate::simulate::num_qubits;
+
+// Compact binary encoding of a program. All multi-byte fixed width fields
+// are little-endian.
+//
+// Header:
+//   magic             4 bytes  "INQB"
+//   version           1 byte   FORMAT_VERSION
+//   flags             1 byte   bit 0 set when angles are quantized
+//   qubit count       varint   every qubit index is below this
+//   instruction count varint
+//   checksum          4 bytes  CRC-32 of the instruction stream
+//
+// Instruction stream, one entry per instruction:
+//   RX/RZ    opcode, angle, varint qubit
+//   CZ       opcode, varint qubit, varint qubit
+//   MEASURE  opcode, varint qubit
+//
+// Angles are an f64 (8 bytes) or, when quantized, a u32 counting turns in
+// units of 2π / 2^32.
+
+pub const MAGIC: &[u8; 4] = b"INQB";
+pub const FORMAT_VERSION: u8 = 1;
+
+const FLAG_QUANTIZED: u8 = 0x01;
+
+const OP_RX: u8 = 0;
+const OP_RZ: u8 = 1;
+const OP_CZ: u8 = 2;
+const OP_MEASURE: u8 = 3;
+
+// How rotation angles are stored
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum AngleEncoding {
+    // Exact, 8 bytes per angle
+    F64,
+    // 4 bytes per angle, accurate to about 1.5e-9 radians. Angles are
+    // reduced modulo 2π, which only changes a rotation by a global phase.
+    Quantized,
+}
+
+// Encode program in the binary format
+pub fn encode(program: &[Instruction], angles: AngleEncoding) -> Vec<u8> {
+    let mut body = Vec::new();
+    for instr in program {
+        match *instr {
+            Instruction::RX(f, q) | Instruction::RZ(f, q) => {
+                let opcode = match instr {
+                    Instruction::RX(..) => OP_RX,
+                    _ => OP_RZ,
+                };
+                body.push(opcode);
+                match angles {
+                    AngleEncoding::F64 => body.extend_from_slice(&f.to_le_bytes()),
+                    AngleEncoding::Quantized => body.extend_from_slice(&quantize(f).to_le_bytes()),
+                }
+                write_varint(&mut body, q as u64);
+            }
+            Instruction::CZ(q1, q2) => {
+                body.push(OP_CZ);
+                write_varint(&mut body, q1 as u64);
+                write_varint(&mut body, q2 as u64);
+            }
+            Instruction::MEASURE(q) => {
+                body.push(OP_MEASURE);
+                write_varint(&mut body, q as u64);
+            }
+            Instruction::INVALID => panic!("invalid instruction"),
+        }
+    }
+
+    let mut output = Vec::with_capacity(body.len() + 20);
+    output.extend_from_slice(MAGIC);
+    output.push(FORMAT_VERSION);
+    output.push(match angles {
+        AngleEncoding::F64 => 0,
+        AngleEncoding::Quantized => FLAG_QUANTIZED,
+    });
+    write_varint(&mut output, num_qubits(program));
+    write_varint(&mut output, program.len() as u64);
+    output.extend_from_slice(&crc32(&body).to_le_bytes());
+    output.extend_from_slice(&body);
+
+    output
+}
+
+// Decode a program in the binary format, checking its header and checksum
+pub fn decode(bytes: &[u8]) -> Result<Vec<Instruction>, CompileError> {
+    let mut reader = Reader { bytes, offset: 0 };
+
+    if reader.take(MAGIC.len()).ok() != Some(MAGIC.as_slice()) {
+        return Err(CompileError::io(
+            "Not a binary program, missing 'INQB' header",
+        ));
+    }
+    let version = reader.byte()?;
+    if version != FORMAT_VERSION {
+        return Err(CompileError::io(format!(
+            "Unsupported binary program version {}",
+            version
+        )));
+    }
+    let flags = reader.byte()?;
+    if flags & !FLAG_QUANTIZED != 0 {
+        return Err(CompileError::io(format!(
+            "Unknown binary program flags {:#04x}",
+            flags
+        )));
+    }
+    let qubit_count = reader.varint()?;
+    let instruction_count = reader.varint()?;
+    let checksum = u32::from_le_bytes(reader.array()?);
+
+    if crc32(&bytes[reader.offset..]) != checksum {
+        return Err(CompileError::io("Checksum mismatch in binary program"));
+    }
+
+    // Every instruction takes at least two bytes, so a count larger than
+    // that is rejected before allocating
+    let capacity = (instruction_count as usize).min(bytes.len() / 2);
+    let mut program = Vec::with_capacity(capacity);
+    for _ in 0..instruction_count {
+        let offset = reader.offset;
+        let instr = match reader.byte()? {
+            opcode @ (OP_RX | OP_RZ) => {
+                let f = if flags & FLAG_QUANTIZED != 0 {
+                    dequantize(u32::from_le_bytes(reader.array()?))
+                } else {
+                    f64::from_le_bytes(reader.array()?)
+                };
+                if !f.is_finite() {
+                    return Err(CompileError::io(format!(
+                        "Rotation angle is not a finite number at byte {}",
+                        offset
+                    )));
+                }
+                let q = reader.qubit()?;
+                if opcode == OP_RX {
+                    Instruction::RX(f, q)
+                } else {
+                    Instruction::RZ(f, q)
+                }
+            }
+            OP_CZ => {
+                let (q1, q2) = (reader.qubit()?, reader.qubit()?);
+                if q1 == q2 {
+                    return Err(CompileError::io(format!(
+                        "CZ requires two distinct qbits at byte {}",
+                        offset
+                    )));
+                }
+                Instruction::CZ(q1, q2)
+            }
+            OP_MEASURE => Instruction::MEASURE(reader.qubit()?),
+            opcode => {
+                return Err(CompileError::io(format!(
+                    "Unknown opcode {} at byte {}",
+                    opcode, offset
+                )))
+            }
+        };
+
+        if num_qubits(&[instr]) > qubit_count {
+            return Err(CompileError::io(format!(
+                "Qubit index out of range for {} qubit(s) at byte {}",
+                qubit_count, offset
+            )));
+        }
+        program.push(instr);
+    }
+
+    if reader.offset != bytes.len() {
+        return Err(CompileError::io(format!(
+            "Unexpected trailing bytes at byte {}",
+            reader.offset
+        )));
+    }
+
+    Ok(program)
+}
+
+// True if bytes start with the binary program header
+pub fn is_binary(bytes: &[u8]) -> bool {
+    bytes.starts_with(MAGIC)
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    offset: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], CompileError> {
+        match self.bytes.get(self.offset..self.offset + len) {
+            Some(slice) => {
+                self.offset += len;
+                Ok(slice)
+            }
+            None => Err(CompileError::io(format!(
+                "Unexpected end of binary program at byte {}",
+                self.bytes.len()
+            ))),
+        }
+    }
+
+    fn byte(&mut self) -> Result<u8, CompileError> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn array<const N: usize>(&mut self) -> Result<[u8; N], CompileError> {
+        Ok(self.take(N)?.try_into().unwrap())
+    }
+
+    // Up to five bytes, enough for the qubit count 2^32 of a program using
+    // qubit u32::MAX
+    fn varint(&mut self) -> Result<u64, CompileError> {
+        let start = self.offset;
+        let mut value: u64 = 0;
+        for shift in (0..35).step_by(7) {
+            let byte = self.byte()?;
+            value |= ((byte & 0x7f) as u64) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        Err(overlong(start))
+    }
+
+    fn qubit(&mut self) -> Result<u32, CompileError> {
+        let start = self.offset;
+        u32::try_from(self.varint()?).map_err(|_| overlong(start))
+    }
+}
+
+fn overlong(offset: usize) -> CompileError {
+    CompileError::io(format!("Integer out of range at byte {}", offset))
+}
+
+fn write_varint(output: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        output.push((value as u8 & 0x7f) | 0x80);
+        value >>= 7;
+    }
+    output.push(value as u8);
+}
+
+// Angle as a fraction of a full turn, in units of 2^-32 turns
+fn quantize(f: f64) -> u32 {
+    let turns = f.rem_euclid(TAU) / TAU;
+    ((turns * 4294967296.0).round() as u64 % (1 << 32)) as u32
+}
+
+// Inverse of quantize, mapped into (-π, π]
+fn dequantize(units: u32) -> f64 {
+    let f = units as f64 / 4294967296.0 * TAU;
+    if f > TAU / 2.0 {
+        f - TAU
+    } else {
+        f
+    }
+}
+
+// CRC-32 (IEEE 802.3), as used by zip and png
+fn crc32(bytes: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for &byte in bytes {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 {
+                (crc >> 1) ^ 0xedb88320
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    !crc
+}
+
+#[rustfmt::skip]
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::equivalence::check_equivalence;
+    use crate::equivalence::testing::{random_program, Rng};
+    use std::f64::consts::PI;
+
+    #[test]
+    fn encode_program() {
+        let program = [
+            Instruction::RX(0.45, 0),
+            Instruction::RZ(-PI/2.0, 200),
+            Instruction::CZ(0, 200),
+            Instruction::MEASURE(200),
+        ];
+        let bytes = encode(&program, AngleEncoding::F64);
+
+        assert_eq!(&bytes[..4], b"INQB");
+        assert_eq!(bytes[4], FORMAT_VERSION);
+        assert_eq!(bytes[5], 0);
+        // 201 qubits and 4 instructions as varints
+        assert_eq!(&bytes[6..9], &[0xc9, 0x01, 0x04]);
+        assert_eq!(bytes[13], OP_RX);
+        assert_eq!(&bytes[14..22], &0.45f64.to_le_bytes());
+        assert_eq!(bytes.len(), 13 + 10 + 11 + 4 + 3);
+    }
+
+    #[test]
+    fn round_trip() {
+        let program = vec![
+            Instruction::RZ(PI/3.0, 7),
+            Instruction::CZ(7, 1000),
+            Instruction::RX(-0.125, 1000),
+            Instruction::MEASURE(7),
+        ];
+        let bytes = encode(&program, AngleEncoding::F64);
+        assert_eq!(decode(&bytes).unwrap(), program);
+        assert!(is_binary(&bytes));
+
+        assert_eq!(decode(&encode(&[], AngleEncoding::F64)).unwrap(), vec![]);
+    }
+
+    #[test]
+    fn round_trip_quantized() {
+        let program = [Instruction::RX(0.45, 0), Instruction::RZ(-PI/2.0, 200), Instruction::MEASURE(200)];
+        let bytes = encode(&program, AngleEncoding::Quantized);
+        assert_eq!(bytes.len(), encode(&program, AngleEncoding::F64).len() - 8);
+
+        let decoded = decode(&bytes).unwrap();
+        match (decoded[0], decoded[1]) {
+            (Instruction::RX(f1, 0), Instruction::RZ(f2, 200)) => {
+                assert!((f1 - 0.45).abs() < 1e-8);
+                assert!((f2 + PI/2.0).abs() < 1e-8);
+            }
+            _ => panic!("unexpected instructions {:?}", decoded),
+        }
+
+        // Angles outside (-π, π] only move by a multiple of 2π
+        let program = random_program(&mut Rng::new(11), 3, 40);
+        let decoded = decode(&encode(&program, AngleEncoding::Quantized)).unwrap();
+        assert!(check_equivalence(&program, &decoded, 1e-6).unwrap().is_equivalent());
+    }
+
+    #[test]
+    fn varints() {
+        for value in [0, 1, 127, 128, 300, 16384, u32::MAX as u64, 1 << 32] {
+            let mut bytes = Vec::new();
+            write_varint(&mut bytes, value);
+            let mut reader = Reader { bytes: &bytes, offset: 0 };
+            assert_eq!(reader.varint().unwrap(), value);
+            assert_eq!(reader.offset, bytes.len());
+        }
+
+        let mut reader = Reader { bytes: &[0xff, 0xff, 0xff, 0xff, 0x1f], offset: 0 };
+        assert_eq!(reader.qubit().unwrap_err().message(), "Integer out of range at byte 0");
+
+        let mut reader = Reader { bytes: &[0xff, 0xff, 0xff, 0xff, 0xff, 0x01], offset: 0 };
+        assert_eq!(reader.varint().unwrap_err().message(), "Integer out of range at byte 0");
+    }
+
+    #[test]
+    fn highest_qubit() {
+        let program = vec![Instruction::MEASURE(u32::MAX)];
+        assert_eq!(decode(&encode(&program, AngleEncoding::F64)).unwrap(), program);
+    }
+
+    #[test]
+    fn invalid_binaries() {
+        let bytes = encode(&[Instruction::CZ(0, 200), Instruction::MEASURE(200)], AngleEncoding::F64);
+
+        assert_eq!(decode(b"RX(0.5) 0").unwrap_err().message(), "Not a binary program, missing 'INQB' header");
+
+        let mut wrong_version = bytes.clone();
+        wrong_version[4] = 9;
+        assert_eq!(decode(&wrong_version).unwrap_err().message(), "Unsupported binary program version 9");
+
+        let mut corrupted = bytes.clone();
+        *corrupted.last_mut().unwrap() ^= 1;
+        assert_eq!(decode(&corrupted).unwrap_err().message(), "Checksum mismatch in binary program");
+
+        let truncated = &bytes[..bytes.len() - 1];
+        assert_eq!(decode(truncated).unwrap_err().message(), "Checksum mismatch in binary program");
+
+        // Header claims fewer qubits than the program uses
+        let mut small = bytes.clone();
+        small.splice(6..8, [0x05]);
+        assert_eq!(decode(&small).unwrap_err().message(), "Qubit index out of range for 5 qubit(s) at byte 12");
+    }
+
+    #[test]
+    fn checksum() {
+        assert_eq!(crc32(b"123456789"), 0xcbf43926);
+        assert_eq!(crc32(b""), 0);
+    }
+}
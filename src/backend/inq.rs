@@ -0,0 +1,32 @@
+use crate::instruction::Instruction;
+
+// Render program as .inq text, one instruction per line
+pub fn prog_to_string(program: &[Instruction]) -> String {
+    let mut output = String::new();
+
+    for instr in program {
+        output.push_str(&format!("{instr}\n"));
+    }
+    output.pop();
+
+    output
+}
+
+#[rustfmt::skip]
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn program_to_string() {
+        let program = [
+            Instruction::RX(0.45, 0),
+            Instruction::RZ(-1.0, 1),
+            Instruction::CZ(0, 1),
+            Instruction::MEASURE(0),
+        ];
+
+        assert_eq!(prog_to_string(&program), "RX(0.45) 0\nRZ(-1) 1\nCZ 0 1\nMEASURE 0");
+        assert_eq!(prog_to_string(&[]), "");
+    }
+}
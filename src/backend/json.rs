@@ -0,0 +1,371 @@
+use crate::error::{CompileError, Span};
+use crate::instruction::Instruction;
+use crate::json::{self, Json, Value};
+
+// Version of the JSON program format written by this module
+pub const FORMAT_VERSION: u32 = 1;
+
+// Instruction along with the source location it was parsed from, if known
+#[derive(Debug, PartialEq, Clone)]
+pub struct SpannedInstruction {
+    pub instruction: Instruction,
+    pub span: Option<Span>,
+}
+
+// Render program as a JSON document of the form
+//
+// {
+//   "version": 1,
+//   "instructions": [
+//     {"gate": "RX", "params": [0.45], "qubits": [0]},
+//     {"gate": "CZ", "params": [], "qubits": [0, 1]}
+//   ]
+// }
+pub fn prog_to_json(program: &[Instruction]) -> String {
+    let spanned: Vec<SpannedInstruction> = program
+        .iter()
+        .map(|instr| SpannedInstruction {
+            instruction: *instr,
+            span: None,
+        })
+        .collect();
+    spanned_to_json(&spanned)
+}
+
+// Render program as JSON, instructions with a known location carry a
+// "span" member, e.g. {"gate": "MEASURE", ..., "span": {"line": 3, "pos": 1, "len": 7}}
+pub fn spanned_to_json(program: &[SpannedInstruction]) -> String {
+    let mut output = format!(
+        "{{\n  \"version\": {},\n  \"instructions\": [",
+        FORMAT_VERSION
+    );
+
+    for (i, spanned) in program.iter().enumerate() {
+        let (gate, params, qubits) = match spanned.instruction {
+            Instruction::RX(f, q) => ("RX", vec![f], vec![q]),
+            Instruction::RZ(f, q) => ("RZ", vec![f], vec![q]),
+            Instruction::CZ(q1, q2) => ("CZ", vec![], vec![q1, q2]),
+            Instruction::MEASURE(q) => ("MEASURE", vec![], vec![q]),
+            Instruction::INVALID => panic!("invalid instruction"),
+        };
+        let params: Vec<String> = params.iter().map(|f| f.to_string()).collect();
+        let qubits: Vec<String> = qubits.iter().map(|q| q.to_string()).collect();
+
+        if i > 0 {
+            output.push(',');
+        }
+        output.push_str(&format!(
+            "\n    {{\"gate\": {}, \"params\": [{}], \"qubits\": [{}]",
+            json::quote(gate),
+            params.join(", "),
+            qubits.join(", ")
+        ));
+        if let Some(span) = spanned.span {
+            output.push_str(&format!(
+                ", \"span\": {{\"line\": {}, \"pos\": {}, \"len\": {}}}",
+                span.line, span.pos, span.len
+            ));
+        }
+        output.push('}');
+    }
+
+    if !program.is_empty() {
+        output.push_str("\n  ");
+    }
+    output.push_str("]\n}");
+    output
+}
+
+// Load a program from its JSON form, dropping source spans
+pub fn json_to_prog(source: &str) -> Result<Vec<Instruction>, CompileError> {
+    Ok(json_to_spanned(source)?
+        .into_iter()
+        .map(|spanned| spanned.instruction)
+        .collect())
+}
+
+// Load a program from its JSON form. Every malformed instruction is reported,
+// locations in the errors refer to the JSON text.
+pub fn json_to_spanned(source: &str) -> Result<Vec<SpannedInstruction>, CompileError> {
+    let document = json::parse(source)?;
+    if !matches!(document.value, Value::Object(_)) {
+        return Err(mismatch(&document, "the document", "an object"));
+    }
+
+    let version = member(&document, "version")?;
+    match version.value {
+        Value::Number(v) if v == FORMAT_VERSION as f64 => {}
+        Value::Number(_) => {
+            return Err(CompileError::semantic(
+                format!("Unsupported program format version at {}", version.span),
+                version.span,
+            )
+            .with_hint(format!("only version {} is supported", FORMAT_VERSION)))
+        }
+        _ => return Err(mismatch(version, "'version'", "a number")),
+    }
+
+    let instructions = member(&document, "instructions")?;
+    let items = match &instructions.value {
+        Value::Array(items) => items,
+        _ => return Err(mismatch(instructions, "'instructions'", "an array")),
+    };
+
+    let mut program = Vec::new();
+    let mut errors = Vec::new();
+    for item in items {
+        match read_instruction(item) {
+            Ok(spanned) => program.push(spanned),
+            Err(err) => errors.push(err),
+        }
+    }
+
+    if !errors.is_empty() {
+        return Err(CompileError::from_errors(errors));
+    }
+    Ok(program)
+}
+
+fn read_instruction(item: &Json) -> Result<SpannedInstruction, CompileError> {
+    if !matches!(item.value, Value::Object(_)) {
+        return Err(mismatch(item, "an instruction", "an object"));
+    }
+
+    let gate = member(item, "gate")?;
+    let name = match &gate.value {
+        Value::String(name) => name.as_str(),
+        _ => return Err(mismatch(gate, "'gate'", "a string")),
+    };
+    let (num_params, num_qubits) = match name {
+        "RX" | "RZ" => (1, 1),
+        "CZ" => (0, 2),
+        "MEASURE" => (0, 1),
+        _ => {
+            return Err(CompileError::semantic(
+                format!("Unknown gate '{}' at {}", name, gate.span),
+                gate.span,
+            )
+            .with_hint("expected RX, RZ, CZ or MEASURE"))
+        }
+    };
+
+    // Gates without parameters may leave out "params"
+    let params = match item.get("params") {
+        Some(params) => read_array(params, "'params'", num_params, name, "parameter")?
+            .iter()
+            .map(|param| match param.value {
+                Value::Number(f) => Ok(f),
+                _ => Err(mismatch(param, "a parameter", "a number")),
+            })
+            .collect::<Result<Vec<f64>, CompileError>>()?,
+        None if num_params == 0 => Vec::new(),
+        None => return Err(missing(item, "params")),
+    };
+
+    let qubits = read_array(
+        member(item, "qubits")?,
+        "'qubits'",
+        num_qubits,
+        name,
+        "qubit",
+    )?
+    .iter()
+    .map(read_qubit)
+    .collect::<Result<Vec<u32>, CompileError>>()?;
+
+    let instruction = match name {
+        "RX" => Instruction::RX(params[0], qubits[0]),
+        "RZ" => Instruction::RZ(params[0], qubits[0]),
+        "CZ" => {
+            if qubits[0] == qubits[1] {
+                return Err(CompileError::semantic(
+                    format!("CZ requires two distinct qbits at {}", item.span),
+                    item.span,
+                )
+                .with_hint("use two different qbits, e.g. [0, 1]"));
+            }
+            Instruction::CZ(qubits[0], qubits[1])
+        }
+        _ => Instruction::MEASURE(qubits[0]),
+    };
+
+    let span = match item.get("span") {
+        Some(span) => Some(read_span(span)?),
+        None => None,
+    };
+
+    Ok(SpannedInstruction { instruction, span })
+}
+
+fn read_array<'a>(
+    json: &'a Json,
+    what: &str,
+    len: usize,
+    gate: &str,
+    kind: &str,
+) -> Result<&'a [Json], CompileError> {
+    match &json.value {
+        Value::Array(items) if items.len() == len => Ok(items),
+        Value::Array(items) => Err(CompileError::semantic(
+            format!(
+                "Gate '{}' takes {} {}(s), got {} at {}",
+                gate,
+                len,
+                kind,
+                items.len(),
+                json.span
+            ),
+            json.span,
+        )),
+        _ => Err(mismatch(json, what, "an array")),
+    }
+}
+
+fn read_qubit(json: &Json) -> Result<u32, CompileError> {
+    match json.value {
+        Value::Number(q) if q >= 0.0 && q <= u32::MAX as f64 && q.fract() == 0.0 => Ok(q as u32),
+        _ => Err(CompileError::semantic(
+            format!("Invalid qubit index at {}", json.span),
+            json.span,
+        )
+        .with_hint("qubit indices are integers between 0 and 4294967295")),
+    }
+}
+
+fn read_span(json: &Json) -> Result<Span, CompileError> {
+    let field = |key: &str| -> Result<f64, CompileError> {
+        let value = member(json, key)?;
+        match value.value {
+            Value::Number(n) if n >= 0.0 && n <= u32::MAX as f64 && n.fract() == 0.0 => Ok(n),
+            _ => Err(mismatch(
+                value,
+                &format!("'{}'", key),
+                "an unsigned integer",
+            )),
+        }
+    };
+
+    if !matches!(json.value, Value::Object(_)) {
+        return Err(mismatch(json, "'span'", "an object"));
+    }
+    Ok(Span::new(
+        field("line")? as u32,
+        field("pos")? as u32,
+        field("len")? as usize,
+    ))
+}
+
+fn member<'a>(json: &'a Json, key: &str) -> Result<&'a Json, CompileError> {
+    json.get(key).ok_or_else(|| missing(json, key))
+}
+
+fn missing(json: &Json, key: &str) -> CompileError {
+    CompileError::semantic(
+        format!("Missing member '{}' at {}", key, json.span),
+        json.span,
+    )
+}
+
+fn mismatch(json: &Json, what: &str, expected: &str) -> CompileError {
+    CompileError::semantic(
+        format!(
+            "Expected {} to be {}, got {} at {}",
+            what,
+            expected,
+            json.type_name(),
+            json.span
+        ),
+        json.span,
+    )
+}
+
+#[rustfmt::skip]
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lexer;
+    use crate::parser;
+    use std::f64::consts::PI;
+
+    #[test]
+    fn program_to_json() {
+        let program = [
+            Instruction::RX(0.45, 0),
+            Instruction::RZ(-PI/2.0, 2),
+            Instruction::CZ(0, 2),
+            Instruction::MEASURE(2),
+        ];
+
+        let expected = "\
+{
+  \"version\": 1,
+  \"instructions\": [
+    {\"gate\": \"RX\", \"params\": [0.45], \"qubits\": [0]},
+    {\"gate\": \"RZ\", \"params\": [-1.5707963267948966], \"qubits\": [2]},
+    {\"gate\": \"CZ\", \"params\": [], \"qubits\": [0, 2]},
+    {\"gate\": \"MEASURE\", \"params\": [], \"qubits\": [2]}
+  ]
+}";
+        assert_eq!(prog_to_json(&program), expected);
+        assert_eq!(prog_to_json(&[]), "{\n  \"version\": 1,\n  \"instructions\": []\n}");
+    }
+
+    #[test]
+    fn round_trip() {
+        let tokens = lexer::tokenize_str("RZ(pi/3) 1\nCZ 1 0\nRX(-0.125) 0\nMEASURE 0").unwrap();
+        let program = parser::parse(&tokens).unwrap();
+        assert_eq!(json_to_prog(&prog_to_json(&program)).unwrap(), program);
+
+        // Spans recorded by the parser survive the trip
+        let tokens = lexer::tokenize_str("RX(0.45) 0\nCZ 0 1; MEASURE 1").unwrap();
+        let output = parser::parse_all(&tokens);
+        let spanned: Vec<SpannedInstruction> = output.program.into_iter()
+            .zip(output.spans)
+            .map(|(instruction, span)| SpannedInstruction { instruction, span: Some(span) })
+            .collect();
+
+        let json = spanned_to_json(&spanned);
+        assert!(json.contains("{\"gate\": \"MEASURE\", \"params\": [], \"qubits\": [1], \"span\": {\"line\": 2, \"pos\": 9, \"len\": 7}}"));
+        assert_eq!(json_to_spanned(&json).unwrap(), spanned);
+    }
+
+    #[test]
+    fn params_are_optional_without_parameters() {
+        let json = "{\"version\": 1, \"instructions\": [{\"gate\": \"CZ\", \"qubits\": [1, 0]}]}";
+        assert_eq!(json_to_prog(json).unwrap(), vec![Instruction::CZ(1, 0)]);
+    }
+
+    #[test]
+    fn invalid_programs() {
+        let err = json_to_prog("[]").unwrap_err();
+        assert_eq!(err.message(), "Expected the document to be an object, got an array at 1:1");
+
+        let err = json_to_prog("{\"version\": 2, \"instructions\": []}").unwrap_err();
+        assert_eq!(err.message(), "Unsupported program format version at 1:13");
+
+        let err = json_to_prog("{\"version\": 1}").unwrap_err();
+        assert_eq!(err.message(), "Missing member 'instructions' at 1:1");
+
+        let json = "\
+{\"version\": 1, \"instructions\": [
+  {\"gate\": \"RY\", \"params\": [1], \"qubits\": [0]},
+  {\"gate\": \"RX\", \"qubits\": [0]},
+  {\"gate\": \"RX\", \"params\": [\"pi\"], \"qubits\": [0]},
+  {\"gate\": \"CZ\", \"qubits\": [0]},
+  {\"gate\": \"CZ\", \"qubits\": [1, 1]},
+  {\"gate\": \"MEASURE\", \"qubits\": [-1]},
+  {\"gate\": \"MEASURE\", \"qubits\": [0], \"span\": {\"line\": 1}}
+]}";
+        let err = json_to_prog(json).unwrap_err();
+        let messages: Vec<&str> = err.errors().iter().map(|err| err.message()).collect();
+        assert_eq!(messages, vec![
+            "Unknown gate 'RY' at 2:12",
+            "Missing member 'params' at 3:3",
+            "Expected a parameter to be a number, got a string at 4:29",
+            "Gate 'CZ' takes 2 qubit(s), got 1 at 5:28",
+            "CZ requires two distinct qbits at 6:3",
+            "Invalid qubit index at 7:34",
+            "Missing member 'pos' at 8:46",
+        ]);
+    }
+}
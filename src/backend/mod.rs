@@ -0,0 +1,5 @@
+pub mod binary;
+pub mod inq;
+pub mod json;
+pub mod qasm;
+pub mod quil;
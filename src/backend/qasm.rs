@@ -0,0 +1,149 @@
+use crate::instruction::Instruction;
+use crate::simulate::num_qubits;
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum QasmVersion {
+    V2,
+    V3,
+}
+
+// Render program as OpenQASM text. All qubits live in a single register `q`
+// sized to the highest qubit index used, and qubit i is measured into bit
+// i of the classical register `c`. Fails for a program using qubit
+// u32::MAX, whose register of 2^32 qubits cannot be declared.
+pub fn prog_to_qasm(program: &[Instruction], version: QasmVersion) -> Result<String, String> {
+    let num_qubits = num_qubits(program);
+    if num_qubits > u32::MAX as u64 {
+        return Err(format!(
+            "Cannot write qubit {} as OpenQASM, registers hold at most {} qubits",
+            u32::MAX,
+            u32::MAX
+        ));
+    }
+    let measures = program
+        .iter()
+        .any(|instr| matches!(instr, Instruction::MEASURE(_)));
+
+    let mut output = String::new();
+    match version {
+        QasmVersion::V2 => {
+            output.push_str("OPENQASM 2.0;\ninclude \"qelib1.inc\";\n");
+            if num_qubits > 0 {
+                output.push_str(&format!("qreg q[{}];\n", num_qubits));
+            }
+            if measures {
+                output.push_str(&format!("creg c[{}];\n", num_qubits));
+            }
+        }
+        QasmVersion::V3 => {
+            output.push_str("OPENQASM 3.0;\ninclude \"stdgates.inc\";\n");
+            if num_qubits > 0 {
+                output.push_str(&format!("qubit[{}] q;\n", num_qubits));
+            }
+            if measures {
+                output.push_str(&format!("bit[{}] c;\n", num_qubits));
+            }
+        }
+    }
+
+    for instr in program {
+        let line = match *instr {
+            Instruction::RX(f, q) => format!("rx({}) q[{}];", f, q),
+            Instruction::RZ(f, q) => format!("rz({}) q[{}];", f, q),
+            Instruction::CZ(q1, q2) => format!("cz q[{}], q[{}];", q1, q2),
+            Instruction::MEASURE(q) => match version {
+                QasmVersion::V2 => format!("measure q[{}] -> c[{}];", q, q),
+                QasmVersion::V3 => format!("c[{}] = measure q[{}];", q, q),
+            },
+            Instruction::INVALID => panic!("invalid instruction"),
+        };
+        output.push_str(&line);
+        output.push('\n');
+    }
+    output.pop();
+
+    Ok(output)
+}
+
+#[rustfmt::skip]
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::frontend::parse_qasm;
+    use std::f64::consts::PI;
+
+    #[test]
+    fn program_to_qasm2() {
+        let program = [
+            Instruction::RX(0.45, 0),
+            Instruction::RZ(-PI/2.0, 2),
+            Instruction::CZ(0, 2),
+            Instruction::MEASURE(2),
+        ];
+
+        let expected = "\
+OPENQASM 2.0;
+include \"qelib1.inc\";
+qreg q[3];
+creg c[3];
+rx(0.45) q[0];
+rz(-1.5707963267948966) q[2];
+cz q[0], q[2];
+measure q[2] -> c[2];";
+        assert_eq!(prog_to_qasm(&program, QasmVersion::V2).unwrap(), expected);
+    }
+
+    #[test]
+    fn program_to_qasm3() {
+        let program = [
+            Instruction::RZ(-PI/2.0, 1),
+            Instruction::CZ(1, 0),
+            Instruction::MEASURE(1),
+        ];
+
+        let expected = "\
+OPENQASM 3.0;
+include \"stdgates.inc\";
+qubit[2] q;
+bit[2] c;
+rz(-1.5707963267948966) q[1];
+cz q[1], q[0];
+c[1] = measure q[1];";
+        assert_eq!(prog_to_qasm(&program, QasmVersion::V3).unwrap(), expected);
+
+        let expected = "OPENQASM 3.0;\ninclude \"stdgates.inc\";\nqubit[1] q;\nrz(1) q[0];";
+        assert_eq!(prog_to_qasm(&[Instruction::RZ(1.0, 0)], QasmVersion::V3).unwrap(), expected);
+    }
+
+    #[test]
+    fn round_trip_through_frontend() {
+        let program = vec![
+            Instruction::RX(PI/3.0, 4),
+            Instruction::CZ(4, 0),
+            Instruction::RZ(-0.125, 0),
+            Instruction::MEASURE(0),
+            Instruction::MEASURE(4),
+        ];
+
+        for version in [QasmVersion::V2, QasmVersion::V3] {
+            let qasm = prog_to_qasm(&program, version).unwrap();
+            assert_eq!(parse_qasm(&qasm).unwrap(), program);
+        }
+    }
+
+    #[test]
+    fn round_trip_highest_qubit() {
+        // The largest register the frontends accept
+        let program = vec![Instruction::RZ(0.5, u32::MAX - 1), Instruction::MEASURE(u32::MAX - 1)];
+        for version in [QasmVersion::V2, QasmVersion::V3] {
+            let qasm = prog_to_qasm(&program, version).unwrap();
+            assert!(qasm.contains("[4294967295]"));
+            assert_eq!(parse_qasm(&qasm).unwrap(), program);
+        }
+
+        for version in [QasmVersion::V2, QasmVersion::V3] {
+            let err = prog_to_qasm(&[Instruction::MEASURE(u32::MAX)], version).unwrap_err();
+            assert_eq!(err, "Cannot write qubit 4294967295 as OpenQASM, registers hold at most 4294967295 qubits");
+        }
+    }
+}
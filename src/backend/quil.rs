@@ -0,0 +1,73 @@
+use crate::instruction::Instruction;
+
+// Render program as Quil. Measured qubits are read out into the `ro`
+// register in ascending qubit order, e.g. measuring qubits 3 and 5 declares
+// `ro BIT[2]` with qubit 3 in ro[0] and qubit 5 in ro[1].
+pub fn prog_to_quil(program: &[Instruction]) -> String {
+    let mut measured: Vec<u32> = program
+        .iter()
+        .filter_map(|instr| match instr {
+            Instruction::MEASURE(q) => Some(*q),
+            _ => None,
+        })
+        .collect();
+    measured.sort_unstable();
+    measured.dedup();
+
+    let mut output = String::new();
+    if !measured.is_empty() {
+        output.push_str(&format!("DECLARE ro BIT[{}]\n", measured.len()));
+    }
+
+    for instr in program {
+        let line = match *instr {
+            Instruction::RX(f, q) => format!("RX({}) {}", f, q),
+            Instruction::RZ(f, q) => format!("RZ({}) {}", f, q),
+            Instruction::CZ(q1, q2) => format!("CZ {} {}", q1, q2),
+            Instruction::MEASURE(q) => {
+                let bit = measured.binary_search(&q).unwrap();
+                format!("MEASURE {} ro[{}]", q, bit)
+            }
+            Instruction::INVALID => panic!("invalid instruction"),
+        };
+        output.push_str(&line);
+        output.push('\n');
+    }
+    output.pop();
+
+    output
+}
+
+#[rustfmt::skip]
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn program_to_quil() {
+        let program = [
+            Instruction::RX(0.45, 3),
+            Instruction::RZ(-1.0, 5),
+            Instruction::CZ(3, 5),
+            Instruction::MEASURE(5),
+            Instruction::MEASURE(3),
+            Instruction::MEASURE(5),
+        ];
+
+        let expected = "\
+DECLARE ro BIT[2]
+RX(0.45) 3
+RZ(-1) 5
+CZ 3 5
+MEASURE 5 ro[1]
+MEASURE 3 ro[0]
+MEASURE 5 ro[1]";
+        assert_eq!(prog_to_quil(&program), expected);
+    }
+
+    #[test]
+    fn program_without_measurements() {
+        assert_eq!(prog_to_quil(&[Instruction::CZ(0, 1)]), "CZ 0 1");
+        assert_eq!(prog_to_quil(&[]), "");
+    }
+}
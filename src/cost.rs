@@ -0,0 +1,143 @@
+use std::collections::BTreeMap;
+
+use crate::instruction::Instruction;
+
+// Error rates assumed by the estimated error model
+pub const SINGLE_QUBIT_ERROR: f64 = 1e-4;
+pub const TWO_QUBIT_ERROR: f64 = 1e-2;
+pub const MEASURE_ERROR: f64 = 1e-2;
+
+pub type CostFn = fn(&[Instruction]) -> f64;
+
+// Named cost function, lower is better. The pipeline keeps optimizing while
+// the cost of the program decreases.
+#[derive(Debug, Clone, Copy)]
+pub struct CostModel {
+    pub name: &'static str,
+    pub func: CostFn,
+}
+
+impl Default for CostModel {
+    fn default() -> CostModel {
+        CostModel::gate_count()
+    }
+}
+
+impl CostModel {
+    pub fn new(name: &'static str, func: CostFn) -> CostModel {
+        CostModel { name, func }
+    }
+
+    pub fn gate_count() -> CostModel {
+        CostModel::new("gate count", gate_count)
+    }
+
+    pub fn two_qubit_count() -> CostModel {
+        CostModel::new("two-qubit count", two_qubit_count)
+    }
+
+    pub fn depth() -> CostModel {
+        CostModel::new("depth", depth)
+    }
+
+    pub fn estimated_error() -> CostModel {
+        CostModel::new("estimated error", estimated_error)
+    }
+
+    pub fn cost(&self, program: &[Instruction]) -> f64 {
+        (self.func)(program)
+    }
+}
+
+pub fn gate_count(program: &[Instruction]) -> f64 {
+    program.len() as f64
+}
+
+pub fn two_qubit_count(program: &[Instruction]) -> f64 {
+    program
+        .iter()
+        .filter(|instr| matches!(instr, Instruction::CZ(..)))
+        .count() as f64
+}
+
+// Length of the longest chain of instructions sharing qubits
+pub fn depth(program: &[Instruction]) -> f64 {
+    // Depth reached so far on each qubit, keyed so sparse indices stay cheap
+    let mut wire_depth: BTreeMap<u32, u32> = BTreeMap::new();
+    let mut depth = 0;
+    for instr in program {
+        let qubits = instr.qubits();
+        if qubits.is_empty() {
+            continue;
+        }
+
+        let layer = qubits
+            .iter()
+            .map(|q| wire_depth.get(q).copied().unwrap_or(0))
+            .max()
+            .unwrap_or(0)
+            + 1;
+        for q in qubits {
+            wire_depth.insert(q, layer);
+        }
+        depth = depth.max(layer);
+    }
+
+    depth as f64
+}
+
+// Probability that at least one instruction fails, assuming independent
+// errors at the rates above
+pub fn estimated_error(program: &[Instruction]) -> f64 {
+    let success: f64 = program
+        .iter()
+        .map(|instr| match instr {
+            Instruction::RX(..) | Instruction::RZ(..) => 1.0 - SINGLE_QUBIT_ERROR,
+            Instruction::CZ(..) => 1.0 - TWO_QUBIT_ERROR,
+            Instruction::MEASURE(..) => 1.0 - MEASURE_ERROR,
+            Instruction::INVALID => 1.0,
+        })
+        .product();
+
+    1.0 - success
+}
+
+#[rustfmt::skip]
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn program_costs() {
+        let program = [
+            Instruction::RX(0.45, 0),
+            Instruction::RZ(0.3, 2),
+            Instruction::CZ(0, 1),
+            Instruction::CZ(1, 2),
+            Instruction::MEASURE(0),
+        ];
+
+        assert_eq!(CostModel::gate_count().cost(&program), 5.0);
+        assert_eq!(CostModel::two_qubit_count().cost(&program), 2.0);
+        assert_eq!(CostModel::depth().cost(&program), 3.0);
+
+        let expected = 1.0 - (1.0 - SINGLE_QUBIT_ERROR).powi(2) * (1.0 - TWO_QUBIT_ERROR).powi(2) * (1.0 - MEASURE_ERROR);
+        assert!((CostModel::estimated_error().cost(&program) - expected).abs() < 1e-15);
+
+        for model in [CostModel::gate_count(), CostModel::two_qubit_count(), CostModel::depth(), CostModel::estimated_error()] {
+            assert_eq!(model.cost(&[]), 0.0);
+        }
+    }
+
+    #[test]
+    fn depth_counts_parallel_gates_once() {
+        let parallel = [Instruction::RX(0.45, 0), Instruction::RX(0.45, 1), Instruction::RX(0.45, 2)];
+        assert_eq!(depth(&parallel), 1.0);
+
+        let serial = [Instruction::RX(0.45, 0), Instruction::RZ(0.45, 0), Instruction::MEASURE(0)];
+        assert_eq!(depth(&serial), 3.0);
+
+        let sparse = [Instruction::RX(0.45, 4000000000), Instruction::CZ(u32::MAX, 4000000000)];
+        assert_eq!(depth(&sparse), 2.0);
+    }
+}
@@ -0,0 +1,428 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::instruction::Instruction;
+
+pub type NodeId = usize;
+
+#[derive(Debug, Clone)]
+struct Node {
+    instr: Instruction,
+    // Neighbouring nodes on the wire of each operand, parallel to
+    // `instr.qubits()`
+    prev: Vec<Option<NodeId>>,
+    next: Vec<Option<NodeId>>,
+}
+
+// Circuit as a directed acyclic graph. Every qubit is a wire threading the
+// instructions that act on it in order, so the predecessors of a node are
+// exactly the instructions it has to wait for. Node ids are never reused and
+// follow program order for a DAG built with `from_program`.
+#[derive(Debug, Clone)]
+pub struct CircuitDag {
+    nodes: Vec<Option<Node>>,
+    // First and last node on each non-empty wire, keyed by qubit so sparse
+    // qubit indices stay cheap
+    first: BTreeMap<u32, NodeId>,
+    last: BTreeMap<u32, NodeId>,
+    len: usize,
+}
+
+impl CircuitDag {
+    pub fn new() -> CircuitDag {
+        CircuitDag {
+            nodes: Vec::new(),
+            first: BTreeMap::new(),
+            last: BTreeMap::new(),
+            len: 0,
+        }
+    }
+
+    pub fn from_program(program: &[Instruction]) -> CircuitDag {
+        let mut dag = CircuitDag::new();
+        for instr in program {
+            dag.push(*instr);
+        }
+        dag
+    }
+
+    // Instructions in topological order
+    pub fn to_program(&self) -> Vec<Instruction> {
+        self.topological_order()
+            .into_iter()
+            .map(|id| *self.instruction(id))
+            .collect()
+    }
+
+    // Append an instruction at the end of its wires
+    pub fn push(&mut self, instr: Instruction) -> NodeId {
+        let id = self.nodes.len();
+        let qubits = instr.qubits();
+
+        let mut prev = Vec::with_capacity(qubits.len());
+        for &q in &qubits {
+            let tail = self.last.insert(q, id);
+            match tail {
+                Some(tail) => {
+                    let slot = self.slot(tail, q);
+                    self.node_mut(tail).next[slot] = Some(id);
+                }
+                None => {
+                    self.first.insert(q, id);
+                }
+            }
+            prev.push(tail);
+        }
+
+        self.nodes.push(Some(Node {
+            instr,
+            prev,
+            next: vec![None; qubits.len()],
+        }));
+        self.len += 1;
+        id
+    }
+
+    // Qubits with at least one node on their wire, in ascending order
+    pub fn qubits(&self) -> Vec<u32> {
+        self.first.keys().copied().collect()
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn contains(&self, id: NodeId) -> bool {
+        matches!(self.nodes.get(id), Some(Some(_)))
+    }
+
+    pub fn instruction(&self, id: NodeId) -> &Instruction {
+        &self.node(id).instr
+    }
+
+    // Replace the instruction of a node with one acting on the same qubits
+    pub fn set_instruction(&mut self, id: NodeId, instr: Instruction) {
+        assert_eq!(
+            self.node(id).instr.qubits(),
+            instr.qubits(),
+            "replacement must act on the same qubits"
+        );
+        self.node_mut(id).instr = instr;
+    }
+
+    // Previous node on the wire of `qubit`, which `id` must act on
+    pub fn predecessor(&self, id: NodeId, qubit: u32) -> Option<NodeId> {
+        self.node(id).prev[self.slot(id, qubit)]
+    }
+
+    // Next node on the wire of `qubit`, which `id` must act on
+    pub fn successor(&self, id: NodeId, qubit: u32) -> Option<NodeId> {
+        self.node(id).next[self.slot(id, qubit)]
+    }
+
+    // Distinct nodes directly before `id` on any of its wires
+    pub fn predecessors(&self, id: NodeId) -> Vec<NodeId> {
+        let mut prev: Vec<NodeId> = self.node(id).prev.iter().flatten().copied().collect();
+        prev.dedup();
+        prev
+    }
+
+    // Distinct nodes directly after `id` on any of its wires
+    pub fn successors(&self, id: NodeId) -> Vec<NodeId> {
+        let mut next: Vec<NodeId> = self.node(id).next.iter().flatten().copied().collect();
+        next.dedup();
+        next
+    }
+
+    // Nodes on the wire of `qubit`, in order
+    pub fn wire(&self, qubit: u32) -> Vec<NodeId> {
+        let mut nodes = Vec::new();
+        let mut current = self.first.get(&qubit).copied();
+        while let Some(id) = current {
+            nodes.push(id);
+            current = self.successor(id, qubit);
+        }
+        nodes
+    }
+
+    // Remove a node, joining its neighbours on every wire
+    pub fn remove(&mut self, id: NodeId) -> Instruction {
+        let node = self.nodes[id].take().expect("node was already removed");
+        for (slot, q) in node.instr.qubits().into_iter().enumerate() {
+            self.link(node.prev[slot], q, node.next[slot]);
+        }
+        self.len -= 1;
+        node.instr
+    }
+
+    // Move a single-qubit node directly before `target` on its wire. Both
+    // nodes must be on the same wire, and since the moved node has no other
+    // wire this can never introduce a cycle.
+    pub fn move_before(&mut self, id: NodeId, target: NodeId) {
+        let qubits = self.node(id).instr.qubits();
+        assert_eq!(qubits.len(), 1, "only single-qubit nodes can be moved");
+        let q = qubits[0];
+        if id == target || self.successor(id, q) == Some(target) {
+            return;
+        }
+
+        let (prev, next) = (self.node(id).prev[0], self.node(id).next[0]);
+        self.link(prev, q, next);
+
+        let before = self.predecessor(target, q);
+        self.link(before, q, Some(id));
+        self.link(Some(id), q, Some(target));
+    }
+
+    // Move `id` directly after `target` on the wire of `qubit`, keeping its
+    // place on its other wires. Returns false and leaves the DAG unchanged if
+    // the move would create a cycle.
+    pub fn move_after(&mut self, id: NodeId, qubit: u32, target: NodeId) -> bool {
+        if id == target || self.predecessor(id, qubit) == Some(target) {
+            return true;
+        }
+
+        let slot = self.slot(id, qubit);
+        let (prev, next) = (self.node(id).prev[slot], self.node(id).next[slot]);
+        let after = self.successor(target, qubit);
+
+        self.link(prev, qubit, next);
+        self.link(Some(target), qubit, Some(id));
+        self.link(Some(id), qubit, after);
+
+        // Only the edges of `id` changed, so any new cycle runs through it
+        if self.reaches(id, id) {
+            self.link(Some(target), qubit, after);
+            self.link(prev, qubit, Some(id));
+            self.link(Some(id), qubit, next);
+            return false;
+        }
+        true
+    }
+
+    // Node ids in topological order. Among the nodes that are ready, the one
+    // with the smallest id comes first, so a DAG built from a program gives
+    // back that program unchanged.
+    pub fn topological_order(&self) -> Vec<NodeId> {
+        // Number of predecessors of each node not yet in the order
+        let mut waiting = vec![0usize; self.nodes.len()];
+        let mut ready: BTreeSet<NodeId> = BTreeSet::new();
+        for id in (0..self.nodes.len()).filter(|&id| self.contains(id)) {
+            waiting[id] = self.predecessors(id).len();
+            if waiting[id] == 0 {
+                ready.insert(id);
+            }
+        }
+
+        let mut order = Vec::with_capacity(self.len);
+        while let Some(id) = ready.pop_first() {
+            order.push(id);
+            for next in self.successors(id) {
+                waiting[next] -= 1;
+                if waiting[next] == 0 {
+                    ready.insert(next);
+                }
+            }
+        }
+
+        order
+    }
+
+    // True if `to` can be reached from `from` along at least one edge
+    fn reaches(&self, from: NodeId, to: NodeId) -> bool {
+        let mut seen = vec![false; self.nodes.len()];
+        let mut stack = self.successors(from);
+        while let Some(id) = stack.pop() {
+            if id == to {
+                return true;
+            }
+            if !seen[id] {
+                seen[id] = true;
+                stack.extend(self.successors(id));
+            }
+        }
+        false
+    }
+
+    // Make `next` follow `prev` on the wire of `qubit`, either may be the
+    // start or end of the wire
+    fn link(&mut self, prev: Option<NodeId>, qubit: u32, next: Option<NodeId>) {
+        match prev {
+            Some(prev) => {
+                let slot = self.slot(prev, qubit);
+                self.node_mut(prev).next[slot] = next;
+            }
+            None => set_end(&mut self.first, qubit, next),
+        }
+        match next {
+            Some(next) => {
+                let slot = self.slot(next, qubit);
+                self.node_mut(next).prev[slot] = prev;
+            }
+            None => set_end(&mut self.last, qubit, prev),
+        }
+    }
+
+    fn node(&self, id: NodeId) -> &Node {
+        self.nodes[id].as_ref().expect("node was removed")
+    }
+
+    fn node_mut(&mut self, id: NodeId) -> &mut Node {
+        self.nodes[id].as_mut().expect("node was removed")
+    }
+
+    // Position of `qubit` among the operands of node `id`
+    fn slot(&self, id: NodeId, qubit: u32) -> usize {
+        self.node(id)
+            .instr
+            .qubits()
+            .iter()
+            .position(|&q| q == qubit)
+            .expect("node does not act on qubit")
+    }
+}
+
+// Update the first or last node of a wire, forgetting wires that are empty
+fn set_end(ends: &mut BTreeMap<u32, NodeId>, qubit: u32, id: Option<NodeId>) {
+    match id {
+        Some(id) => ends.insert(qubit, id),
+        None => ends.remove(&qubit),
+    };
+}
+
+impl Default for CircuitDag {
+    fn default() -> CircuitDag {
+        CircuitDag::new()
+    }
+}
+
+#[rustfmt::skip]
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::equivalence::testing::{random_program, Rng};
+
+    #[test]
+    fn wires_and_neighbours() {
+        let dag = CircuitDag::from_program(&[
+            Instruction::RX(0.5, 0),
+            Instruction::CZ(0, 1),
+            Instruction::RZ(0.3, 5),
+            Instruction::CZ(1, 0),
+            Instruction::MEASURE(1),
+        ]);
+
+        assert_eq!(dag.len(), 5);
+        assert_eq!(dag.qubits(), vec![0, 1, 5]);
+        assert_eq!(dag.wire(0), vec![0, 1, 3]);
+        assert_eq!(dag.wire(1), vec![1, 3, 4]);
+        assert_eq!(dag.wire(5), vec![2]);
+        assert_eq!(dag.wire(3), Vec::<NodeId>::new());
+
+        assert_eq!(dag.predecessor(1, 0), Some(0));
+        assert_eq!(dag.predecessor(1, 1), None);
+        assert_eq!(dag.successor(1, 0), Some(3));
+        assert_eq!(dag.successor(1, 1), Some(3));
+        assert_eq!(dag.successors(1), vec![3]);
+        assert_eq!(dag.predecessors(3), vec![1]);
+        assert_eq!(dag.predecessors(2), Vec::<NodeId>::new());
+    }
+
+    #[test]
+    fn program_round_trip() {
+        for seed in 0..20 {
+            let program = random_program(&mut Rng::new(seed + 1), 4, 30);
+            assert_eq!(CircuitDag::from_program(&program).to_program(), program);
+        }
+        assert_eq!(CircuitDag::new().to_program(), vec![]);
+    }
+
+    #[test]
+    fn remove_nodes() {
+        // CZ pair separated only by a gate on an unrelated qubit
+        let mut dag = CircuitDag::from_program(&[
+            Instruction::RX(0.5, 0),
+            Instruction::CZ(0, 1),
+            Instruction::RZ(0.3, 5),
+            Instruction::CZ(1, 0),
+            Instruction::MEASURE(1),
+        ]);
+
+        assert_eq!(dag.successor(1, 0), dag.successor(1, 1));
+        assert_eq!(dag.remove(1), Instruction::CZ(0, 1));
+        assert_eq!(dag.remove(3), Instruction::CZ(1, 0));
+
+        assert!(!dag.contains(1));
+        assert_eq!(dag.len(), 3);
+        assert_eq!(dag.successor(0, 0), None);
+        assert_eq!(dag.predecessor(4, 1), None);
+        assert_eq!(dag.to_program(), vec![
+            Instruction::RX(0.5, 0),
+            Instruction::RZ(0.3, 5),
+            Instruction::MEASURE(1),
+        ]);
+
+        // Emptied wires are forgotten
+        dag.remove(2);
+        assert_eq!(dag.qubits(), vec![0, 1]);
+    }
+
+    #[test]
+    fn sparse_qubit_indices() {
+        let mut dag = CircuitDag::from_program(&[
+            Instruction::RZ(0.5, 4_000_000_000),
+            Instruction::CZ(u32::MAX, 4_000_000_000),
+            Instruction::RZ(0.5, 4_000_000_000),
+        ]);
+
+        assert_eq!(dag.qubits(), vec![4_000_000_000, u32::MAX]);
+        assert_eq!(dag.wire(4_000_000_000), vec![0, 1, 2]);
+        assert_eq!(dag.wire(u32::MAX), vec![1]);
+
+        dag.remove(1);
+        assert_eq!(dag.successor(0, 4_000_000_000), Some(2));
+        assert_eq!(dag.qubits(), vec![4_000_000_000]);
+    }
+
+    #[test]
+    fn move_nodes() {
+        let mut dag = CircuitDag::from_program(&[
+            Instruction::CZ(0, 1),
+            Instruction::RZ(0.5, 0),
+            Instruction::CZ(0, 2),
+            Instruction::CZ(1, 2),
+            Instruction::CZ(0, 1),
+        ]);
+
+        dag.move_before(1, 0);
+        assert_eq!(dag.wire(0), vec![1, 0, 2, 4]);
+        assert_eq!(dag.topological_order(), vec![1, 0, 2, 3, 4]);
+
+        // Moving the last CZ 0 1 ahead of CZ 0 2 would put it before CZ 1 2,
+        // which itself has to wait for CZ 0 2
+        assert!(!dag.move_after(4, 0, 0));
+        assert_eq!(dag.wire(0), vec![1, 0, 2, 4]);
+
+        assert!(dag.move_after(0, 0, 2));
+        assert_eq!(dag.wire(0), vec![1, 2, 0, 4]);
+        assert_eq!(dag.wire(1), vec![0, 3, 4]);
+        assert_eq!(dag.to_program(), vec![
+            Instruction::RZ(0.5, 0),
+            Instruction::CZ(0, 2),
+            Instruction::CZ(0, 1),
+            Instruction::CZ(1, 2),
+            Instruction::CZ(0, 1),
+        ]);
+    }
+
+    #[test]
+    fn replace_instruction() {
+        let mut dag = CircuitDag::from_program(&[Instruction::RX(0.5, 0), Instruction::MEASURE(0)]);
+        dag.set_instruction(0, Instruction::RZ(1.0, 0));
+        assert_eq!(dag.instruction(0), &Instruction::RZ(1.0, 0));
+        assert_eq!(dag.to_program(), vec![Instruction::RZ(1.0, 0), Instruction::MEASURE(0)]);
+    }
+}
@@ -0,0 +1,155 @@
+use crate::error::CompileError;
+
+const RED: &str = "\x1b[1;31m";
+const BLUE: &str = "\x1b[1;34m";
+const CYAN: &str = "\x1b[1;36m";
+const BOLD: &str = "\x1b[1m";
+const RESET: &str = "\x1b[0m";
+
+// Render errors rustc-style with the offending source line and a caret
+// underline beneath the span:
+//
+// error[E0200]: Unexpected token at 1:4, expected '('
+//  --> prog.inq:1:4
+//   |
+// 1 | RZ 0.5) 0
+//   |    ^^^
+//   = hint: expected '(' after RZ
+//
+// `filename` is only used for the location line. ANSI colors are added when
+// `color` is set.
+pub fn render(err: &CompileError, source: &str, filename: Option<&str>, color: bool) -> String {
+    let paint = |style: &str, text: &str| -> String {
+        if color {
+            format!("{}{}{}", style, text, RESET)
+        } else {
+            text.to_string()
+        }
+    };
+
+    let mut output = String::new();
+    for (i, err) in err.errors().iter().enumerate() {
+        if i > 0 {
+            output.push('\n');
+        }
+
+        // Errors without a location keep their stage prefix for context
+        let message = match err.span() {
+            Some(_) => err.message().to_string(),
+            None => err.to_string(),
+        };
+        output.push_str(&paint(RED, &format!("error[{}]", err.code())));
+        output.push_str(&paint(BOLD, &format!(": {}", message)));
+        output.push('\n');
+
+        if let Some(span) = err.span() {
+            let line_no = span.line.to_string();
+            let gutter = " ".repeat(line_no.len());
+            let location = match filename {
+                Some(name) => format!("{}:{}:{}", name, span.line, span.pos),
+                None => format!("{}:{}", span.line, span.pos),
+            };
+
+            output.push_str(&format!("{}{} {}\n", gutter, paint(BLUE, "-->"), location));
+
+            let source_line = (span.line as usize)
+                .checked_sub(1)
+                .and_then(|i| source.lines().nth(i));
+            if let Some(line) = source_line {
+                // Caret position is counted in characters, like Token::pos
+                let padding = " ".repeat((span.pos as usize).saturating_sub(1));
+                let carets = "^".repeat(span.len.max(1));
+
+                output.push_str(&format!("{} {}\n", gutter, paint(BLUE, "|")));
+                output.push_str(&format!(
+                    "{} {} {}\n",
+                    paint(BLUE, &line_no),
+                    paint(BLUE, "|"),
+                    line
+                ));
+                output.push_str(&format!(
+                    "{} {} {}{}\n",
+                    gutter,
+                    paint(BLUE, "|"),
+                    padding,
+                    paint(RED, &carets)
+                ));
+            }
+
+            if let Some(hint) = err.hint() {
+                output.push_str(&format!(
+                    "{} {} {} {}\n",
+                    gutter,
+                    paint(BLUE, "="),
+                    paint(CYAN, "hint:"),
+                    hint
+                ));
+            }
+        }
+    }
+
+    output
+}
+
+#[rustfmt::skip]
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pipeline::Pipeline;
+
+    #[test]
+    fn render_syntax_error() {
+        let source = "RX(0.45) 0\nRZ 0.5) 0\n";
+        let err = Pipeline::default().compile_str(source).unwrap_err();
+
+        let expected = concat!(
+            "error[E0200]: Unexpected token at 2:4, expected '('\n",
+            " --> prog.inq:2:4\n",
+            "  |\n",
+            "2 | RZ 0.5) 0\n",
+            "  |    ^^^\n",
+            "  = hint: expected '(' after RZ\n",
+        );
+        assert_eq!(render(&err, source, Some("prog.inq"), false), expected);
+    }
+
+    #[test]
+    fn render_multiple_errors() {
+        let source = "RX(0.45) 0\nrz(1.0) 0\n";
+        let err = Pipeline::default().compile_str(source).unwrap_err();
+
+        let expected = concat!(
+            "error[E0100]: Undefined token at 2:1 \"rz\"\n",
+            " --> 2:1\n",
+            "  |\n",
+            "2 | rz(1.0) 0\n",
+            "  | ^^\n",
+            "  = hint: instructions are RX, RZ, CZ and MEASURE, in upper case\n",
+        );
+        assert_eq!(render(&err, source, None, false), expected);
+
+        let source = "CZ 3 3\n\n\n\n\n\n\n\n\nMEASURE\n";
+        let err = Pipeline::default().compile_str(source).unwrap_err();
+        let rendered = render(&err, source, None, false);
+
+        assert!(rendered.starts_with("error[E0300]: CZ requires two distinct qbits at 1:1\n --> 1:1\n"));
+        assert!(rendered.contains("\nerror[E0200]: Invalid or missing token sequence after instruction at 10:1\n"));
+        assert!(rendered.contains("\n10 | MEASURE\n   | ^^^^^^^\n   = hint: usage: MEASURE qbit\n"));
+    }
+
+    #[test]
+    fn render_without_span() {
+        let err = CompileError::pass("deadcode", "failed");
+        assert_eq!(render(&err, "", None, false), "error[E0400]: deadcode: failed\n");
+    }
+
+    #[test]
+    fn render_with_color() {
+        let source = "RZ 0.5) 0";
+        let err = Pipeline::default().compile_str(source).unwrap_err();
+        let rendered = render(&err, source, None, true);
+
+        assert!(rendered.starts_with("\x1b[1;31merror[E0200]\x1b[0m\x1b[1m: Unexpected token"));
+        assert!(rendered.contains("\x1b[1;31m^^^\x1b[0m"));
+    }
+}
@@ -0,0 +1,349 @@
+use std::fmt;
+
+use crate::complex::Complex;
+use crate::instruction::Instruction;
+use crate::simulate::StateVector;
+
+// A full unitary is 4^n complex values, keep it small
+pub const MAX_UNITARY_QUBITS: u32 = 10;
+
+// Dense unitary matrix, row-major. Rows and columns are indexed by basis
+// states using the same little-endian qubit ordering as the simulator.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Unitary {
+    num_qubits: u32,
+    dim: usize,
+    data: Vec<Complex>,
+}
+
+impl Unitary {
+    pub fn num_qubits(&self) -> u32 {
+        self.num_qubits
+    }
+
+    pub fn dim(&self) -> usize {
+        self.dim
+    }
+
+    pub fn get(&self, row: usize, col: usize) -> Complex {
+        self.data[row * self.dim + col]
+    }
+}
+
+// First matrix element that differs once global phase has been removed
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct Divergence {
+    pub row: usize,
+    pub col: usize,
+    pub expected: Complex,
+    pub actual: Complex,
+}
+
+impl fmt::Display for Divergence {
+    fn fmt(&self, ftr: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            ftr,
+            "unitaries diverge at ({}, {}): expected {}, got {} (difference {:e})",
+            self.row,
+            self.col,
+            self.expected,
+            self.actual,
+            (self.expected - self.actual).abs()
+        )
+    }
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Equivalence {
+    Equivalent,
+    Diverges(Divergence),
+}
+
+impl Equivalence {
+    pub fn is_equivalent(&self) -> bool {
+        *self == Equivalence::Equivalent
+    }
+}
+
+// Instructions up to (not including) the first MEASURE
+pub fn measurement_free_prefix(program: &[Instruction]) -> &[Instruction] {
+    let end = program
+        .iter()
+        .position(|instr| matches!(instr, Instruction::MEASURE(_)))
+        .unwrap_or(program.len());
+
+    &program[..end]
+}
+
+// Compute the unitary of a measurement-free program over `num_qubits`
+// qubits. Column j is the program applied to basis state |j>.
+pub fn unitary(program: &[Instruction], num_qubits: u32) -> Result<Unitary, String> {
+    if num_qubits > MAX_UNITARY_QUBITS {
+        return Err(format!(
+            "Cannot build unitary over {} qubits, maximum is {}",
+            num_qubits, MAX_UNITARY_QUBITS
+        ));
+    }
+
+    let dim = 1usize << num_qubits;
+    let mut data = vec![Complex::ZERO; dim * dim];
+
+    for col in 0..dim {
+        let mut state = StateVector::basis(num_qubits, col)?;
+        for instr in program {
+            if let Instruction::MEASURE(q) = instr {
+                return Err(format!(
+                    "Cannot build unitary of program containing MEASURE {}",
+                    q
+                ));
+            }
+            state.apply(instr)?;
+        }
+
+        for (row, amp) in state.amplitudes().iter().enumerate() {
+            data[row * dim + col] = *amp;
+        }
+    }
+
+    Ok(Unitary {
+        num_qubits,
+        dim,
+        data,
+    })
+}
+
+// Compare the measurement-free prefixes of two programs up to global phase.
+// Qubit indices used by either program are compacted so that sparse indices
+// (e.g. qubit 77) do not blow up the matrix size.
+pub fn check_equivalence(
+    expected: &[Instruction],
+    actual: &[Instruction],
+    tolerance: f64,
+) -> Result<Equivalence, String> {
+    let expected = measurement_free_prefix(expected);
+    let actual = measurement_free_prefix(actual);
+
+    let mut qubits: Vec<u32> = expected
+        .iter()
+        .chain(actual.iter())
+        .flat_map(Instruction::qubits)
+        .collect();
+    qubits.sort_unstable();
+    qubits.dedup();
+
+    let num_qubits = qubits.len() as u32;
+    let u_expected = unitary(&remap(expected, &qubits), num_qubits)?;
+    let u_actual = unitary(&remap(actual, &qubits), num_qubits)?;
+
+    Ok(compare_unitaries(&u_expected, &u_actual, tolerance))
+}
+
+// Compare two unitaries up to global phase. The phase is taken from the
+// first element of `expected` with non-negligible magnitude.
+pub fn compare_unitaries(expected: &Unitary, actual: &Unitary, tolerance: f64) -> Equivalence {
+    let mut phase: Option<Complex> = None;
+
+    for row in 0..expected.dim {
+        for col in 0..expected.dim {
+            let e = expected.get(row, col);
+            let a = actual.get(row, col);
+
+            if phase.is_none() && e.abs() > tolerance && a.abs() > tolerance {
+                // Unit phase that rotates actual onto expected
+                let ratio = e * a.conj();
+                phase = Some(ratio.scale(1.0 / ratio.abs()));
+            }
+
+            let a_rotated = match phase {
+                Some(p) => a * p,
+                None => a,
+            };
+            if !e.approx_eq(a_rotated, tolerance) {
+                return Equivalence::Diverges(Divergence {
+                    row,
+                    col,
+                    expected: e,
+                    actual: a_rotated,
+                });
+            }
+        }
+    }
+
+    Equivalence::Equivalent
+}
+
+// Map each qubit to its position in the sorted `qubits` list
+fn remap(program: &[Instruction], qubits: &[u32]) -> Vec<Instruction> {
+    let index = |q: u32| qubits.binary_search(&q).unwrap() as u32;
+
+    program
+        .iter()
+        .map(|instr| match *instr {
+            Instruction::RX(f, q) => Instruction::RX(f, index(q)),
+            Instruction::RZ(f, q) => Instruction::RZ(f, index(q)),
+            Instruction::CZ(q1, q2) => Instruction::CZ(index(q1), index(q2)),
+            Instruction::MEASURE(q) => Instruction::MEASURE(index(q)),
+            Instruction::INVALID => Instruction::INVALID,
+        })
+        .collect()
+}
+
+// Helpers for validating passes against random circuits
+#[cfg(test)]
+pub(crate) mod testing {
+    use super::*;
+    use crate::angle::Tolerance;
+    use crate::pipeline::PassFn;
+    use std::f64::consts::PI;
+
+    // Same epsilon the passes use for comparing angles
+    pub const TOLERANCE: f64 = crate::angle::DEFAULT_EPSILON;
+    const ROUNDS: u64 = 200;
+
+    // Small xorshift generator so tests are reproducible without extra deps
+    pub struct Rng(u64);
+
+    impl Rng {
+        pub fn new(seed: u64) -> Rng {
+            Rng(seed.wrapping_mul(0x9E3779B97F4A7C15) | 1)
+        }
+
+        pub fn next_u64(&mut self) -> u64 {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            self.0
+        }
+
+        pub fn below(&mut self, n: u64) -> u64 {
+            self.next_u64() % n
+        }
+
+        pub fn angle(&mut self) -> f64 {
+            // Favour the special angles the passes care about
+            match self.below(8) {
+                0 => 0.0,
+                1 => PI,
+                2 => -PI,
+                3 => PI / 2.0,
+                4 => -PI / 2.0,
+                _ => (self.below(1 << 20) as f64 / (1 << 20) as f64 - 0.5) * 4.0 * PI,
+            }
+        }
+    }
+
+    // Random measurement-free circuit over a handful of qubits. Few qubits
+    // means plenty of adjacent gates for the passes to merge and cancel.
+    pub fn random_program(rng: &mut Rng, num_qubits: u32, len: usize) -> Vec<Instruction> {
+        let mut program = Vec::new();
+        while program.len() < len {
+            let q1 = rng.below(num_qubits as u64) as u32;
+            let instr = match rng.below(3) {
+                0 => Instruction::RX(rng.angle(), q1),
+                1 => Instruction::RZ(rng.angle(), q1),
+                _ => {
+                    let q2 = rng.below(num_qubits as u64) as u32;
+                    if q1 == q2 {
+                        continue;
+                    }
+                    Instruction::CZ(q1, q2)
+                }
+            };
+            program.push(instr);
+        }
+        program
+    }
+
+    pub fn assert_equivalent(expected: &[Instruction], actual: &[Instruction]) {
+        if let Equivalence::Diverges(divergence) =
+            check_equivalence(expected, actual, TOLERANCE).unwrap()
+        {
+            panic!(
+                "{}\nexpected program: {:?}\nactual program:   {:?}",
+                divergence, expected, actual
+            );
+        }
+    }
+
+    // Run `pass` on many random circuits and check the unitary is preserved
+    pub fn assert_pass_preserves_unitary(pass: PassFn) {
+        for seed in 0..ROUNDS {
+            let mut rng = Rng::new(seed + 1);
+            let num_qubits = 1 + rng.below(4) as u32;
+            let len = rng.below(24) as usize;
+            let program = random_program(&mut rng, num_qubits, len);
+
+            let optimized = pass(program.clone(), Tolerance::default()).unwrap();
+            assert_equivalent(&program, &optimized);
+        }
+    }
+}
+
+#[rustfmt::skip]
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::testing::*;
+    use std::f64::consts::PI;
+
+    #[test]
+    fn unitary_of_gates() {
+        let u = unitary(&[Instruction::RX(PI, 0)], 1).unwrap();
+        assert!(u.get(0, 0).approx_eq(Complex::ZERO, TOLERANCE));
+        assert!(u.get(1, 0).approx_eq(Complex::new(0.0, -1.0), TOLERANCE));
+
+        let u = unitary(&[Instruction::CZ(0, 1)], 2).unwrap();
+        for i in 0..4 {
+            let expected = if i == 3 { -Complex::ONE } else { Complex::ONE };
+            assert!(u.get(i, i).approx_eq(expected, TOLERANCE));
+        }
+
+        let err = unitary(&[Instruction::MEASURE(0)], 1).unwrap_err();
+        assert_eq!(err, "Cannot build unitary of program containing MEASURE 0");
+
+        let err = unitary(&[], MAX_UNITARY_QUBITS + 1).unwrap_err();
+        assert_eq!(err, "Cannot build unitary over 11 qubits, maximum is 10");
+    }
+
+    #[test]
+    fn prefix_stops_at_measure() {
+        let program = [
+            Instruction::RX(PI, 0),
+            Instruction::MEASURE(0),
+            Instruction::RX(PI, 1),
+        ];
+        assert_eq!(measurement_free_prefix(&program), &program[..1]);
+    }
+
+    #[test]
+    fn equivalent_up_to_global_phase() {
+        // RZ(t) and RZ(t + 2PI) differ by a factor of -1
+        let a = [Instruction::RZ(0.3, 0), Instruction::CZ(0, 1)];
+        let b = [Instruction::CZ(1, 0), Instruction::RZ(0.3 + 2.0*PI, 0)];
+
+        assert!(check_equivalence(&a, &b, TOLERANCE).unwrap().is_equivalent());
+    }
+
+    #[test]
+    fn sparse_qubit_indices() {
+        let a = [Instruction::RX(0.1, 77), Instruction::RX(0.2, 77), Instruction::RZ(0.0, 3)];
+        let b = [Instruction::RX(0.30000000000000004, 77)];
+
+        assert!(check_equivalence(&a, &b, TOLERANCE).unwrap().is_equivalent());
+    }
+
+    #[test]
+    fn report_divergence() {
+        let a = [Instruction::RX(0.3, 0)];
+        let b = [Instruction::RX(-0.3, 0)];
+
+        let result = check_equivalence(&a, &b, TOLERANCE).unwrap();
+        match result {
+            Equivalence::Diverges(divergence) => {
+                assert_eq!((divergence.row, divergence.col), (0, 1));
+                assert!(divergence.to_string().starts_with("unitaries diverge at (0, 1)"));
+            }
+            Equivalence::Equivalent => panic!("expected divergence"),
+        }
+    }
+}
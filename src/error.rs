@@ -0,0 +1,256 @@
+use std::fmt;
+
+use crate::token::Token;
+
+// Location of a token in the source, 1-based like `Token`
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct Span {
+    pub line: u32,
+    pub pos: u32,
+    pub len: usize,
+}
+
+impl Span {
+    pub fn new(line: u32, pos: u32, len: usize) -> Span {
+        Span { line, pos, len }
+    }
+}
+
+impl From<&Token> for Span {
+    fn from(token: &Token) -> Span {
+        Span {
+            line: token.line,
+            pos: token.pos,
+            len: token.len,
+        }
+    }
+}
+
+impl fmt::Display for Span {
+    fn fmt(&self, ftr: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(ftr, "{}:{}", self.line, self.pos)
+    }
+}
+
+// Error raised by any stage of the compilation pipeline
+#[derive(Debug, PartialEq, Clone)]
+pub enum CompileError {
+    // Source could not be read
+    Io {
+        message: String,
+    },
+    // Characters that do not form a valid token
+    Lexical {
+        message: String,
+        span: Span,
+        hint: Option<String>,
+    },
+    // Tokens that do not form a valid instruction
+    Syntax {
+        message: String,
+        span: Span,
+        hint: Option<String>,
+    },
+    // Well-formed instruction that is not meaningful (e.g. CZ 0 0)
+    Semantic {
+        message: String,
+        span: Span,
+        hint: Option<String>,
+    },
+    // Failure inside an optimization pass
+    Pass {
+        pass: String,
+        message: String,
+    },
+    // Several errors reported at once, in source order
+    Multiple(Vec<CompileError>),
+}
+
+impl CompileError {
+    pub fn io(message: impl Into<String>) -> CompileError {
+        CompileError::Io {
+            message: message.into(),
+        }
+    }
+
+    pub fn lexical(message: impl Into<String>, span: Span) -> CompileError {
+        CompileError::Lexical {
+            message: message.into(),
+            span,
+            hint: None,
+        }
+    }
+
+    pub fn syntax(message: impl Into<String>, span: Span) -> CompileError {
+        CompileError::Syntax {
+            message: message.into(),
+            span,
+            hint: None,
+        }
+    }
+
+    pub fn semantic(message: impl Into<String>, span: Span) -> CompileError {
+        CompileError::Semantic {
+            message: message.into(),
+            span,
+            hint: None,
+        }
+    }
+
+    pub fn pass(pass: impl Into<String>, message: impl Into<String>) -> CompileError {
+        CompileError::Pass {
+            pass: pass.into(),
+            message: message.into(),
+        }
+    }
+
+    // Attach a suggestion on how to fix the error. Only errors with a
+    // source location carry hints.
+    pub fn with_hint(mut self, new_hint: impl Into<String>) -> CompileError {
+        match &mut self {
+            CompileError::Lexical { hint, .. }
+            | CompileError::Syntax { hint, .. }
+            | CompileError::Semantic { hint, .. } => *hint = Some(new_hint.into()),
+            _ => {}
+        }
+        self
+    }
+
+    // Collapse a list of errors, keeping a lone error as is
+    pub fn from_errors(mut errors: Vec<CompileError>) -> CompileError {
+        if errors.len() == 1 {
+            errors.remove(0)
+        } else {
+            CompileError::Multiple(errors)
+        }
+    }
+
+    // Every individual error contained in this one
+    pub fn errors(&self) -> &[CompileError] {
+        match self {
+            CompileError::Multiple(errors) => errors,
+            _ => std::slice::from_ref(self),
+        }
+    }
+
+    // Message without the stage prefix. For multiple errors this is the
+    // message of the first one.
+    pub fn message(&self) -> &str {
+        match self {
+            CompileError::Io { message }
+            | CompileError::Lexical { message, .. }
+            | CompileError::Syntax { message, .. }
+            | CompileError::Semantic { message, .. }
+            | CompileError::Pass { message, .. } => message,
+            CompileError::Multiple(errors) => errors.first().map_or("", |err| err.message()),
+        }
+    }
+
+    // Stable identifier for the category of error
+    pub fn code(&self) -> &'static str {
+        match self {
+            CompileError::Io { .. } => "E0001",
+            CompileError::Lexical { .. } => "E0100",
+            CompileError::Syntax { .. } => "E0200",
+            CompileError::Semantic { .. } => "E0300",
+            CompileError::Pass { .. } => "E0400",
+            CompileError::Multiple(errors) => errors.first().map_or("E0000", |err| err.code()),
+        }
+    }
+
+    pub fn hint(&self) -> Option<&str> {
+        match self {
+            CompileError::Lexical { hint, .. }
+            | CompileError::Syntax { hint, .. }
+            | CompileError::Semantic { hint, .. } => hint.as_deref(),
+            CompileError::Io { .. } | CompileError::Pass { .. } => None,
+            CompileError::Multiple(errors) => errors.first().and_then(|err| err.hint()),
+        }
+    }
+
+    // Source location, if the error originates from the source text
+    pub fn span(&self) -> Option<Span> {
+        match self {
+            CompileError::Lexical { span, .. }
+            | CompileError::Syntax { span, .. }
+            | CompileError::Semantic { span, .. } => Some(*span),
+            CompileError::Io { .. } | CompileError::Pass { .. } => None,
+            CompileError::Multiple(errors) => errors.first().and_then(|err| err.span()),
+        }
+    }
+}
+
+impl fmt::Display for CompileError {
+    fn fmt(&self, ftr: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CompileError::Io { message } => write!(ftr, "io: {}", message),
+            CompileError::Lexical { message, .. } => write!(ftr, "lexer: {}", message),
+            CompileError::Syntax { message, .. } => write!(ftr, "parser: {}", message),
+            CompileError::Semantic { message, .. } => write!(ftr, "semantic: {}", message),
+            CompileError::Pass { pass, message } => write!(ftr, "{}: {}", pass, message),
+            CompileError::Multiple(errors) => {
+                for (i, err) in errors.iter().enumerate() {
+                    if i > 0 {
+                        writeln!(ftr)?;
+                    }
+                    write!(ftr, "{}", err)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl std::error::Error for CompileError {}
+
+#[rustfmt::skip]
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::token::TokenType;
+
+    #[test]
+    fn span_from_token() {
+        let token = Token{t: TokenType::RX, line: 3, pos: 5, len: 2};
+        assert_eq!(Span::from(&token), Span::new(3, 5, 2));
+        assert_eq!(Span::from(&token).to_string(), "3:5");
+    }
+
+    #[test]
+    fn error_accessors() {
+        let err = CompileError::syntax("Unexpected token at 1:2, expected '('", Span::new(1, 2, 1));
+        assert_eq!(err.message(), "Unexpected token at 1:2, expected '('");
+        assert_eq!(err.span(), Some(Span::new(1, 2, 1)));
+        assert_eq!(err.to_string(), "parser: Unexpected token at 1:2, expected '('");
+
+        assert_eq!(err.code(), "E0200");
+        assert_eq!(err.hint(), None);
+        assert_eq!(err.with_hint("expected '(' after RZ").hint(), Some("expected '(' after RZ"));
+
+        let err = CompileError::pass("deadcode", "failed");
+        assert_eq!(err.span(), None);
+        assert_eq!(err.code(), "E0400");
+        assert_eq!(err.clone().with_hint("ignored").hint(), None);
+        assert_eq!(err.to_string(), "deadcode: failed");
+
+        let boxed: Box<dyn std::error::Error> = Box::new(CompileError::io("Error reading file: missing"));
+        assert_eq!(boxed.to_string(), "io: Error reading file: missing");
+    }
+
+    #[test]
+    fn multiple_errors() {
+        let first = CompileError::syntax("Missing qbit index after instruction at 1:1", Span::new(1, 1, 2));
+        let second = CompileError::semantic("CZ requires two distinct qbits at 3:1", Span::new(3, 1, 2));
+
+        assert_eq!(CompileError::from_errors(vec![first.clone()]), first);
+
+        let err = CompileError::from_errors(vec![first.clone(), second.clone()]);
+        assert_eq!(err.errors(), &[first.clone(), second]);
+        assert_eq!(err.message(), first.message());
+        assert_eq!(err.span(), Some(Span::new(1, 1, 2)));
+        assert_eq!(
+            err.to_string(),
+            "parser: Missing qbit index after instruction at 1:1\nsemantic: CZ requires two distinct qbits at 3:1"
+        );
+    }
+}
@@ -0,0 +1,274 @@
+use crate::error::{CompileError, Span};
+use std::f64::consts::{E, PI, TAU};
+use std::fmt;
+
+// Deepest nesting of parentheses, negations and operators accepted in an
+// angle, so that hostile input cannot overflow the stack of the recursive
+// parser or of `Expr::eval`
+pub const MAX_DEPTH: usize = 128;
+
+// Named constants that may appear in rotation angles
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Constant {
+    Pi,
+    Tau,
+    E,
+}
+
+impl Constant {
+    pub fn value(&self) -> f64 {
+        match self {
+            Constant::Pi => PI,
+            Constant::Tau => TAU,
+            Constant::E => E,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum BinOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+}
+
+// Arithmetic expression used as a rotation angle, e.g. `-3*pi/4`
+#[derive(Debug, PartialEq, Clone)]
+pub enum Expr {
+    Number(f64),
+    Constant(Constant),
+    // Gate parameter or loop variable, bound when the expression is folded
+    Var(String),
+    Neg(Box<Expr>),
+    Binary(BinOp, Box<Expr>, Box<Expr>),
+}
+
+impl Expr {
+    pub fn binary(op: BinOp, lhs: Expr, rhs: Expr) -> Expr {
+        Expr::Binary(op, Box::new(lhs), Box::new(rhs))
+    }
+
+    pub fn negate(expr: Expr) -> Expr {
+        Expr::Neg(Box::new(expr))
+    }
+
+    // Fold the expression to a single value. Operations are evaluated in
+    // source order with plain f64 arithmetic, so `pi/2` is exactly PI / 2.0.
+    // Variables evaluate to NaN, use `eval_with` to bind them.
+    pub fn eval(&self) -> f64 {
+        self.eval_with(&|_| None).unwrap_or(f64::NAN)
+    }
+
+    // Fold the expression looking up variables with `vars`. Returns the name
+    // of the first unbound variable on failure.
+    pub fn eval_with(&self, vars: &dyn Fn(&str) -> Option<f64>) -> Result<f64, String> {
+        Ok(match self {
+            Expr::Number(f) => *f,
+            Expr::Constant(c) => c.value(),
+            Expr::Var(name) => return vars(name).ok_or_else(|| name.clone()),
+            Expr::Neg(expr) => -expr.eval_with(vars)?,
+            Expr::Binary(op, lhs, rhs) => {
+                let (lhs, rhs) = (lhs.eval_with(vars)?, rhs.eval_with(vars)?);
+                match op {
+                    BinOp::Add => lhs + rhs,
+                    BinOp::Sub => lhs - rhs,
+                    BinOp::Mul => lhs * rhs,
+                    BinOp::Div => lhs / rhs,
+                }
+            }
+        })
+    }
+}
+
+impl fmt::Display for Expr {
+    fn fmt(&self, ftr: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Expr::Number(f) => write!(ftr, "{}", f),
+            Expr::Constant(Constant::Pi) => write!(ftr, "pi"),
+            Expr::Constant(Constant::Tau) => write!(ftr, "tau"),
+            Expr::Constant(Constant::E) => write!(ftr, "e"),
+            Expr::Var(name) => write!(ftr, "{}", name),
+            Expr::Neg(expr) => write!(ftr, "-{}", expr),
+            Expr::Binary(op, lhs, rhs) => {
+                let op = match op {
+                    BinOp::Add => "+",
+                    BinOp::Sub => "-",
+                    BinOp::Mul => "*",
+                    BinOp::Div => "/",
+                };
+                write!(ftr, "({}{}{})", lhs, op, rhs)
+            }
+        }
+    }
+}
+
+// Token of an angle as classified by the language the angle is written in
+#[derive(Debug, PartialEq)]
+pub enum ExprToken {
+    Plus,
+    Minus,
+    Star,
+    Slash,
+    LParen,
+    RParen,
+    // Number, constant or variable
+    Operand(Expr),
+    Other,
+}
+
+// What the angle grammar was looking for when it failed
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Expected {
+    Operand,
+    RParen,
+}
+
+// Token stream an angle is parsed from. The grammar is shared by every
+// language, each one words its own errors.
+pub trait ExprTokens {
+    // Next token, None at the end of the angle
+    fn peek(&self) -> Option<ExprToken>;
+    fn bump(&mut self);
+    // Error for the next token, or for the end of the angle
+    fn expected(&self, expected: Expected) -> CompileError;
+    // Span of the next token, or of the end of the angle
+    fn span(&self) -> Span;
+}
+
+// Parse an angle, lowest precedence first:
+//
+// expr    := term (('+' | '-') term)*
+// term    := unary (('*' | '/') unary)*
+// unary   := '-' unary | primary
+// primary := Operand | '(' expr ')'
+pub fn parse(tokens: &mut impl ExprTokens) -> Result<Expr, CompileError> {
+    Parser { tokens }.expr(0).map(|(expr, _)| expr)
+}
+
+// Each rule returns the expression along with the height of its tree
+struct Parser<'t, T> {
+    tokens: &'t mut T,
+}
+
+impl<T: ExprTokens> Parser<'_, T> {
+    fn expr(&mut self, depth: usize) -> Result<(Expr, usize), CompileError> {
+        let (mut expr, mut height) = self.term(depth)?;
+        loop {
+            let op = match self.tokens.peek() {
+                Some(ExprToken::Plus) => BinOp::Add,
+                Some(ExprToken::Minus) => BinOp::Sub,
+                _ => return Ok((expr, height)),
+            };
+            self.tokens.bump();
+            let (rhs, rhs_height) = self.term(depth)?;
+            height = self.node(height.max(rhs_height))?;
+            expr = Expr::binary(op, expr, rhs);
+        }
+    }
+
+    fn term(&mut self, depth: usize) -> Result<(Expr, usize), CompileError> {
+        let (mut expr, mut height) = self.unary(depth)?;
+        loop {
+            let op = match self.tokens.peek() {
+                Some(ExprToken::Star) => BinOp::Mul,
+                Some(ExprToken::Slash) => BinOp::Div,
+                _ => return Ok((expr, height)),
+            };
+            self.tokens.bump();
+            let (rhs, rhs_height) = self.unary(depth)?;
+            height = self.node(height.max(rhs_height))?;
+            expr = Expr::binary(op, expr, rhs);
+        }
+    }
+
+    fn unary(&mut self, depth: usize) -> Result<(Expr, usize), CompileError> {
+        match self.tokens.peek() {
+            Some(ExprToken::Minus | ExprToken::LParen) if depth >= MAX_DEPTH => {
+                Err(self.too_deep())
+            }
+            Some(ExprToken::Minus) => {
+                self.tokens.bump();
+                let (expr, height) = self.unary(depth + 1)?;
+                Ok((Expr::negate(expr), self.node(height)?))
+            }
+            Some(ExprToken::LParen) => {
+                self.tokens.bump();
+                let expr = self.expr(depth + 1)?;
+                match self.tokens.peek() {
+                    Some(ExprToken::RParen) => self.tokens.bump(),
+                    _ => return Err(self.tokens.expected(Expected::RParen)),
+                }
+                Ok(expr)
+            }
+            Some(ExprToken::Operand(expr)) => {
+                self.tokens.bump();
+                Ok((expr, 1))
+            }
+            _ => Err(self.tokens.expected(Expected::Operand)),
+        }
+    }
+
+    // Height of a new node over a subtree of height `height`
+    fn node(&self, height: usize) -> Result<usize, CompileError> {
+        if height >= MAX_DEPTH {
+            return Err(self.too_deep());
+        }
+        Ok(height + 1)
+    }
+
+    fn too_deep(&self) -> CompileError {
+        let span = self.tokens.span();
+        CompileError::syntax(
+            format!(
+                "Angle nested deeper than {} levels at {}:{}",
+                MAX_DEPTH, span.line, span.pos
+            ),
+            span,
+        )
+        .with_hint("simplify the angle expression")
+    }
+}
+
+#[rustfmt::skip]
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn eval_constants() {
+        assert_eq!(Expr::Constant(Constant::Pi).eval(), PI);
+        assert_eq!(Expr::Constant(Constant::Tau).eval(), 2.0*PI);
+        assert_eq!(Expr::Constant(Constant::E).eval(), E);
+    }
+
+    #[test]
+    fn eval_arithmetic() {
+        // -3*pi/4
+        let expr = Expr::binary(
+            BinOp::Div,
+            Expr::binary(BinOp::Mul, Expr::negate(Expr::Number(3.0)), Expr::Constant(Constant::Pi)),
+            Expr::Number(4.0),
+        );
+        assert_eq!(expr.eval(), -3.0*PI/4.0);
+        assert_eq!(expr.to_string(), "((-3*pi)/4)");
+
+        let expr = Expr::binary(BinOp::Sub, Expr::Number(1.0), Expr::Number(0.25));
+        assert_eq!(expr.eval(), 0.75);
+    }
+
+    #[test]
+    fn eval_variables() {
+        // theta*pi/2
+        let expr = Expr::binary(
+            BinOp::Div,
+            Expr::binary(BinOp::Mul, Expr::Var(String::from("theta")), Expr::Constant(Constant::Pi)),
+            Expr::Number(2.0),
+        );
+        let vars = |name: &str| if name == "theta" { Some(0.5) } else { None };
+
+        assert_eq!(expr.eval_with(&vars), Ok(0.5*PI/2.0));
+        assert_eq!(expr.eval_with(&|_| None), Err(String::from("theta")));
+        assert!(expr.eval().is_nan());
+    }
+}
@@ -0,0 +1,215 @@
+use crate::error::{CompileError, Span};
+use crate::expr::{self, Constant, Expected, Expr, ExprToken, ExprTokens};
+use crate::frontend::qasm_lexer::{QasmToken, QasmTokenType};
+
+// Position in a stream of OpenQASM tokens, with the helpers shared by the
+// QASM 2 and QASM 3 parsers
+pub(crate) struct Cursor<'a> {
+    tokens: &'a [QasmToken],
+    index: usize,
+}
+
+impl<'a> Cursor<'a> {
+    pub fn new(tokens: &'a [QasmToken]) -> Cursor<'a> {
+        Cursor { tokens, index: 0 }
+    }
+
+    pub fn position(&self) -> usize {
+        self.index
+    }
+
+    pub fn rewind(&mut self, index: usize) {
+        self.index = index;
+    }
+
+    pub fn at_end(&self) -> bool {
+        self.index >= self.tokens.len()
+    }
+
+    pub fn peek(&self) -> Option<&'a QasmToken> {
+        self.tokens.get(self.index)
+    }
+
+    pub fn peek_is(&self, t: &QasmTokenType) -> bool {
+        self.peek().is_some_and(|token| token.t == *t)
+    }
+
+    pub fn peek_ident(&self, ident: &str) -> bool {
+        matches!(self.peek(), Some(QasmToken { t: QasmTokenType::Ident(name), .. }) if name == ident)
+    }
+
+    pub fn next(&mut self) -> Option<&'a QasmToken> {
+        let token = self.tokens.get(self.index);
+        self.index += 1;
+        token
+    }
+
+    // Consume the next token if it is `t`
+    pub fn eat(&mut self, t: &QasmTokenType) -> bool {
+        if self.peek_is(t) {
+            self.index += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    pub fn expect(
+        &mut self,
+        t: &QasmTokenType,
+        expected: &str,
+    ) -> Result<&'a QasmToken, CompileError> {
+        match self.peek() {
+            Some(token) if token.t == *t => {
+                self.index += 1;
+                Ok(token)
+            }
+            _ => Err(self.unexpected(expected)),
+        }
+    }
+
+    pub fn expect_ident(
+        &mut self,
+        expected: &str,
+    ) -> Result<(&'a QasmToken, String), CompileError> {
+        match self.peek() {
+            Some(
+                token @ QasmToken {
+                    t: QasmTokenType::Ident(name),
+                    ..
+                },
+            ) => {
+                self.index += 1;
+                Ok((token, name.clone()))
+            }
+            _ => Err(self.unexpected(expected)),
+        }
+    }
+
+    pub fn expect_integer(&mut self, expected: &str) -> Result<u32, CompileError> {
+        match self.peek() {
+            Some(QasmToken {
+                t: QasmTokenType::Integer(u),
+                ..
+            }) => {
+                self.index += 1;
+                Ok(*u)
+            }
+            _ => Err(self.unexpected(expected)),
+        }
+    }
+
+    // Span of the token at the cursor, or just past the last token at the
+    // end of input
+    pub fn span(&self) -> Span {
+        match self.peek() {
+            Some(token) => Span::from(token),
+            None => self.tokens.last().map_or(Span::new(1, 1, 1), |token| {
+                Span::new(token.line, token.pos + token.len as u32, 1)
+            }),
+        }
+    }
+
+    // Error for the token at the cursor, or for the end of input
+    pub fn unexpected(&self, expected: &str) -> CompileError {
+        match self.peek() {
+            Some(token) => CompileError::syntax(
+                format!(
+                    "Unexpected token at {}:{}, expected {}",
+                    token.line, token.pos, expected
+                ),
+                Span::from(token),
+            ),
+            None => CompileError::syntax(
+                format!("Unexpected end of input, expected {}", expected),
+                self.span(),
+            ),
+        }
+    }
+
+    // Skip past the next ';' or past the end of the next block, so parsing
+    // can resume at the next statement
+    pub fn skip_statement(&mut self) {
+        let mut depth = 0;
+        while let Some(token) = self.next() {
+            match token.t {
+                QasmTokenType::Semicolon if depth == 0 => break,
+                QasmTokenType::LBrace => depth += 1,
+                QasmTokenType::RBrace => {
+                    depth -= 1;
+                    if depth <= 0 {
+                        break;
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    // Angle expression, see `expr::parse` for the grammar. Operands are
+    // numbers, pi, tau, e and the variables named in `scope`.
+    pub fn expr(&mut self, scope: &[String]) -> Result<Expr, CompileError> {
+        expr::parse(&mut AngleTokens {
+            cursor: self,
+            scope,
+        })
+    }
+}
+
+// Tokens of an angle, with the variables that are in scope
+struct AngleTokens<'c, 'a> {
+    cursor: &'c mut Cursor<'a>,
+    scope: &'c [String],
+}
+
+impl ExprTokens for AngleTokens<'_, '_> {
+    fn peek(&self) -> Option<ExprToken> {
+        self.cursor.peek().map(|token| match &token.t {
+            QasmTokenType::Plus => ExprToken::Plus,
+            QasmTokenType::Minus => ExprToken::Minus,
+            QasmTokenType::Star => ExprToken::Star,
+            QasmTokenType::Slash => ExprToken::Slash,
+            QasmTokenType::LParen => ExprToken::LParen,
+            QasmTokenType::RParen => ExprToken::RParen,
+            QasmTokenType::Real(f) => ExprToken::Operand(Expr::Number(*f)),
+            QasmTokenType::Integer(u) => ExprToken::Operand(Expr::Number(*u as f64)),
+            QasmTokenType::Ident(name) if self.scope.contains(name) => {
+                ExprToken::Operand(Expr::Var(name.clone()))
+            }
+            QasmTokenType::Ident(name) => match name.as_str() {
+                "pi" | "π" => ExprToken::Operand(Expr::Constant(Constant::Pi)),
+                "tau" | "τ" => ExprToken::Operand(Expr::Constant(Constant::Tau)),
+                "e" | "euler" => ExprToken::Operand(Expr::Constant(Constant::E)),
+                _ => ExprToken::Other,
+            },
+            _ => ExprToken::Other,
+        })
+    }
+
+    fn bump(&mut self) {
+        self.cursor.index += 1;
+    }
+
+    fn expected(&self, expected: Expected) -> CompileError {
+        match (expected, self.cursor.peek()) {
+            (Expected::RParen, _) => self.cursor.unexpected("')'"),
+            (Expected::Operand, Some(token)) => match &token.t {
+                // Only reached for names that are neither constants nor in scope
+                QasmTokenType::Ident(name) => CompileError::semantic(
+                    format!(
+                        "Unknown identifier '{}' at {}:{}",
+                        name, token.line, token.pos
+                    ),
+                    Span::from(token),
+                )
+                .with_hint("angles may only use numbers, pi, tau and e"),
+                _ => self.cursor.unexpected("an angle"),
+            },
+            (Expected::Operand, None) => self.cursor.unexpected("an angle"),
+        }
+    }
+
+    fn span(&self) -> Span {
+        self.cursor.span()
+    }
+}
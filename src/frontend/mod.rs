@@ -0,0 +1,386 @@
+pub(crate) mod cursor;
+pub mod qasm2;
+pub mod qasm3;
+pub mod qasm_lexer;
+pub mod stdgates;
+
+use std::cell::Cell;
+use std::ops::Range;
+
+use crate::error::{CompileError, Span};
+use crate::frontend::cursor::Cursor;
+use crate::frontend::qasm_lexer::{QasmToken, QasmTokenType};
+use crate::instruction::Instruction;
+
+// Parse an OpenQASM program with the frontend matching its version header.
+// Programs without a header are read as OpenQASM 3.
+pub fn parse_qasm(source: &str) -> Result<Vec<Instruction>, CompileError> {
+    let tokens = qasm_lexer::tokenize(source)?;
+    let is_qasm2 = matches!(
+        tokens.as_slice(),
+        [QasmToken { t: QasmTokenType::Ident(header), .. }, QasmToken { t: QasmTokenType::Real(version), .. }, ..]
+            if header == "OPENQASM" && *version == 2.0
+    );
+
+    if is_qasm2 {
+        qasm2::parse(source)
+    } else {
+        qasm3::parse(source)
+    }
+}
+
+// Upper bound on the instructions and loop iterations a program may expand
+// to, so that gates broadcast over huge registers, nested loops or gates
+// calling each other cannot exhaust memory or run without end
+pub const MAX_EXPANSION: u64 = 1 << 20;
+
+// Instructions and loop iterations expanded so far, shared by the whole
+// program
+#[derive(Debug, Default)]
+pub(crate) struct Budget {
+    spent: Cell<u64>,
+}
+
+impl Budget {
+    // Count `n` more instructions or loop iterations, blaming the statement
+    // at `token`. Called before anything is allocated for them. A rejected
+    // statement spends nothing, so later statements are judged on their own.
+    pub fn spend(&self, token: &QasmToken, n: u64) -> Result<(), CompileError> {
+        let spent = self.spent.get().saturating_add(n);
+        if spent > MAX_EXPANSION {
+            return Err(CompileError::semantic(
+                format!(
+                    "Program expands beyond {} instructions and loop iterations at {}:{}",
+                    MAX_EXPANSION, token.line, token.pos
+                ),
+                Span::from(token),
+            )
+            .with_hint("shorten the loops, the chains of nested gate calls or the registers gates apply to"));
+        }
+        self.spent.set(spent);
+        Ok(())
+    }
+}
+
+// Named register of qubits or classical bits. Qubit registers are laid out
+// one after another, so q[i] is qubit `offset + i` of the program.
+#[derive(Debug, Clone)]
+pub(crate) struct Register {
+    pub name: String,
+    pub offset: u32,
+    pub size: u32,
+}
+
+#[derive(Debug, Default)]
+pub(crate) struct Registers {
+    pub qubits: Vec<Register>,
+    pub bits: Vec<Register>,
+}
+
+impl Registers {
+    pub fn declare_qubits(
+        &mut self,
+        token: &QasmToken,
+        name: &str,
+        size: u32,
+    ) -> Result<(), CompileError> {
+        self.check_unique(token, name)?;
+        let offset = next_offset(&self.qubits, token, size, "qubits")?;
+        self.qubits.push(Register {
+            name: name.to_string(),
+            offset,
+            size,
+        });
+        Ok(())
+    }
+
+    pub fn declare_bits(
+        &mut self,
+        token: &QasmToken,
+        name: &str,
+        size: u32,
+    ) -> Result<(), CompileError> {
+        self.check_unique(token, name)?;
+        let offset = next_offset(&self.bits, token, size, "classical bits")?;
+        self.bits.push(Register {
+            name: name.to_string(),
+            offset,
+            size,
+        });
+        Ok(())
+    }
+
+    fn check_unique(&self, token: &QasmToken, name: &str) -> Result<(), CompileError> {
+        if self
+            .qubits
+            .iter()
+            .chain(self.bits.iter())
+            .any(|reg| reg.name == name)
+        {
+            return Err(CompileError::semantic(
+                format!(
+                    "Register '{}' is already declared at {}:{}",
+                    name, token.line, token.pos
+                ),
+                Span::from(token),
+            )
+            .with_hint("give each register a unique name"));
+        }
+        Ok(())
+    }
+}
+
+// Offset of a register of `size` declared after `registers`. Every index of
+// the new register has to fit in a u32.
+fn next_offset(
+    registers: &[Register],
+    token: &QasmToken,
+    size: u32,
+    kind: &str,
+) -> Result<u32, CompileError> {
+    let offset = registers
+        .iter()
+        .try_fold(0u32, |offset, reg| offset.checked_add(reg.size));
+    match offset {
+        Some(offset) if size == 0 || offset.checked_add(size - 1).is_some() => Ok(offset),
+        _ => Err(CompileError::semantic(
+            format!("Too many {} declared at {}:{}", kind, token.line, token.pos),
+            Span::from(token),
+        )
+        .with_hint(format!(
+            "a program may declare at most {} {}",
+            u32::MAX as u64 + 1,
+            kind
+        ))),
+    }
+}
+
+// Reference to a whole register or to one element of it
+pub(crate) struct Operand<'a> {
+    pub token: &'a QasmToken,
+    pub name: String,
+    pub index: Option<(&'a QasmToken, u32)>,
+}
+
+// operand := ident ('[' Integer ']')?
+pub(crate) fn parse_operand<'a>(cursor: &mut Cursor<'a>) -> Result<Operand<'a>, CompileError> {
+    let (token, name) = cursor.expect_ident("a register")?;
+    let mut index = None;
+    if cursor.eat(&QasmTokenType::LBracket) {
+        let index_token = cursor.peek();
+        let i = cursor.expect_integer("an index")?;
+        cursor.expect(&QasmTokenType::RBracket, "']'")?;
+        index = index_token.map(|index_token| (index_token, i));
+    }
+
+    Ok(Operand { token, name, index })
+}
+
+// Barriers only constrain scheduling, which the optimizer ignores, so they
+// lower to no instructions. `operand` reads (and may check) each operand, an
+// empty list stands for all qubits.
+// barrier := 'barrier' (operand (',' operand)*)? ';'
+pub(crate) fn parse_barrier<'a, T>(
+    cursor: &mut Cursor<'a>,
+    mut operand: impl FnMut(&mut Cursor<'a>) -> Result<T, CompileError>,
+) -> Result<(), CompileError> {
+    if cursor.eat(&QasmTokenType::Semicolon) {
+        return Ok(());
+    }
+    loop {
+        operand(cursor)?;
+        if !cursor.eat(&QasmTokenType::Comma) {
+            break;
+        }
+    }
+    cursor.expect(&QasmTokenType::Semicolon, "';'")?;
+    Ok(())
+}
+
+// Indices referred to by `operand` within `registers`, in order. Registers
+// are contiguous, so this is a range, in u64 as the last register may end at
+// 2^32.
+pub(crate) fn resolve(
+    operand: &Operand,
+    registers: &[Register],
+    kind: &str,
+) -> Result<Range<u64>, CompileError> {
+    let register = match registers.iter().find(|reg| reg.name == operand.name) {
+        Some(register) => register,
+        None => {
+            return Err(CompileError::semantic(
+                format!(
+                    "Unknown {} register '{}' at {}:{}",
+                    kind, operand.name, operand.token.line, operand.token.pos
+                ),
+                Span::from(operand.token),
+            )
+            .with_hint(format!("declare the {} register before using it", kind)))
+        }
+    };
+
+    match operand.index {
+        Some((token, i)) if i >= register.size => Err(CompileError::semantic(
+            format!(
+                "Index {} out of range for register '{}' of size {} at {}:{}",
+                i, register.name, register.size, token.line, token.pos
+            ),
+            Span::from(token),
+        )
+        .with_hint(format!(
+            "valid indices are 0 to {}",
+            register.size.saturating_sub(1)
+        ))),
+        Some((_, i)) => {
+            let index = register.offset as u64 + i as u64;
+            Ok(index..index + 1)
+        }
+        None => Ok(register.offset as u64..register.offset as u64 + register.size as u64),
+    }
+}
+
+// Pair up operands of a multi-qubit statement. Whole registers are applied
+// element-wise and must all have the same size, single elements are
+// repeated, e.g. `cx q, r[0]` is `cx q[0], r[0]; cx q[1], r[0]; ...`.
+// Returns the number of applications, see `broadcast_qubits` for each one.
+pub(crate) fn broadcast(token: &QasmToken, operands: &[Range<u64>]) -> Result<u64, CompileError> {
+    let mut size = 1;
+    for len in operands
+        .iter()
+        .map(|operand| operand.end - operand.start)
+        .filter(|&len| len != 1)
+    {
+        if size != 1 && len != size {
+            return Err(CompileError::semantic(
+                format!(
+                    "Registers of different sizes used together at {}:{}",
+                    token.line, token.pos
+                ),
+                Span::from(token),
+            )
+            .with_hint("registers applied together must have the same size"));
+        }
+        size = len;
+    }
+    Ok(size)
+}
+
+// Qubits of the `i`-th application of a broadcast statement
+pub(crate) fn broadcast_qubits(operands: &[Range<u64>], i: u64) -> Vec<u32> {
+    operands
+        .iter()
+        .map(|operand| match operand.end - operand.start {
+            1 => operand.start as u32,
+            _ => (operand.start + i) as u32,
+        })
+        .collect()
+}
+
+// Lower an application of the standard gate `name` to native instructions,
+// counting them against `budget`
+pub(crate) fn apply_std_gate(
+    token: &QasmToken,
+    name: &str,
+    params: &[f64],
+    operands: &[Range<u64>],
+    budget: &Budget,
+) -> Result<Vec<Instruction>, CompileError> {
+    let (num_params, num_qubits) = match stdgates::signature(name) {
+        Some(signature) => signature,
+        None => return Err(CompileError::semantic(
+            format!("Unknown gate '{}' at {}:{}", name, token.line, token.pos),
+            Span::from(token),
+        )
+        .with_hint(
+            "supported gates are id, x, y, z, h, s, sdg, t, tdg, rx, ry, rz, u1, u2, u3, cx and cz",
+        )),
+    };
+
+    check_arity(
+        token,
+        name,
+        params.len(),
+        num_params,
+        operands.len(),
+        num_qubits,
+    )?;
+
+    // Every application costs at least one step, which bounds the fan-out
+    // before any of it is lowered
+    let size = broadcast(token, operands)?;
+    budget.spend(token, size)?;
+
+    let mut instrs = Vec::new();
+    for i in 0..size {
+        let qubits = broadcast_qubits(operands, i);
+        check_distinct(token, name, &qubits)?;
+        let lowered = stdgates::lower(name, params, &qubits);
+        budget.spend(token, (lowered.len() as u64).saturating_sub(1))?;
+        instrs.extend(lowered);
+    }
+    Ok(instrs)
+}
+
+pub(crate) fn check_arity(
+    token: &QasmToken,
+    name: &str,
+    params: usize,
+    expected_params: usize,
+    qubits: usize,
+    expected_qubits: usize,
+) -> Result<(), CompileError> {
+    if params != expected_params {
+        return Err(CompileError::semantic(
+            format!(
+                "Gate '{}' takes {} parameter(s), got {} at {}:{}",
+                name, expected_params, params, token.line, token.pos
+            ),
+            Span::from(token),
+        ));
+    }
+    if qubits != expected_qubits {
+        return Err(CompileError::semantic(
+            format!(
+                "Gate '{}' acts on {} qubit(s), got {} at {}:{}",
+                name, expected_qubits, qubits, token.line, token.pos
+            ),
+            Span::from(token),
+        ));
+    }
+    Ok(())
+}
+
+pub(crate) fn check_distinct(
+    token: &QasmToken,
+    name: &str,
+    qubits: &[u32],
+) -> Result<(), CompileError> {
+    for (i, q) in qubits.iter().enumerate() {
+        if qubits[..i].contains(q) {
+            return Err(CompileError::semantic(
+                format!(
+                    "Gate '{}' requires distinct qubits at {}:{}",
+                    name, token.line, token.pos
+                ),
+                Span::from(token),
+            )
+            .with_hint("a qubit cannot be both control and target"));
+        }
+    }
+    Ok(())
+}
+
+// Angle parameters are folded like .inq rotation angles
+pub(crate) fn check_finite(token: &QasmToken, value: f64) -> Result<f64, CompileError> {
+    if !value.is_finite() {
+        return Err(CompileError::semantic(
+            format!(
+                "Gate parameter is not a finite number at {}:{}",
+                token.line, token.pos
+            ),
+            Span::from(token),
+        )
+        .with_hint("check the angle for a division by zero"));
+    }
+    Ok(value)
+}
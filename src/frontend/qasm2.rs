@@ -0,0 +1,351 @@
+use crate::error::{CompileError, Span};
+use crate::frontend::cursor::Cursor;
+use crate::frontend::qasm_lexer::{self, QasmTokenType};
+use crate::frontend::{
+    apply_std_gate, check_finite, parse_barrier, parse_operand, resolve, Budget, Registers,
+};
+use crate::instruction::Instruction;
+
+// Parse an OpenQASM 2.0 program and lower it to native instructions. Like
+// the .inq parser, every malformed statement is reported at once.
+pub fn parse(source: &str) -> Result<Vec<Instruction>, CompileError> {
+    let tokens = qasm_lexer::tokenize(source)?;
+    let mut cursor = Cursor::new(&tokens);
+    parse_header(&mut cursor)?;
+
+    let mut registers = Registers::default();
+    let budget = Budget::default();
+    let mut program: Vec<Instruction> = Vec::new();
+    let mut errors: Vec<CompileError> = Vec::new();
+
+    while !cursor.at_end() {
+        let start = cursor.position();
+        match parse_statement(&mut cursor, &mut registers, &budget) {
+            Ok(instrs) => program.extend(instrs),
+            Err(err) => {
+                errors.push(err);
+                cursor.rewind(start);
+                cursor.skip_statement();
+            }
+        }
+    }
+
+    if !errors.is_empty() {
+        return Err(CompileError::from_errors(errors));
+    }
+    Ok(program)
+}
+
+// header := 'OPENQASM' '2.0' ';'
+fn parse_header(cursor: &mut Cursor) -> Result<(), CompileError> {
+    if !cursor.peek_ident("OPENQASM") {
+        let span = cursor.peek().map_or(Span::new(1, 1, 1), Span::from);
+        return Err(CompileError::syntax("Missing 'OPENQASM 2.0;' header", span)
+            .with_hint("start the program with 'OPENQASM 2.0;'"));
+    }
+    cursor.next();
+
+    match cursor.peek() {
+        Some(token) if token.t == QasmTokenType::Real(2.0) => {
+            cursor.next();
+        }
+        Some(
+            token @ qasm_lexer::QasmToken {
+                t: QasmTokenType::Real(_) | QasmTokenType::Integer(_),
+                ..
+            },
+        ) => {
+            return Err(CompileError::syntax(
+                format!(
+                    "Unsupported OpenQASM version at {}:{}",
+                    token.line, token.pos
+                ),
+                Span::from(token),
+            )
+            .with_hint("this frontend reads OpenQASM 2.0"))
+        }
+        _ => return Err(cursor.unexpected("a version number")),
+    }
+
+    cursor.expect(&QasmTokenType::Semicolon, "';'")?;
+    Ok(())
+}
+
+fn parse_statement(
+    cursor: &mut Cursor,
+    registers: &mut Registers,
+    budget: &Budget,
+) -> Result<Vec<Instruction>, CompileError> {
+    let (token, keyword) = cursor.expect_ident("a statement")?;
+
+    match keyword.as_str() {
+        "include" => {
+            let file = cursor.peek();
+            match file.map(|file| &file.t) {
+                Some(QasmTokenType::Str(name)) if name == "qelib1.inc" => {
+                    cursor.next();
+                }
+                Some(QasmTokenType::Str(name)) => {
+                    let file = file.unwrap();
+                    return Err(CompileError::semantic(
+                        format!(
+                            "Unsupported include \"{}\" at {}:{}",
+                            name, file.line, file.pos
+                        ),
+                        Span::from(file),
+                    )
+                    .with_hint("only \"qelib1.inc\" can be included"));
+                }
+                _ => return Err(cursor.unexpected("a file name")),
+            }
+            cursor.expect(&QasmTokenType::Semicolon, "';'")?;
+            Ok(vec![])
+        }
+
+        "qreg" | "creg" => {
+            let (name_token, name) = cursor.expect_ident("a register name")?;
+            cursor.expect(&QasmTokenType::LBracket, "'['")?;
+            let size = cursor.expect_integer("a register size")?;
+            cursor.expect(&QasmTokenType::RBracket, "']'")?;
+            cursor.expect(&QasmTokenType::Semicolon, "';'")?;
+
+            if keyword == "qreg" {
+                registers.declare_qubits(name_token, &name, size)?;
+            } else {
+                registers.declare_bits(name_token, &name, size)?;
+            }
+            Ok(vec![])
+        }
+
+        "barrier" => {
+            parse_barrier(cursor, |cursor| {
+                let operand = parse_operand(cursor)?;
+                resolve(&operand, &registers.qubits, "qubit")
+            })?;
+            Ok(vec![])
+        }
+
+        // measure := 'measure' operand '->' operand ';'
+        "measure" => {
+            let qubit_operand = parse_operand(cursor)?;
+            cursor.expect(&QasmTokenType::Arrow, "'->'")?;
+            let bit_operand = parse_operand(cursor)?;
+            cursor.expect(&QasmTokenType::Semicolon, "';'")?;
+
+            let qubits = resolve(&qubit_operand, &registers.qubits, "qubit")?;
+            let bits = resolve(&bit_operand, &registers.bits, "classical")?;
+            if qubits.end - qubits.start != bits.end - bits.start {
+                return Err(CompileError::semantic(
+                    format!(
+                        "Cannot measure {} qubit(s) into {} bit(s) at {}:{}",
+                        qubits.end - qubits.start,
+                        bits.end - bits.start,
+                        token.line,
+                        token.pos
+                    ),
+                    Span::from(token),
+                )
+                .with_hint("measure into a classical register of the same size"));
+            }
+
+            budget.spend(token, qubits.end - qubits.start)?;
+            Ok(qubits.map(|q| Instruction::MEASURE(q as u32)).collect())
+        }
+
+        "gate" | "opaque" | "reset" | "if" => Err(CompileError::syntax(
+            format!(
+                "Unsupported statement '{}' at {}:{}",
+                keyword, token.line, token.pos
+            ),
+            Span::from(token),
+        )
+        .with_hint(
+            "supported statements are include, qreg, creg, barrier, measure and gate applications",
+        )),
+
+        // gate application := name ('(' expr (',' expr)* ')')? operand (',' operand)* ';'
+        _ => {
+            let mut params: Vec<f64> = Vec::new();
+            if cursor.eat(&QasmTokenType::LParen) {
+                loop {
+                    let param_token = cursor.peek().unwrap_or(token);
+                    params.push(check_finite(param_token, cursor.expr(&[])?.eval())?);
+                    if !cursor.eat(&QasmTokenType::Comma) {
+                        break;
+                    }
+                }
+                cursor.expect(&QasmTokenType::RParen, "')'")?;
+            }
+
+            let mut operands = Vec::new();
+            loop {
+                let operand = parse_operand(cursor)?;
+                operands.push(resolve(&operand, &registers.qubits, "qubit")?);
+                if !cursor.eat(&QasmTokenType::Comma) {
+                    break;
+                }
+            }
+            cursor.expect(&QasmTokenType::Semicolon, "';'")?;
+
+            apply_std_gate(token, &keyword, &params, &operands, budget)
+        }
+    }
+}
+
+#[rustfmt::skip]
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::equivalence::testing::assert_equivalent;
+    use crate::frontend::stdgates;
+    use crate::pipeline::Pipeline;
+    use crate::simulate;
+    use std::f64::consts::PI;
+
+    const BELL: &str = "\
+OPENQASM 2.0;
+include \"qelib1.inc\";
+qreg q[2];
+creg c[2];
+h q[0];
+cx q[0], q[1];
+measure q -> c;
+";
+
+    // General tests
+
+    #[test]
+    fn parse_bell_pair() {
+        let program = parse(BELL).unwrap();
+
+        let mut expected = stdgates::lower("h", &[], &[0]);
+        expected.extend(stdgates::lower("cx", &[], &[0, 1]));
+        expected.extend([Instruction::MEASURE(0), Instruction::MEASURE(1)]);
+        assert_eq!(program, expected);
+
+        let sim = simulate::simulate(&program).unwrap();
+        let probs = sim.probabilities();
+        assert!((probs[0b00] - 0.5).abs() < 1e-12);
+        assert!((probs[0b11] - 0.5).abs() < 1e-12);
+    }
+
+    #[test]
+    fn parse_parameters_and_registers() {
+        let source = "\
+OPENQASM 2.0;
+include \"qelib1.inc\";
+qreg a[1];
+qreg b[2]; // b[0] is qubit 1
+rz(pi/2) b[1];
+rx(-0.25) a[0];
+u3(pi, 0, pi) b[0];
+x b;
+barrier a, b;
+";
+        let program = parse(source).unwrap();
+
+        let mut expected = vec![Instruction::RZ(PI/2.0, 2), Instruction::RX(-0.25, 0)];
+        expected.extend(stdgates::lower("u3", &[PI, 0.0, PI], &[1]));
+        expected.extend([Instruction::RX(PI, 1), Instruction::RX(PI, 2)]);
+        assert_eq!(program, expected);
+    }
+
+    #[test]
+    fn broadcast_two_qubit_gates() {
+        let source = "OPENQASM 2.0;\nqreg a[2];\nqreg b[2];\ncx a, b;\ncx a[0], b;\n";
+        let program = parse(source).unwrap();
+
+        let mut expected = Vec::new();
+        for (c, t) in [(0, 2), (1, 3), (0, 2), (0, 3)] {
+            expected.extend(stdgates::lower("cx", &[], &[c, t]));
+        }
+        assert_eq!(program, expected);
+    }
+
+    #[test]
+    fn compile_through_pipeline() {
+        let output = Pipeline::default().compile_qasm(BELL).unwrap();
+        let parsed = parse(BELL).unwrap();
+
+        assert!(output.program.len() <= parsed.len());
+        assert_equivalent(&parsed, &output.program);
+    }
+
+    #[test]
+    fn invalid_programs() {
+        let err = parse("qreg q[1];").unwrap_err();
+        assert_eq!(err.message(), "Missing 'OPENQASM 2.0;' header");
+        assert_eq!(err.span(), Some(Span::new(1, 1, 4)));
+
+        let err = parse("OPENQASM 3.0;").unwrap_err();
+        assert_eq!(err.message(), "Unsupported OpenQASM version at 1:10");
+
+        let err = parse("OPENQASM 2.0;\ninclude \"stdgates.inc\";").unwrap_err();
+        assert_eq!(err.message(), "Unsupported include \"stdgates.inc\" at 2:9");
+
+        let source = "\
+OPENQASM 2.0;
+qreg q[2];
+creg c[1];
+foo q[0];
+h q[2];
+cx q[0], q[0];
+rx q[0];
+measure q -> c;
+h r[0];
+qreg q[3];
+h q[1]
+";
+        let err = parse(source).unwrap_err();
+        let messages: Vec<&str> = err.errors().iter().map(|err| err.message()).collect();
+        assert_eq!(messages, vec![
+            "Unknown gate 'foo' at 4:1",
+            "Index 2 out of range for register 'q' of size 2 at 5:5",
+            "Gate 'cx' requires distinct qubits at 6:1",
+            "Gate 'rx' takes 1 parameter(s), got 0 at 7:1",
+            "Cannot measure 2 qubit(s) into 1 bit(s) at 8:1",
+            "Unknown qubit register 'r' at 9:3",
+            "Register 'q' is already declared at 10:6",
+            "Unexpected end of input, expected ';'",
+        ]);
+        assert_eq!(err.errors()[7].span(), Some(Span::new(11, 7, 1)));
+    }
+
+    #[test]
+    fn highest_qubit() {
+        let source = "OPENQASM 2.0;\nqreg a[4294967295];\nqreg b[1];\nrz(0.5) b[0];\n";
+        assert_eq!(parse(source).unwrap(), vec![Instruction::RZ(0.5, u32::MAX)]);
+
+        let source = "OPENQASM 2.0;\nqreg a[4294967295];\nqreg b[2];\ncreg c[4294967295];\ncreg d[1];\ncreg e[1];\n";
+        let err = parse(source).unwrap_err();
+        let messages: Vec<&str> = err.errors().iter().map(|err| err.message()).collect();
+        assert_eq!(messages, vec!["Too many qubits declared at 3:6", "Too many classical bits declared at 6:6"]);
+        assert_eq!(err.errors()[0].span(), Some(Span::new(3, 6, 1)));
+        assert_eq!(err.errors()[0].hint(), Some("a program may declare at most 4294967296 qubits"));
+    }
+
+    #[test]
+    fn expansion_limit() {
+        // Huge registers are checked before any of them is allocated
+        let source = "OPENQASM 2.0;\nqreg q[2000000000];\ncreg c[2000000000];\nh q;\nmeasure q -> c;\nx q[0];\n";
+        let err = parse(source).unwrap_err();
+        let messages: Vec<&str> = err.errors().iter().map(|err| err.message()).collect();
+        assert_eq!(messages, vec![
+            "Program expands beyond 1048576 instructions and loop iterations at 4:1",
+            "Program expands beyond 1048576 instructions and loop iterations at 5:1",
+        ]);
+
+        // The limit holds for the whole program, not per statement
+        let err = parse("OPENQASM 2.0;\nqreg q[1000000];\nx q;\nx q;\n").unwrap_err();
+        assert_eq!(err.message(), "Program expands beyond 1048576 instructions and loop iterations at 4:1");
+    }
+
+    #[test]
+    fn deeply_nested_angle() {
+        let source = format!("OPENQASM 2.0;\nqreg q[1];\nrz({}0{}) q[0];\n", "(".repeat(200000), ")".repeat(200000));
+        let err = parse(&source).unwrap_err();
+        assert!(matches!(err, CompileError::Syntax { .. }));
+        assert_eq!(err.message(), "Angle nested deeper than 128 levels at 3:132");
+        assert_eq!(err.span(), Some(Span::new(3, 132, 1)));
+    }
+}
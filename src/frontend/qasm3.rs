@@ -0,0 +1,1052 @@
+use std::collections::HashMap;
+use std::ops::Range;
+
+use crate::error::{CompileError, Span};
+use crate::expr::Expr;
+use crate::frontend::cursor::Cursor;
+use crate::frontend::qasm_lexer::{self, QasmToken, QasmTokenType};
+use crate::frontend::{
+    apply_std_gate, broadcast, broadcast_qubits, check_arity, check_distinct, check_finite,
+    parse_barrier, resolve, stdgates, Budget, Operand, Registers,
+};
+use crate::instruction::Instruction;
+
+// Upper bound on the iterations of a single for loop, so that a typo in a
+// range cannot unroll into millions of instructions
+pub const MAX_LOOP_ITERATIONS: i64 = 1 << 16;
+
+// Deepest nesting of for loops accepted, so that hostile input cannot
+// overflow the stack of the recursive statement parser and unroller
+pub const MAX_LOOP_DEPTH: usize = 128;
+
+// Register reference whose index may depend on loop variables
+struct Target<'a> {
+    token: &'a QasmToken,
+    name: String,
+    index: Option<(&'a QasmToken, Expr)>,
+}
+
+// Executable statement, kept until loop variables and gate parameters are
+// known
+enum Stmt<'a> {
+    Apply {
+        token: &'a QasmToken,
+        name: String,
+        params: Vec<(&'a QasmToken, Expr)>,
+        targets: Vec<Target<'a>>,
+    },
+    Measure {
+        token: &'a QasmToken,
+        qubits: Target<'a>,
+        bits: Option<Target<'a>>,
+    },
+    For {
+        token: &'a QasmToken,
+        var: String,
+        values: Vec<(&'a QasmToken, Expr)>,
+        // Set when `values` holds [start, step, end] rather than a list
+        range: bool,
+        body: Vec<Stmt<'a>>,
+    },
+}
+
+// User gate definition. Its body only applies gates to the qubit arguments.
+struct GateDef<'a> {
+    params: Vec<String>,
+    qubits: Vec<String>,
+    body: Vec<Stmt<'a>>,
+}
+
+#[derive(Default)]
+struct Frontend<'a> {
+    registers: Registers,
+    gates: HashMap<String, GateDef<'a>>,
+    budget: Budget,
+}
+
+// Parse an OpenQASM 3 program and lower it to native instructions. User
+// gates are inlined and for loops unrolled. Every malformed statement is
+// reported at once.
+//
+// Supported subset: `qubit`/`bit` (and `qreg`/`creg`) declarations,
+// `include "stdgates.inc"`, standard gate applications, `gate` definitions
+// with angle parameters, `for` loops over constant ranges or sets,
+// `barrier` and measurements (`c = measure q;`, `measure q -> c;`).
+pub fn parse(source: &str) -> Result<Vec<Instruction>, CompileError> {
+    let tokens = qasm_lexer::tokenize(source)?;
+    let mut cursor = Cursor::new(&tokens);
+    parse_header(&mut cursor)?;
+
+    let mut frontend = Frontend::default();
+    let mut program: Vec<Instruction> = Vec::new();
+    let mut errors: Vec<CompileError> = Vec::new();
+
+    while !cursor.at_end() {
+        let start = cursor.position();
+        match frontend.top_level(&mut cursor, &mut program) {
+            Ok(()) => {}
+            Err(err) => {
+                errors.push(err);
+                cursor.rewind(start);
+                cursor.skip_statement();
+            }
+        }
+    }
+
+    if !errors.is_empty() {
+        return Err(CompileError::from_errors(errors));
+    }
+    Ok(program)
+}
+
+// header := ('OPENQASM' version ';')?
+fn parse_header(cursor: &mut Cursor) -> Result<(), CompileError> {
+    if !cursor.peek_ident("OPENQASM") {
+        return Ok(());
+    }
+    cursor.next();
+
+    match cursor.peek() {
+        Some(token) if matches!(token.t, QasmTokenType::Integer(3)) => {}
+        Some(token) if matches!(token.t, QasmTokenType::Real(f) if f.trunc() == 3.0) => {}
+        Some(
+            token @ QasmToken {
+                t: QasmTokenType::Real(_) | QasmTokenType::Integer(_),
+                ..
+            },
+        ) => {
+            return Err(CompileError::syntax(
+                format!(
+                    "Unsupported OpenQASM version at {}:{}",
+                    token.line, token.pos
+                ),
+                Span::from(token),
+            )
+            .with_hint("this frontend reads OpenQASM 3"))
+        }
+        _ => return Err(cursor.unexpected("a version number")),
+    }
+    cursor.next();
+
+    cursor.expect(&QasmTokenType::Semicolon, "';'")?;
+    Ok(())
+}
+
+impl<'a> Frontend<'a> {
+    // Parse one top-level statement and append the instructions it produces
+    fn top_level(
+        &mut self,
+        cursor: &mut Cursor<'a>,
+        program: &mut Vec<Instruction>,
+    ) -> Result<(), CompileError> {
+        let token = match cursor.peek() {
+            Some(token) => token,
+            None => return Err(cursor.unexpected("a statement")),
+        };
+
+        let keyword = match &token.t {
+            QasmTokenType::Ident(keyword) => keyword.as_str(),
+            _ => return Err(cursor.unexpected("a statement")),
+        };
+
+        match keyword {
+            "include" => {
+                cursor.next();
+                let file = cursor.peek();
+                match file.map(|file| &file.t) {
+                    Some(QasmTokenType::Str(name))
+                        if name == "stdgates.inc" || name == "qelib1.inc" =>
+                    {
+                        cursor.next();
+                    }
+                    Some(QasmTokenType::Str(name)) => {
+                        let file = file.unwrap();
+                        return Err(CompileError::semantic(
+                            format!(
+                                "Unsupported include \"{}\" at {}:{}",
+                                name, file.line, file.pos
+                            ),
+                            Span::from(file),
+                        )
+                        .with_hint("only \"stdgates.inc\" can be included"));
+                    }
+                    _ => return Err(cursor.unexpected("a file name")),
+                }
+                cursor.expect(&QasmTokenType::Semicolon, "';'")?;
+                Ok(())
+            }
+
+            // declaration := ('qubit' | 'bit') ('[' Integer ']')? ident ('=' measure)? ';'
+            "qubit" | "bit" => {
+                cursor.next();
+                let mut size = 1;
+                if cursor.eat(&QasmTokenType::LBracket) {
+                    size = cursor.expect_integer("a register size")?;
+                    cursor.expect(&QasmTokenType::RBracket, "']'")?;
+                }
+                let (name_token, name) = cursor.expect_ident("a register name")?;
+
+                if keyword == "qubit" {
+                    cursor.expect(&QasmTokenType::Semicolon, "';'")?;
+                    return self.registers.declare_qubits(name_token, &name, size);
+                }
+
+                self.registers.declare_bits(name_token, &name, size)?;
+                if cursor.eat(&QasmTokenType::Equals) {
+                    let measure_token = cursor.peek().unwrap_or(name_token);
+                    if !cursor.peek_ident("measure") {
+                        return Err(cursor.unexpected("'measure'"));
+                    }
+                    cursor.next();
+                    let qubits = self.target(cursor, &[])?;
+                    cursor.expect(&QasmTokenType::Semicolon, "';'")?;
+
+                    let bits = Target {
+                        token: name_token,
+                        name,
+                        index: None,
+                    };
+                    let stmt = Stmt::Measure {
+                        token: measure_token,
+                        qubits,
+                        bits: Some(bits),
+                    };
+                    return self.run(&stmt, &mut Vec::new(), program);
+                }
+                cursor.expect(&QasmTokenType::Semicolon, "';'")?;
+                Ok(())
+            }
+
+            // Legacy QASM 2 declarations are still valid in QASM 3
+            "qreg" | "creg" => {
+                cursor.next();
+                let (name_token, name) = cursor.expect_ident("a register name")?;
+                cursor.expect(&QasmTokenType::LBracket, "'['")?;
+                let size = cursor.expect_integer("a register size")?;
+                cursor.expect(&QasmTokenType::RBracket, "']'")?;
+                cursor.expect(&QasmTokenType::Semicolon, "';'")?;
+
+                if keyword == "qreg" {
+                    self.registers.declare_qubits(name_token, &name, size)
+                } else {
+                    self.registers.declare_bits(name_token, &name, size)
+                }
+            }
+
+            "gate" => {
+                cursor.next();
+                self.gate_definition(cursor)
+            }
+
+            _ => {
+                let stmt = self.statement(cursor, &[])?;
+                if let Some(stmt) = stmt {
+                    self.run(&stmt, &mut Vec::new(), program)?;
+                }
+                Ok(())
+            }
+        }
+    }
+
+    // gate := 'gate' ident ('(' ident (',' ident)* ')')? ident (',' ident)* '{' gate_call* '}'
+    fn gate_definition(&mut self, cursor: &mut Cursor<'a>) -> Result<(), CompileError> {
+        let (name_token, name) = cursor.expect_ident("a gate name")?;
+        if stdgates::signature(&name).is_some() || self.gates.contains_key(&name) {
+            return Err(CompileError::semantic(
+                format!(
+                    "Gate '{}' is already defined at {}:{}",
+                    name, name_token.line, name_token.pos
+                ),
+                Span::from(name_token),
+            )
+            .with_hint("give the gate a different name"));
+        }
+
+        let mut params: Vec<String> = Vec::new();
+        if cursor.eat(&QasmTokenType::LParen) && !cursor.eat(&QasmTokenType::RParen) {
+            loop {
+                params.push(cursor.expect_ident("a parameter name")?.1);
+                if !cursor.eat(&QasmTokenType::Comma) {
+                    break;
+                }
+            }
+            cursor.expect(&QasmTokenType::RParen, "')'")?;
+        }
+
+        let mut qubits: Vec<String> = Vec::new();
+        loop {
+            qubits.push(cursor.expect_ident("a qubit argument")?.1);
+            if !cursor.eat(&QasmTokenType::Comma) {
+                break;
+            }
+        }
+
+        cursor.expect(&QasmTokenType::LBrace, "'{'")?;
+        let mut body: Vec<Stmt> = Vec::new();
+        while !cursor.eat(&QasmTokenType::RBrace) {
+            let (token, callee) = cursor.expect_ident("a gate application or '}'")?;
+            let call_params = self.params(cursor, &params)?;
+
+            let mut targets: Vec<Target> = Vec::new();
+            loop {
+                let (arg_token, arg) = cursor.expect_ident("a qubit argument")?;
+                if !qubits.contains(&arg) {
+                    return Err(CompileError::semantic(
+                        format!(
+                            "Unknown qubit argument '{}' at {}:{}",
+                            arg, arg_token.line, arg_token.pos
+                        ),
+                        Span::from(arg_token),
+                    )
+                    .with_hint(format!(
+                        "the qubit arguments of '{}' are {}",
+                        name,
+                        qubits.join(", ")
+                    )));
+                }
+                targets.push(Target {
+                    token: arg_token,
+                    name: arg,
+                    index: None,
+                });
+                if !cursor.eat(&QasmTokenType::Comma) {
+                    break;
+                }
+            }
+            cursor.expect(&QasmTokenType::Semicolon, "';'")?;
+
+            // Only previously defined gates may be called, which also rules
+            // out recursion
+            let (num_params, num_qubits) = self.signature(token, &callee)?;
+            check_arity(
+                token,
+                &callee,
+                call_params.len(),
+                num_params,
+                targets.len(),
+                num_qubits,
+            )?;
+
+            body.push(Stmt::Apply {
+                token,
+                name: callee,
+                params: call_params,
+                targets,
+            });
+        }
+
+        self.gates.insert(
+            name,
+            GateDef {
+                params,
+                qubits,
+                body,
+            },
+        );
+        Ok(())
+    }
+
+    // Parse an executable statement. `scope` holds the loop variables of
+    // the enclosing for loops. Statements without any effect return None.
+    fn statement(
+        &mut self,
+        cursor: &mut Cursor<'a>,
+        scope: &[String],
+    ) -> Result<Option<Stmt<'a>>, CompileError> {
+        let (token, keyword) = cursor.expect_ident("a statement")?;
+
+        match keyword.as_str() {
+            // for := 'for' type? ident 'in' (range | set) (block | statement)
+            "for" => {
+                // Every enclosing loop adds its variable to the scope
+                if scope.len() >= MAX_LOOP_DEPTH {
+                    return Err(CompileError::syntax(
+                        format!(
+                            "Loops nested deeper than {} levels at {}:{}",
+                            MAX_LOOP_DEPTH, token.line, token.pos
+                        ),
+                        Span::from(token),
+                    )
+                    .with_hint("flatten the loops"));
+                }
+                let (_, mut var) = cursor.expect_ident("a loop variable")?;
+                if !cursor.peek_ident("in") {
+                    // The first identifier was the loop variable's type
+                    var = cursor.expect_ident("a loop variable")?.1;
+                }
+                if !cursor.peek_ident("in") {
+                    return Err(cursor.unexpected("'in'"));
+                }
+                cursor.next();
+
+                let mut values: Vec<(&QasmToken, Expr)> = Vec::new();
+                let range = if cursor.eat(&QasmTokenType::LBracket) {
+                    loop {
+                        let value_token = cursor.peek().unwrap_or(token);
+                        values.push((value_token, cursor.expr(scope)?));
+                        if values.len() == 3 || !cursor.eat(&QasmTokenType::Colon) {
+                            break;
+                        }
+                    }
+                    cursor.expect(&QasmTokenType::RBracket, "']'")?;
+                    if values.len() < 2 {
+                        return Err(cursor.unexpected("':'"));
+                    }
+                    // [start:end] has an implicit step of 1
+                    if values.len() == 2 {
+                        values.insert(1, (token, Expr::Number(1.0)));
+                    }
+                    true
+                } else if cursor.eat(&QasmTokenType::LBrace) {
+                    loop {
+                        let value_token = cursor.peek().unwrap_or(token);
+                        values.push((value_token, cursor.expr(scope)?));
+                        if !cursor.eat(&QasmTokenType::Comma) {
+                            break;
+                        }
+                    }
+                    cursor.expect(&QasmTokenType::RBrace, "'}'")?;
+                    false
+                } else {
+                    return Err(cursor.unexpected("a range '[start:end]' or a set '{...}'"));
+                };
+
+                let mut inner_scope = scope.to_vec();
+                inner_scope.push(var.clone());
+
+                let mut body: Vec<Stmt> = Vec::new();
+                if cursor.eat(&QasmTokenType::LBrace) {
+                    while !cursor.eat(&QasmTokenType::RBrace) {
+                        if cursor.at_end() {
+                            return Err(cursor.unexpected("'}'"));
+                        }
+                        body.extend(self.statement(cursor, &inner_scope)?);
+                    }
+                } else {
+                    body.extend(self.statement(cursor, &inner_scope)?);
+                }
+
+                Ok(Some(Stmt::For {
+                    token,
+                    var,
+                    values,
+                    range,
+                    body,
+                }))
+            }
+
+            // measure := 'measure' target ('->' target)? ';'
+            "measure" => {
+                let qubits = self.target(cursor, scope)?;
+                let bits = if cursor.eat(&QasmTokenType::Arrow) {
+                    Some(self.target(cursor, scope)?)
+                } else {
+                    None
+                };
+                cursor.expect(&QasmTokenType::Semicolon, "';'")?;
+                Ok(Some(Stmt::Measure { token, qubits, bits }))
+            }
+
+            "barrier" => {
+                parse_barrier(cursor, |cursor| self.target(cursor, scope))?;
+                Ok(None)
+            }
+
+            "qubit" | "bit" | "qreg" | "creg" | "gate" | "include" => Err(CompileError::syntax(
+                format!("'{}' is only allowed at the top level at {}:{}", keyword, token.line, token.pos),
+                Span::from(token),
+            )
+            .with_hint("move the declaration out of the loop")),
+
+            "reset" | "if" | "while" | "def" | "const" | "ctrl" | "inv" | "pow" | "negctrl" | "int" | "uint"
+            | "float" | "angle" | "bool" | "let" | "box" | "delay" => Err(CompileError::syntax(
+                format!("Unsupported statement '{}' at {}:{}", keyword, token.line, token.pos),
+                Span::from(token),
+            )
+            .with_hint(
+                "supported statements are declarations, include, gate, for, barrier, measure and gate applications",
+            )),
+
+            // assignment := ident ('[' expr ']')? '=' 'measure' target ';'
+            _ if cursor.peek_is(&QasmTokenType::Equals) || cursor.peek_is(&QasmTokenType::LBracket) => {
+                let bits = self.target_after_name(cursor, token, &keyword, scope)?;
+                cursor.expect(&QasmTokenType::Equals, "'='")?;
+                let measure_token = cursor.peek().unwrap_or(token);
+                if !cursor.peek_ident("measure") {
+                    return Err(cursor.unexpected("'measure'"));
+                }
+                cursor.next();
+                let qubits = self.target(cursor, scope)?;
+                cursor.expect(&QasmTokenType::Semicolon, "';'")?;
+
+                Ok(Some(Stmt::Measure {
+                    token: measure_token,
+                    qubits,
+                    bits: Some(bits),
+                }))
+            }
+
+            // gate application := ident ('(' expr (',' expr)* ')')? target (',' target)* ';'
+            _ => {
+                let params = self.params(cursor, scope)?;
+                let mut targets: Vec<Target> = Vec::new();
+                loop {
+                    targets.push(self.target(cursor, scope)?);
+                    if !cursor.eat(&QasmTokenType::Comma) {
+                        break;
+                    }
+                }
+                cursor.expect(&QasmTokenType::Semicolon, "';'")?;
+
+                let (num_params, num_qubits) = self.signature(token, &keyword)?;
+                check_arity(token, &keyword, params.len(), num_params, targets.len(), num_qubits)?;
+
+                Ok(Some(Stmt::Apply {
+                    token,
+                    name: keyword,
+                    params,
+                    targets,
+                }))
+            }
+        }
+    }
+
+    // Optional parenthesized list of angle expressions
+    fn params(
+        &self,
+        cursor: &mut Cursor<'a>,
+        scope: &[String],
+    ) -> Result<Vec<(&'a QasmToken, Expr)>, CompileError> {
+        let mut params = Vec::new();
+        if cursor.eat(&QasmTokenType::LParen) && !cursor.eat(&QasmTokenType::RParen) {
+            loop {
+                match cursor.peek() {
+                    Some(token) => params.push((token, cursor.expr(scope)?)),
+                    None => return Err(cursor.unexpected("an angle")),
+                }
+                if !cursor.eat(&QasmTokenType::Comma) {
+                    break;
+                }
+            }
+            cursor.expect(&QasmTokenType::RParen, "')'")?;
+        }
+        Ok(params)
+    }
+
+    // target := ident ('[' expr ']')?
+    fn target(
+        &self,
+        cursor: &mut Cursor<'a>,
+        scope: &[String],
+    ) -> Result<Target<'a>, CompileError> {
+        let (token, name) = cursor.expect_ident("a register")?;
+        self.target_after_name(cursor, token, &name, scope)
+    }
+
+    fn target_after_name(
+        &self,
+        cursor: &mut Cursor<'a>,
+        token: &'a QasmToken,
+        name: &str,
+        scope: &[String],
+    ) -> Result<Target<'a>, CompileError> {
+        let mut index = None;
+        if cursor.eat(&QasmTokenType::LBracket) {
+            match cursor.peek() {
+                Some(index_token) => index = Some((index_token, cursor.expr(scope)?)),
+                None => return Err(cursor.unexpected("an index")),
+            }
+            cursor.expect(&QasmTokenType::RBracket, "']'")?;
+        }
+
+        Ok(Target {
+            token,
+            name: name.to_string(),
+            index,
+        })
+    }
+
+    // Number of parameters and qubits of a standard or user gate
+    fn signature(&self, token: &QasmToken, name: &str) -> Result<(usize, usize), CompileError> {
+        if let Some(def) = self.gates.get(name) {
+            return Ok((def.params.len(), def.qubits.len()));
+        }
+        stdgates::signature(name).ok_or_else(|| {
+            CompileError::semantic(
+                format!("Unknown gate '{}' at {}:{}", name, token.line, token.pos),
+                Span::from(token),
+            )
+            .with_hint("define the gate with 'gate' before using it")
+        })
+    }
+
+    // Execute a statement, binding loop variables in `vars`
+    fn run(
+        &self,
+        stmt: &Stmt,
+        vars: &mut Vec<(String, f64)>,
+        program: &mut Vec<Instruction>,
+    ) -> Result<(), CompileError> {
+        match stmt {
+            Stmt::Apply {
+                token,
+                name,
+                params,
+                targets,
+            } => {
+                let params = params
+                    .iter()
+                    .map(|(token, expr)| check_finite(token, eval(token, expr, vars)?))
+                    .collect::<Result<Vec<f64>, CompileError>>()?;
+                let operands = targets
+                    .iter()
+                    .map(|target| self.resolve(target, vars, true))
+                    .collect::<Result<Vec<Range<u64>>, CompileError>>()?;
+
+                self.apply(token, name, &params, &operands, program)
+            }
+
+            Stmt::Measure {
+                token,
+                qubits,
+                bits,
+            } => {
+                let qubits = self.resolve(qubits, vars, true)?;
+                if let Some(bits) = bits {
+                    let bits = self.resolve(bits, vars, false)?;
+                    if qubits.end - qubits.start != bits.end - bits.start {
+                        return Err(CompileError::semantic(
+                            format!(
+                                "Cannot measure {} qubit(s) into {} bit(s) at {}:{}",
+                                qubits.end - qubits.start,
+                                bits.end - bits.start,
+                                token.line,
+                                token.pos
+                            ),
+                            Span::from(*token),
+                        )
+                        .with_hint("measure into a classical register of the same size"));
+                    }
+                }
+                self.budget.spend(token, qubits.end - qubits.start)?;
+                program.extend(qubits.map(|q| Instruction::MEASURE(q as u32)));
+                Ok(())
+            }
+
+            Stmt::For {
+                token,
+                var,
+                values,
+                range,
+                body,
+            } => {
+                let range_values = values;
+                let values = values
+                    .iter()
+                    .map(|(token, expr)| integer(token, eval(token, expr, vars)?))
+                    .collect::<Result<Vec<i64>, CompileError>>()?;
+
+                let iterations: Vec<i64> = if *range {
+                    let (start, step, end) = (values[0], values[1], values[2]);
+                    if step == 0 {
+                        return Err(CompileError::semantic(
+                            format!("Loop range has a step of 0 at {}:{}", token.line, token.pos),
+                            Span::from(*token),
+                        )
+                        .with_hint("use a non-zero step, e.g. [0:2:10]"));
+                    }
+                    let count = if (step > 0 && start > end) || (step < 0 && start < end) {
+                        Some(0)
+                    } else {
+                        end.checked_sub(start)
+                            .and_then(|len| len.checked_div(step))
+                            .and_then(|count| count.checked_add(1))
+                    };
+                    let count = match count {
+                        Some(count) => count,
+                        None => {
+                            let range_token = range_values[0].0;
+                            return Err(CompileError::semantic(
+                                format!(
+                                    "Loop range [{}:{}:{}] is too large at {}:{}",
+                                    start, step, end, range_token.line, range_token.pos
+                                ),
+                                Span::from(range_token),
+                            )
+                            .with_hint(format!(
+                                "loops may run at most {} iterations",
+                                MAX_LOOP_ITERATIONS
+                            )));
+                        }
+                    };
+                    if count > MAX_LOOP_ITERATIONS {
+                        return Err(CompileError::semantic(
+                            format!(
+                                "Loop of {} iterations exceeds the limit of {} at {}:{}",
+                                count, MAX_LOOP_ITERATIONS, token.line, token.pos
+                            ),
+                            Span::from(*token),
+                        ));
+                    }
+                    (0..count).map(|i| start + i * step).collect()
+                } else {
+                    values
+                };
+
+                for value in iterations {
+                    self.budget.spend(token, 1)?;
+                    vars.push((var.clone(), value as f64));
+                    for stmt in body {
+                        self.run(stmt, vars, program)?;
+                    }
+                    vars.pop();
+                }
+                Ok(())
+            }
+        }
+    }
+
+    // Apply a standard or user gate, inlining the body of user gates
+    fn apply(
+        &self,
+        token: &QasmToken,
+        name: &str,
+        params: &[f64],
+        operands: &[Range<u64>],
+        program: &mut Vec<Instruction>,
+    ) -> Result<(), CompileError> {
+        let def = match self.gates.get(name) {
+            Some(def) => def,
+            None => {
+                program.extend(apply_std_gate(token, name, params, operands, &self.budget)?);
+                return Ok(());
+            }
+        };
+
+        let size = broadcast(token, operands)?;
+        self.budget.spend(token, size)?;
+        for i in 0..size {
+            let qubits = broadcast_qubits(operands, i);
+            check_distinct(token, name, &qubits)?;
+
+            for stmt in &def.body {
+                if let Stmt::Apply {
+                    token,
+                    name,
+                    params: call_params,
+                    targets,
+                } = stmt
+                {
+                    let lookup = |var: &str| {
+                        let i = def.params.iter().position(|param| param == var)?;
+                        Some(params[i])
+                    };
+                    let call_params = call_params
+                        .iter()
+                        .map(|(token, expr)| {
+                            let value = expr
+                                .eval_with(&lookup)
+                                .map_err(|var| unbound(token, &var))?;
+                            check_finite(token, value)
+                        })
+                        .collect::<Result<Vec<f64>, CompileError>>()?;
+                    let call_operands: Vec<Range<u64>> = targets
+                        .iter()
+                        .map(|target| {
+                            let i = def
+                                .qubits
+                                .iter()
+                                .position(|arg| *arg == target.name)
+                                .unwrap();
+                            qubits[i] as u64..qubits[i] as u64 + 1
+                        })
+                        .collect();
+
+                    self.apply(token, name, &call_params, &call_operands, program)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    // Qubit (or bit) indices of a target once loop variables are bound
+    fn resolve(
+        &self,
+        target: &Target,
+        vars: &[(String, f64)],
+        quantum: bool,
+    ) -> Result<Range<u64>, CompileError> {
+        let index = match &target.index {
+            Some((token, expr)) => {
+                let value = integer(token, eval(token, expr, vars)?)?;
+                if value < 0 || value > u32::MAX as i64 {
+                    return Err(CompileError::semantic(
+                        format!(
+                            "Index {} is out of range at {}:{}",
+                            value, token.line, token.pos
+                        ),
+                        Span::from(*token),
+                    ));
+                }
+                Some((*token, value as u32))
+            }
+            None => None,
+        };
+
+        let operand = Operand {
+            token: target.token,
+            name: target.name.clone(),
+            index,
+        };
+        if quantum {
+            resolve(&operand, &self.registers.qubits, "qubit")
+        } else {
+            resolve(&operand, &self.registers.bits, "classical")
+        }
+    }
+}
+
+fn eval(token: &QasmToken, expr: &Expr, vars: &[(String, f64)]) -> Result<f64, CompileError> {
+    let lookup = |name: &str| {
+        vars.iter()
+            .rev()
+            .find(|(var, _)| var == name)
+            .map(|(_, value)| *value)
+    };
+    expr.eval_with(&lookup).map_err(|var| unbound(token, &var))
+}
+
+fn unbound(token: &QasmToken, var: &str) -> CompileError {
+    CompileError::semantic(
+        format!(
+            "Variable '{}' has no value at {}:{}",
+            var, token.line, token.pos
+        ),
+        Span::from(token),
+    )
+}
+
+// Loop bounds and indices must be whole numbers
+fn integer(token: &QasmToken, value: f64) -> Result<i64, CompileError> {
+    if value.fract() != 0.0 || !value.is_finite() || value.abs() > i64::MAX as f64 {
+        return Err(CompileError::semantic(
+            format!(
+                "Expected an integer, got {} at {}:{}",
+                value, token.line, token.pos
+            ),
+            Span::from(token),
+        )
+        .with_hint("loop bounds and register indices must be whole numbers"));
+    }
+    Ok(value as i64)
+}
+
+#[rustfmt::skip]
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::frontend::parse_qasm;
+    use crate::pipeline::Pipeline;
+    use std::f64::consts::PI;
+
+    fn lower(name: &str, params: &[f64], qubits: &[u32]) -> Vec<Instruction> {
+        stdgates::lower(name, params, qubits)
+    }
+
+    fn messages(err: &CompileError) -> Vec<&str> {
+        err.errors().iter().map(|err| err.message()).collect()
+    }
+
+    // General tests
+
+    #[test]
+    fn parse_bell_pair() {
+        let source = "\
+OPENQASM 3.0;
+include \"stdgates.inc\";
+qubit[2] q;
+bit[2] c;
+h q[0];
+cx q[0], q[1];
+c = measure q;
+";
+        let mut expected = lower("h", &[], &[0]);
+        expected.extend(lower("cx", &[], &[0, 1]));
+        expected.extend([Instruction::MEASURE(0), Instruction::MEASURE(1)]);
+
+        assert_eq!(parse(source).unwrap(), expected);
+        assert_eq!(parse_qasm(source).unwrap(), expected);
+    }
+
+    #[test]
+    fn inline_gate_definitions() {
+        let source = "\
+OPENQASM 3;
+qubit a;
+qubit[2] q;
+gate rot(theta, phi) t {
+    rz(theta) t;
+    rx(phi/2) t;
+}
+gate twice(theta) x, y {
+    rot(theta, -theta) x;
+    cz x, y;
+    rot(2*theta, pi) y;
+}
+rot(pi, 0.5) q[1];
+twice(0.25) a, q[0];
+";
+        assert_eq!(parse(source).unwrap(), vec![
+            Instruction::RZ(PI, 2),
+            Instruction::RX(0.25, 2),
+            Instruction::RZ(0.25, 0),
+            Instruction::RX(-0.125, 0),
+            Instruction::CZ(0, 1),
+            Instruction::RZ(0.5, 1),
+            Instruction::RX(PI/2.0, 1),
+        ]);
+    }
+
+    #[test]
+    fn unroll_for_loops() {
+        let source = "\
+qubit[4] q;
+for uint i in [0:2] {
+    rz(i*pi/4) q[i];
+    barrier q[i], q;
+}
+barrier;
+for int i in [3:-2:0] rx(i) q[i];
+for i in {1, 3} {
+    for uint j in [0:0] {
+        cz q[j], q[i];
+    }
+}
+";
+        assert_eq!(parse(source).unwrap(), vec![
+            Instruction::RZ(0.0, 0),
+            Instruction::RZ(PI/4.0, 1),
+            Instruction::RZ(2.0*PI/4.0, 2),
+            Instruction::RX(3.0, 3),
+            Instruction::RX(1.0, 1),
+            Instruction::CZ(0, 1),
+            Instruction::CZ(0, 3),
+        ]);
+    }
+
+    #[test]
+    fn measure_assignments() {
+        let source = "\
+qubit[2] q;
+bit[2] c;
+bit b = measure q[1];
+c[1] = measure q[0];
+measure q[1] -> c[0];
+measure q;
+";
+        assert_eq!(parse(source).unwrap(), vec![
+            Instruction::MEASURE(1),
+            Instruction::MEASURE(0),
+            Instruction::MEASURE(1),
+            Instruction::MEASURE(0),
+            Instruction::MEASURE(1),
+        ]);
+    }
+
+    #[test]
+    fn compile_through_pipeline() {
+        let source = "qubit[2] q;\nfor uint i in [0:3] { cz q[0], q[1]; }\nmeasure q;";
+        let output = Pipeline::default().compile_qasm(source).unwrap();
+        assert_eq!(output.program, vec![Instruction::MEASURE(0), Instruction::MEASURE(1)]);
+    }
+
+    #[test]
+    fn invalid_programs() {
+        let err = parse("OPENQASM 4.0;").unwrap_err();
+        assert_eq!(err.message(), "Unsupported OpenQASM version at 1:10");
+
+        let source = "\
+qubit[2] q;
+gate g a { g a; }
+for uint i in [0:2] { x q[i]; }
+for uint i in [0:0:2] { x q[i]; }
+rz(theta) q[0];
+gate r(t) a { rz(t) b; }
+bit[1] c;
+c = measure q;
+for uint i in [0:1] { qubit r; }
+h q[1]
+";
+        let err = parse(source).unwrap_err();
+        assert_eq!(messages(&err), vec![
+            "Unknown gate 'g' at 2:12",
+            "Index 2 out of range for register 'q' of size 2 at 3:27",
+            "Loop range has a step of 0 at 4:1",
+            "Unknown identifier 'theta' at 5:4",
+            "Unknown qubit argument 'b' at 6:21",
+            "Cannot measure 2 qubit(s) into 1 bit(s) at 8:5",
+            "'qubit' is only allowed at the top level at 9:23",
+            "Unexpected end of input, expected ';'",
+        ]);
+
+        let err = parse("qubit[70000] q;\nfor uint i in [0:69999] { x q[i]; }").unwrap_err();
+        assert_eq!(err.message(), "Loop of 70000 iterations exceeds the limit of 65536 at 2:1");
+
+        let err = parse("qubit[2] q;\nfor uint i in [0:1] { x q[i/2]; }").unwrap_err();
+        assert_eq!(err.message(), "Expected an integer, got 0.5 at 2:27");
+    }
+
+    #[test]
+    fn loop_range_overflow() {
+        let err = parse("for int i in [-9e18:9e18] { }").unwrap_err();
+        assert_eq!(err.message(), "Loop range [-9000000000000000000:1:9000000000000000000] is too large at 1:15");
+        assert_eq!(err.span(), Some(Span::new(1, 15, 1)));
+    }
+
+    #[test]
+    fn expansion_limits() {
+        // Loops that are each within the limit but nest too deep
+        let source = "qubit q;\nfor uint i in [0:60000] {\n    for uint j in [0:60000] { }\n}";
+        let err = parse(source).unwrap_err();
+        assert_eq!(err.message(), "Program expands beyond 1048576 instructions and loop iterations at 3:5");
+
+        // Gates that each call the previous one twice
+        let mut source = String::from("qubit q;\ngate g0 a { x a; }\n");
+        for i in 1..40 {
+            source.push_str(&format!("gate g{} a {{ g{} a; g{} a; }}\n", i, i - 1, i - 1));
+        }
+        source.push_str("g39 q;");
+        let err = parse(&source).unwrap_err();
+        assert_eq!(err.message(), "Program expands beyond 1048576 instructions and loop iterations at 5:19");
+        assert_eq!(err.span(), Some(Span::new(5, 19, 2)));
+
+        // Huge registers are checked before any of them is allocated
+        let err = parse("qubit[2000000000] q;\nh q;\nmeasure q;\nfor uint i in [0:1] { x q; }").unwrap_err();
+        assert_eq!(messages(&err), vec![
+            "Program expands beyond 1048576 instructions and loop iterations at 2:1",
+            "Program expands beyond 1048576 instructions and loop iterations at 3:1",
+            "Program expands beyond 1048576 instructions and loop iterations at 4:23",
+        ]);
+    }
+
+    #[test]
+    fn deeply_nested_angle() {
+        let source = format!("qubit q;\ngate g(t) a {{ rz({}t) a; }}\ng(0) q;", "-".repeat(200000));
+        let err = parse(&source).unwrap_err();
+        assert_eq!(messages(&err), vec!["Angle nested deeper than 128 levels at 2:146", "Unknown gate 'g' at 3:1"]);
+        assert!(matches!(err.errors()[0], CompileError::Syntax { .. }));
+
+        let source = format!("qubit q;\nrz({}pi{}) q;", "(".repeat(200000), ")".repeat(200000));
+        assert_eq!(parse(&source).unwrap_err().message(), "Angle nested deeper than 128 levels at 2:132");
+    }
+
+    #[test]
+    fn deeply_nested_loops() {
+        let nested = |depth: usize| format!("qubit q;\n{}measure q;{}", "for uint i in [0:0] { ".repeat(depth), " }".repeat(depth));
+        assert_eq!(parse(&nested(128)).unwrap(), vec![Instruction::MEASURE(0)]);
+
+        let err = parse(&nested(200000)).unwrap_err();
+        assert_eq!(err.message(), "Loops nested deeper than 128 levels at 2:2817");
+        assert_eq!(err.span(), Some(Span::new(2, 2817, 3)));
+    }
+}
@@ -0,0 +1,180 @@
+use crate::error::{CompileError, Span};
+use crate::lexer::parse_number;
+use crate::token::TokenType;
+
+// Tokens shared by the OpenQASM 2 and 3 frontends. Keywords are lexed as
+// identifiers and recognized by the parsers.
+#[derive(Debug, PartialEq, Clone)]
+pub enum QasmTokenType {
+    Ident(String),
+    Real(f64),
+    Integer(u32),
+    Str(String),
+
+    LParen,
+    RParen,
+    LBracket,
+    RBracket,
+    LBrace,
+    RBrace,
+    Semicolon,
+    Comma,
+    Colon,
+    Arrow,
+    Equals,
+    Plus,
+    Minus,
+    Star,
+    Slash,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct QasmToken {
+    pub t: QasmTokenType,
+    pub line: u32,
+    pub pos: u32,
+    pub len: usize,
+}
+
+impl From<&QasmToken> for Span {
+    fn from(token: &QasmToken) -> Span {
+        Span::new(token.line, token.pos, token.len)
+    }
+}
+
+// Split OpenQASM source into tokens. Line breaks are insignificant,
+// statements are terminated by ';'. Comments are '// ...' and '/* ... */'.
+pub fn tokenize(source: &str) -> Result<Vec<QasmToken>, CompileError> {
+    let mut tokens: Vec<QasmToken> = Vec::new();
+    // Position of the '/*' of a block comment that is still open
+    let mut block_comment: Option<(usize, usize)> = None;
+
+    for (lineno, line) in source.lines().enumerate() {
+        let chars: Vec<char> = line.chars().collect();
+        let mut i = 0;
+
+        while i < chars.len() {
+            let ch = chars[i];
+            let start = i;
+            i += 1;
+
+            if block_comment.is_some() {
+                if ch == '*' && chars.get(i) == Some(&'/') {
+                    i += 1;
+                    block_comment = None;
+                }
+                continue;
+            }
+
+            if ch.is_whitespace() {
+                continue;
+            }
+
+            let t = match ch {
+                '/' if chars.get(i) == Some(&'/') => break,
+                '/' if chars.get(i) == Some(&'*') => {
+                    i += 1;
+                    block_comment = Some((lineno + 1, start + 1));
+                    continue;
+                }
+                ch if ch.is_alphabetic() || ch == '_' => {
+                    while i < chars.len() && (chars[i].is_alphanumeric() || chars[i] == '_') {
+                        i += 1;
+                    }
+                    QasmTokenType::Ident(chars[start..i].iter().collect())
+                }
+                ch if ch.is_ascii_digit()
+                    || (ch == '.' && chars.get(i).is_some_and(|c| c.is_ascii_digit())) =>
+                {
+                    while i < chars.len() {
+                        let exponent_sign = (chars[i] == '+' || chars[i] == '-')
+                            && matches!(chars[i - 1], 'e' | 'E');
+                        if !(chars[i].is_alphanumeric()
+                            || chars[i] == '.'
+                            || chars[i] == '_'
+                            || exponent_sign)
+                        {
+                            break;
+                        }
+                        i += 1;
+                    }
+
+                    let text: String = chars[start..i].iter().collect();
+                    match parse_number(&text) {
+                        Ok(TokenType::Float(f)) => QasmTokenType::Real(f),
+                        Ok(TokenType::Integer(u)) => QasmTokenType::Integer(u),
+                        Ok(_) => unreachable!(),
+                        Err(err) => {
+                            return Err(CompileError::lexical(
+                                format!(
+                                    "{} at {}:{} \"{}\"",
+                                    err.message(),
+                                    lineno + 1,
+                                    start + 1,
+                                    text
+                                ),
+                                Span::new(lineno as u32 + 1, start as u32 + 1, i - start),
+                            )
+                            .with_hint(err.hint()))
+                        }
+                    }
+                }
+                '"' => {
+                    while i < chars.len() && chars[i] != '"' {
+                        i += 1;
+                    }
+                    if i == chars.len() {
+                        return Err(CompileError::lexical(
+                            format!("Unterminated string at {}:{}", lineno + 1, start + 1),
+                            Span::new(lineno as u32 + 1, start as u32 + 1, i - start),
+                        )
+                        .with_hint("close the string with '\"'"));
+                    }
+                    i += 1;
+                    QasmTokenType::Str(chars[start + 1..i - 1].iter().collect())
+                }
+                '-' if chars.get(i) == Some(&'>') => {
+                    i += 1;
+                    QasmTokenType::Arrow
+                }
+                '(' => QasmTokenType::LParen,
+                ')' => QasmTokenType::RParen,
+                '[' => QasmTokenType::LBracket,
+                ']' => QasmTokenType::RBracket,
+                '{' => QasmTokenType::LBrace,
+                '}' => QasmTokenType::RBrace,
+                ';' => QasmTokenType::Semicolon,
+                ',' => QasmTokenType::Comma,
+                ':' => QasmTokenType::Colon,
+                '=' => QasmTokenType::Equals,
+                '+' => QasmTokenType::Plus,
+                '-' => QasmTokenType::Minus,
+                '*' => QasmTokenType::Star,
+                '/' => QasmTokenType::Slash,
+                _ => {
+                    return Err(CompileError::lexical(
+                        format!("Undefined token at {}:{} \"{}\"", lineno + 1, start + 1, ch),
+                        Span::new(lineno as u32 + 1, start as u32 + 1, 1),
+                    ))
+                }
+            };
+
+            tokens.push(QasmToken {
+                t,
+                line: lineno as u32 + 1,
+                pos: start as u32 + 1,
+                len: i - start,
+            });
+        }
+    }
+
+    if let Some((line, pos)) = block_comment {
+        return Err(CompileError::lexical(
+            format!("Unterminated block comment at {}:{}", line, pos),
+            Span::new(line as u32, pos as u32, 2),
+        )
+        .with_hint("close the comment with '*/'"));
+    }
+
+    Ok(tokens)
+}
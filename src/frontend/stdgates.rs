@@ -0,0 +1,134 @@
+use std::f64::consts::PI;
+
+use crate::instruction::Instruction;
+
+// Number of angle parameters and qubits taken by a standard gate, or None
+// if the gate is not known. Covers the gates of qelib1.inc and stdgates.inc
+// that have a direct RX/RZ/CZ decomposition.
+pub fn signature(name: &str) -> Option<(usize, usize)> {
+    match name {
+        "id" | "x" | "y" | "z" | "h" | "s" | "sdg" | "t" | "tdg" => Some((0, 1)),
+        "rx" | "ry" | "rz" | "u1" | "p" => Some((1, 1)),
+        "u2" => Some((2, 1)),
+        "u3" | "u" | "U" => Some((3, 1)),
+        "cx" | "CX" | "cz" => Some((0, 2)),
+        _ => None,
+    }
+}
+
+// Decompose a standard gate into native instructions, in time order. The
+// result matches the gate up to global phase. `params` and `qubits` must
+// have the lengths given by `signature`.
+pub fn lower(name: &str, params: &[f64], qubits: &[u32]) -> Vec<Instruction> {
+    let q = qubits[0];
+    match name {
+        "id" => vec![],
+        "x" => vec![Instruction::RX(PI, q)],
+        // Y = iXZ
+        "y" => vec![Instruction::RZ(PI, q), Instruction::RX(PI, q)],
+        "z" => vec![Instruction::RZ(PI, q)],
+        "s" => vec![Instruction::RZ(PI / 2.0, q)],
+        "sdg" => vec![Instruction::RZ(-PI / 2.0, q)],
+        "t" => vec![Instruction::RZ(PI / 4.0, q)],
+        "tdg" => vec![Instruction::RZ(-PI / 4.0, q)],
+        "h" => hadamard(q),
+        "rx" => vec![Instruction::RX(params[0], q)],
+        "ry" => ry(params[0], q),
+        "rz" | "u1" | "p" => vec![Instruction::RZ(params[0], q)],
+        "u2" => u3(PI / 2.0, params[0], params[1], q),
+        "u3" | "u" | "U" => u3(params[0], params[1], params[2], q),
+        "cx" | "CX" => {
+            let mut instrs = hadamard(qubits[1]);
+            instrs.push(Instruction::CZ(qubits[0], qubits[1]));
+            instrs.extend(hadamard(qubits[1]));
+            instrs
+        }
+        "cz" => vec![Instruction::CZ(qubits[0], qubits[1])],
+        _ => unreachable!("unknown standard gate {}", name),
+    }
+}
+
+// H = RZ(PI/2) RX(PI/2) RZ(PI/2) up to global phase
+fn hadamard(q: u32) -> Vec<Instruction> {
+    vec![
+        Instruction::RZ(PI / 2.0, q),
+        Instruction::RX(PI / 2.0, q),
+        Instruction::RZ(PI / 2.0, q),
+    ]
+}
+
+// Conjugating RX by RZ(PI/2) rotates its axis onto Y
+fn ry(theta: f64, q: u32) -> Vec<Instruction> {
+    vec![
+        Instruction::RZ(-PI / 2.0, q),
+        Instruction::RX(theta, q),
+        Instruction::RZ(PI / 2.0, q),
+    ]
+}
+
+// U3(theta, phi, lambda) = RZ(phi) RY(theta) RZ(lambda)
+fn u3(theta: f64, phi: f64, lambda: f64, q: u32) -> Vec<Instruction> {
+    let mut instrs = vec![Instruction::RZ(lambda, q)];
+    instrs.extend(ry(theta, q));
+    instrs.push(Instruction::RZ(phi, q));
+    instrs
+}
+
+#[rustfmt::skip]
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::complex::Complex;
+    use crate::equivalence::testing::assert_equivalent;
+    use crate::simulate::simulate;
+
+    const EPS: f64 = 1e-12;
+
+    fn gate(name: &str, params: &[f64], qubits: &[u32]) -> Vec<Instruction> {
+        let (num_params, num_qubits) = signature(name).unwrap();
+        assert_eq!((params.len(), qubits.len()), (num_params, num_qubits));
+        lower(name, params, qubits)
+    }
+
+    fn seq(gates: &[Vec<Instruction>]) -> Vec<Instruction> {
+        gates.concat()
+    }
+
+    #[test]
+    fn pauli_and_phase_identities() {
+        let h = gate("h", &[], &[0]);
+
+        assert_equivalent(&seq(&[h.clone(), h.clone()]), &[]);
+        assert_equivalent(&seq(&[h.clone(), gate("x", &[], &[0]), h.clone()]), &gate("z", &[], &[0]));
+        assert_equivalent(&seq(&[gate("s", &[], &[0]), gate("s", &[], &[0])]), &gate("z", &[], &[0]));
+        assert_equivalent(&seq(&[gate("t", &[], &[0]), gate("t", &[], &[0])]), &gate("s", &[], &[0]));
+        assert_equivalent(&seq(&[gate("t", &[], &[0]), gate("tdg", &[], &[0])]), &[]);
+        assert_equivalent(&gate("y", &[], &[0]), &gate("ry", &[PI], &[0]));
+        assert_equivalent(&gate("u3", &[PI/2.0, 0.0, PI], &[0]), &h);
+        assert_equivalent(&gate("u2", &[0.0, PI], &[0]), &h);
+        assert_equivalent(&gate("u1", &[0.3], &[0]), &gate("rz", &[0.3], &[0]));
+    }
+
+    #[test]
+    fn u3_state() {
+        // U3|0> = cos(theta/2)|0> + e^(i*phi) sin(theta/2)|1>
+        let (theta, phi, lambda) = (0.7, 1.1, -0.4);
+        let sim = simulate(&gate("u3", &[theta, phi, lambda], &[0])).unwrap();
+        let (a0, a1) = (sim.amplitudes()[0], sim.amplitudes()[1]);
+
+        assert!((a0.abs() - (theta/2.0).cos()).abs() < EPS);
+        assert!((a1.abs() - (theta/2.0).sin()).abs() < EPS);
+        let relative = a1 * a0.conj();
+        assert!(relative.scale(1.0 / relative.abs()).approx_eq(Complex::cis(phi), EPS));
+    }
+
+    #[test]
+    fn cx_flips_target() {
+        let program = seq(&[gate("x", &[], &[0]), gate("cx", &[], &[0, 1])]);
+        let sim = simulate(&program).unwrap();
+        assert!((sim.probabilities()[0b11] - 1.0).abs() < EPS);
+
+        let sim = simulate(&seq(&[gate("x", &[], &[1]), gate("cx", &[], &[0, 1])])).unwrap();
+        assert!((sim.probabilities()[0b10] - 1.0).abs() < EPS);
+    }
+}
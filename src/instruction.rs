@@ -9,6 +9,17 @@ pub enum Instruction {
     MEASURE(u32),
 }
 
+impl Instruction {
+    // Qubits the instruction acts on, in operand order
+    pub fn qubits(&self) -> Vec<u32> {
+        match *self {
+            Instruction::RX(_, q) | Instruction::RZ(_, q) | Instruction::MEASURE(q) => vec![q],
+            Instruction::CZ(q1, q2) => vec![q1, q2],
+            Instruction::INVALID => vec![],
+        }
+    }
+}
+
 impl std::fmt::Display for Instruction {
     fn fmt(&self, ftr: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
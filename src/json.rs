@@ -0,0 +1,371 @@
+use crate::error::{CompileError, Span};
+
+// Minimal JSON reader and writer helpers, enough to exchange programs
+// without pulling in external crates
+
+// Deepest nesting of arrays and objects accepted, so that hostile input
+// cannot overflow the stack of the recursive reader
+pub const MAX_DEPTH: usize = 128;
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum Value {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<Json>),
+    // Members in document order
+    Object(Vec<(String, Json)>),
+}
+
+// JSON value along with the location where it starts
+#[derive(Debug, PartialEq, Clone)]
+pub struct Json {
+    pub value: Value,
+    pub span: Span,
+}
+
+impl Json {
+    // Member `key` of an object
+    pub fn get(&self, key: &str) -> Option<&Json> {
+        match &self.value {
+            Value::Object(members) => members
+                .iter()
+                .find(|(name, _)| name == key)
+                .map(|(_, value)| value),
+            _ => None,
+        }
+    }
+
+    // Name of the value's type, for error messages
+    pub fn type_name(&self) -> &'static str {
+        match self.value {
+            Value::Null => "null",
+            Value::Bool(_) => "a boolean",
+            Value::Number(_) => "a number",
+            Value::String(_) => "a string",
+            Value::Array(_) => "an array",
+            Value::Object(_) => "an object",
+        }
+    }
+}
+
+// Parse a complete JSON document
+pub fn parse(source: &str) -> Result<Json, CompileError> {
+    let mut reader = Reader {
+        chars: source.chars().collect(),
+        index: 0,
+        line: 1,
+        pos: 1,
+    };
+
+    let value = reader.value(0)?;
+    reader.skip_whitespace();
+    if reader.peek().is_some() {
+        return Err(reader.error(
+            "Unexpected trailing characters",
+            "a JSON document holds a single value",
+        ));
+    }
+    Ok(value)
+}
+
+// Quote and escape a string for output
+pub fn quote(text: &str) -> String {
+    let mut output = String::from("\"");
+    for ch in text.chars() {
+        match ch {
+            '"' => output.push_str("\\\""),
+            '\\' => output.push_str("\\\\"),
+            '\n' => output.push_str("\\n"),
+            '\r' => output.push_str("\\r"),
+            '\t' => output.push_str("\\t"),
+            ch if (ch as u32) < 0x20 => output.push_str(&format!("\\u{:04x}", ch as u32)),
+            ch => output.push(ch),
+        }
+    }
+    output.push('"');
+    output
+}
+
+struct Reader {
+    chars: Vec<char>,
+    index: usize,
+    line: u32,
+    pos: u32,
+}
+
+impl Reader {
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.index).copied()
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let ch = self.peek()?;
+        self.index += 1;
+        if ch == '\n' {
+            self.line += 1;
+            self.pos = 1;
+        } else {
+            self.pos += 1;
+        }
+        Some(ch)
+    }
+
+    fn span(&self) -> Span {
+        Span::new(self.line, self.pos, 1)
+    }
+
+    fn error(&self, message: &str, hint: &str) -> CompileError {
+        CompileError::syntax(
+            format!("{} at {}:{}", message, self.line, self.pos),
+            self.span(),
+        )
+        .with_hint(hint)
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.peek().is_some_and(|ch| ch.is_ascii_whitespace()) {
+            self.bump();
+        }
+    }
+
+    fn expect(&mut self, expected: char) -> Result<(), CompileError> {
+        self.skip_whitespace();
+        if self.peek() != Some(expected) {
+            return Err(self.error(&format!("Expected '{}'", expected), "check the JSON syntax"));
+        }
+        self.bump();
+        Ok(())
+    }
+
+    // Read a value nested inside `depth` arrays or objects
+    fn value(&mut self, depth: usize) -> Result<Json, CompileError> {
+        self.skip_whitespace();
+        let span = self.span();
+        if matches!(self.peek(), Some('{' | '[')) && depth >= MAX_DEPTH {
+            return Err(self.error(
+                &format!("Nesting deeper than {} levels", MAX_DEPTH),
+                "flatten the document",
+            ));
+        }
+
+        let value = match self.peek() {
+            Some('{') => {
+                self.bump();
+                let mut members = Vec::new();
+                self.skip_whitespace();
+                if self.peek() == Some('}') {
+                    self.bump();
+                } else {
+                    loop {
+                        self.skip_whitespace();
+                        if self.peek() != Some('"') {
+                            return Err(self.error(
+                                "Expected a member name",
+                                "object keys are quoted strings",
+                            ));
+                        }
+                        let key = self.string()?;
+                        self.expect(':')?;
+                        members.push((key, self.value(depth + 1)?));
+
+                        self.skip_whitespace();
+                        match self.bump() {
+                            Some(',') => continue,
+                            Some('}') => break,
+                            _ => {
+                                return Err(
+                                    self.error("Expected ',' or '}'", "separate members with ','")
+                                )
+                            }
+                        }
+                    }
+                }
+                Value::Object(members)
+            }
+            Some('[') => {
+                self.bump();
+                let mut items = Vec::new();
+                self.skip_whitespace();
+                if self.peek() == Some(']') {
+                    self.bump();
+                } else {
+                    loop {
+                        items.push(self.value(depth + 1)?);
+                        self.skip_whitespace();
+                        match self.bump() {
+                            Some(',') => continue,
+                            Some(']') => break,
+                            _ => {
+                                return Err(
+                                    self.error("Expected ',' or ']'", "separate items with ','")
+                                )
+                            }
+                        }
+                    }
+                }
+                Value::Array(items)
+            }
+            Some('"') => Value::String(self.string()?),
+            Some(ch) if ch == '-' || ch.is_ascii_digit() => {
+                let mut text = String::new();
+                while let Some(ch) = self.peek() {
+                    if !(ch.is_ascii_digit() || matches!(ch, '-' | '+' | '.' | 'e' | 'E')) {
+                        break;
+                    }
+                    text.push(ch);
+                    self.bump();
+                }
+                match text.parse::<f64>() {
+                    Ok(f) if f.is_finite() => Value::Number(f),
+                    _ => {
+                        return Err(CompileError::syntax(
+                            format!("Invalid number at {}:{} \"{}\"", span.line, span.pos, text),
+                            Span::new(span.line, span.pos, text.chars().count()),
+                        )
+                        .with_hint("numbers look like 12, -0.5 or 1e-3"))
+                    }
+                }
+            }
+            Some(ch) if ch.is_alphabetic() => {
+                let mut word = String::new();
+                while let Some(ch) = self.peek().filter(|ch| ch.is_alphabetic()) {
+                    word.push(ch);
+                    self.bump();
+                }
+                match word.as_str() {
+                    "null" => Value::Null,
+                    "true" => Value::Bool(true),
+                    "false" => Value::Bool(false),
+                    _ => {
+                        return Err(CompileError::syntax(
+                            format!("Unexpected word at {}:{} \"{}\"", span.line, span.pos, word),
+                            Span::new(span.line, span.pos, word.chars().count()),
+                        )
+                        .with_hint("strings must be quoted"))
+                    }
+                }
+            }
+            Some(_) => return Err(self.error("Unexpected character", "expected a JSON value")),
+            None => return Err(self.error("Unexpected end of input", "expected a JSON value")),
+        };
+
+        Ok(Json { value, span })
+    }
+
+    // Read a quoted string, the cursor is on the opening quote
+    fn string(&mut self) -> Result<String, CompileError> {
+        let span = self.span();
+        self.bump();
+
+        let mut text = String::new();
+        loop {
+            match self.bump() {
+                Some('"') => return Ok(text),
+                Some('\\') => {
+                    let escaped =
+                        match self.bump() {
+                            Some('"') => '"',
+                            Some('\\') => '\\',
+                            Some('/') => '/',
+                            Some('b') => '\u{8}',
+                            Some('f') => '\u{c}',
+                            Some('n') => '\n',
+                            Some('r') => '\r',
+                            Some('t') => '\t',
+                            Some('u') => {
+                                let hex: String = (0..4).filter_map(|_| self.bump()).collect();
+                                match u32::from_str_radix(&hex, 16).ok().and_then(char::from_u32) {
+                                    Some(ch) => ch,
+                                    None => {
+                                        return Err(self.error(
+                                            "Invalid unicode escape",
+                                            "use \\uXXXX with four hex digits",
+                                        ))
+                                    }
+                                }
+                            }
+                            _ => return Err(self.error(
+                                "Invalid escape",
+                                "valid escapes are \\\" \\\\ \\/ \\b \\f \\n \\r \\t and \\uXXXX",
+                            )),
+                        };
+                    text.push(escaped);
+                }
+                Some(ch) => text.push(ch),
+                None => {
+                    return Err(CompileError::syntax(
+                        format!("Unterminated string at {}:{}", span.line, span.pos),
+                        span,
+                    )
+                    .with_hint("close the string with '\"'"))
+                }
+            }
+        }
+    }
+}
+
+#[rustfmt::skip]
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_values() {
+        let json = parse("{\"a\": [1, -2.5e1, true, null], \"b\": \"x\\n\\u0041\"}").unwrap();
+
+        let a = json.get("a").unwrap();
+        match &a.value {
+            Value::Array(items) => {
+                let values: Vec<&Value> = items.iter().map(|item| &item.value).collect();
+                assert_eq!(values, vec![&Value::Number(1.0), &Value::Number(-25.0), &Value::Bool(true), &Value::Null]);
+            }
+            _ => panic!("expected an array"),
+        }
+        assert_eq!(a.span, Span::new(1, 7, 1));
+        assert_eq!(json.get("b").unwrap().value, Value::String(String::from("x\nA")));
+        assert_eq!(json.get("c"), None);
+    }
+
+    #[test]
+    fn quote_strings() {
+        let text = "say \"hi\"\n\t\\";
+        assert_eq!(quote(text), "\"say \\\"hi\\\"\\n\\t\\\\\"");
+        assert_eq!(parse(&quote(text)).unwrap().value, Value::String(String::from(text)));
+    }
+
+    #[test]
+    fn invalid_documents() {
+        let err = parse("{\"a\": 1,\n \"b\" 2}").unwrap_err();
+        assert_eq!(err.message(), "Expected ':' at 2:6");
+
+        let err = parse("[1, 2").unwrap_err();
+        assert_eq!(err.message(), "Expected ',' or ']' at 1:6");
+
+        let err = parse("[1.2.3]").unwrap_err();
+        assert_eq!(err.message(), "Invalid number at 1:2 \"1.2.3\"");
+
+        let err = parse("{\"a\": yes}").unwrap_err();
+        assert_eq!(err.message(), "Unexpected word at 1:7 \"yes\"");
+
+        let err = parse("\"open").unwrap_err();
+        assert_eq!(err.message(), "Unterminated string at 1:1");
+
+        let err = parse("{} {}").unwrap_err();
+        assert_eq!(err.message(), "Unexpected trailing characters at 1:4");
+    }
+
+    #[test]
+    fn nesting_limit() {
+        let nested = "[".repeat(MAX_DEPTH) + &"]".repeat(MAX_DEPTH);
+        assert!(parse(&nested).is_ok());
+
+        let err = parse(&"[".repeat(200000)).unwrap_err();
+        assert_eq!(err.message(), "Nesting deeper than 128 levels at 1:129");
+        assert_eq!(err.span(), Some(Span::new(1, 129, 1)));
+
+        let err = parse(&"{\"a\": ".repeat(MAX_DEPTH + 1)).unwrap_err();
+        assert_eq!(err.message(), "Nesting deeper than 128 levels at 1:769");
+    }
+}
@@ -1,139 +1,382 @@
 use std::fs::File;
 use std::io::{self, BufRead};
-use std::path::Path;
 
+use crate::error::{CompileError, Span};
 use crate::token::Token;
 use crate::token::TokenType;
 
-// Read file by line from:
-// https://doc.rust-lang.org/rust-by-example/std_misc/file/read_lines.html
-fn read_lines<P>(filename: P) -> io::Result<io::Lines<io::BufReader<File>>>
-where
-    P: AsRef<Path>,
-{
-    let file = File::open(filename)?;
-    Ok(io::BufReader::new(file).lines())
+// Returns vector of tokens derived from file
+pub fn tokenize(filename: &str) -> Result<Vec<Token>, CompileError> {
+    match File::open(filename) {
+        Ok(file) => tokenize_reader(io::BufReader::new(file)),
+        Err(e) => Err(CompileError::io(format!("Error reading file: {}", e))),
+    }
 }
 
-// Returns vector of tokens derived from file
-pub fn tokenize(filename: &str) -> Result<Vec<Token>, String> {
-    match read_lines(filename) {
-        Ok(lines) => {
-            let mut tokens: Vec<Token> = Vec::new();
-
-            for (lineno, line_result) in lines.enumerate() {
-                let line = line_result.unwrap();
-
-                // Iterate over chars with 'peekable' trait to avoid
-                // consuming next character
-                let mut iter = line.chars().enumerate().peekable();
-                while iter.peek() != None {
-                    let (pos, ch) = iter.next().unwrap();
-
-                    if ch.is_whitespace() {
-                        continue;
+// Returns vector of tokens derived from an in-memory program
+pub fn tokenize_str(source: &str) -> Result<Vec<Token>, CompileError> {
+    tokenize_reader(source.as_bytes())
+}
+
+// Returns vector of tokens read line by line from any buffered reader
+pub fn tokenize_reader<R: BufRead>(reader: R) -> Result<Vec<Token>, CompileError> {
+    tokenize_with_options(reader, &LexerOptions::default())
+}
+
+#[derive(Debug, Default, Clone, Copy)]
+pub struct LexerOptions {
+    // Emit TokenType::Comment tokens instead of discarding comments
+    pub keep_comments: bool,
+}
+
+// Block comment that has been opened but not yet closed
+struct BlockComment {
+    line: u32,
+    pos: u32,
+    text: String,
+    // Where the comment token belongs in the token list
+    index: usize,
+}
+
+// Returns vector of tokens, handling comments as configured by `options`.
+// Comments may be written as '# ...', '// ...' or '/* ... */', the latter
+// possibly spanning several lines.
+pub fn tokenize_with_options<R: BufRead>(
+    reader: R,
+    options: &LexerOptions,
+) -> Result<Vec<Token>, CompileError> {
+    let mut tokens: Vec<Token> = Vec::new();
+    let mut block_comment: Option<BlockComment> = None;
+
+    for (lineno, line_result) in reader.lines().enumerate() {
+        let line = match line_result {
+            Ok(line) => line,
+            Err(e) => {
+                return Err(CompileError::io(format!(
+                    "Error reading line {}: {}",
+                    lineno + 1,
+                    e
+                )))
+            }
+        };
+
+        // Iterate over chars with 'peekable' trait to avoid
+        // consuming next character
+        let mut iter = line.chars().enumerate().peekable();
+        while iter.peek().is_some() {
+            let (pos, ch) = iter.next().unwrap();
+
+            // Inside a block comment, everything up to '*/' is skipped
+            if let Some(comment) = block_comment.as_mut() {
+                comment.text.push(ch);
+                if ch == '*' && matches!(iter.peek(), Some((_, '/'))) {
+                    iter.next();
+                    comment.text.push('/');
+
+                    let comment = block_comment.take().unwrap();
+                    if options.keep_comments {
+                        let len = comment.text.chars().count();
+                        tokens.insert(
+                            comment.index,
+                            Token {
+                                t: TokenType::Comment(comment.text),
+                                line: comment.line,
+                                pos: comment.pos,
+                                len,
+                            },
+                        );
                     }
+                }
+                continue;
+            }
 
-                    let mut new_token = Token {
-                        t: TokenType::UNDEF,
+            if ch.is_whitespace() {
+                continue;
+            }
+
+            // Line comments run to the end of the line
+            if ch == '#' || (ch == '/' && matches!(iter.peek(), Some((_, '/')))) {
+                if options.keep_comments {
+                    let text: String = line.chars().skip(pos).collect();
+                    tokens.push(Token {
+                        len: text.chars().count(),
+                        t: TokenType::Comment(text),
                         line: lineno as u32 + 1,
                         pos: pos as u32 + 1,
-                        len: 1,
-                    };
-
-                    new_token.t = match ch {
-                        // Alphabetic tokens (RZ, RZ, etc.)
-                        ch if ch.is_alphabetic() => {
-                            let mut ident = String::from(ch.to_string());
-
-                            while let Some((_, peek_ch)) = iter.peek() {
-                                if !peek_ch.is_alphabetic() {
-                                    break;
-                                }
-
-                                let (_, next_ch) = iter.next().unwrap();
-                                new_token.len += 1;
-                                ident.push(next_ch);
-                            }
-
-                            match ident.as_str() {
-                                "RX" => TokenType::RX,
-                                "RZ" => TokenType::RZ,
-                                "CZ" => TokenType::CZ,
-                                "MEASURE" => TokenType::MEASURE,
-                                _ => TokenType::UNDEF,
-                            }
-                        }
+                    });
+                }
+                break;
+            }
+
+            if ch == '/' && matches!(iter.peek(), Some((_, '*'))) {
+                iter.next();
+                block_comment = Some(BlockComment {
+                    line: lineno as u32 + 1,
+                    pos: pos as u32 + 1,
+                    text: String::from("/*"),
+                    index: tokens.len(),
+                });
+                continue;
+            }
 
-                        // Numeric tokens (Floats or Ints)
-                        ch if ch.is_numeric() => {
-                            let mut ident = String::from(ch.to_string());
-                            let mut is_float = false;
-
-                            while let Some((_, peek_ch)) = iter.peek() {
-                                if !(peek_ch.is_numeric() || *peek_ch == '.') {
-                                    break;
-                                }
-
-                                let (_, next_ch) = iter.next().unwrap();
-                                if next_ch == '.' {
-                                    is_float = true;
-                                }
-                                new_token.len += 1;
-                                ident.push(next_ch);
-                            }
-
-                            if is_float {
-                                let f: f64 = ident.parse().unwrap();
-                                TokenType::Float(f)
-                            } else {
-                                let u: u32 = ident.parse().unwrap();
-                                TokenType::Integer(u)
-                            }
+            let mut new_token = Token {
+                t: TokenType::UNDEF,
+                line: lineno as u32 + 1,
+                pos: pos as u32 + 1,
+                len: 1,
+            };
+
+            new_token.t = match ch {
+                // Alphabetic tokens (RZ, RZ, etc.)
+                ch if ch.is_alphabetic() => {
+                    let mut ident = ch.to_string();
+
+                    while let Some((_, peek_ch)) = iter.peek() {
+                        if !peek_ch.is_alphabetic() {
+                            break;
                         }
 
-                        // Misc Tokens
-                        '(' => TokenType::LParen,
-                        ')' => TokenType::RParen,
-                        '-' => TokenType::Negative,
-                        ';' => TokenType::EOL,
-                        _ => TokenType::UNDEF,
-                    };
-
-                    if new_token.t == TokenType::UNDEF {
-                        return Err(format!(
-                            "Undefined token at {}:{} \"{}\"",
-                            lineno + 1,
-                            pos + 1,
-                            &line[pos..pos + new_token.len]
-                        ));
+                        let (_, next_ch) = iter.next().unwrap();
+                        new_token.len += 1;
+                        ident.push(next_ch);
                     }
 
-                    tokens.push(new_token);
+                    match ident.as_str() {
+                        "RX" => TokenType::RX,
+                        "RZ" => TokenType::RZ,
+                        "CZ" => TokenType::CZ,
+                        "MEASURE" => TokenType::MEASURE,
+                        "pi" => TokenType::PI,
+                        "tau" => TokenType::TAU,
+                        "e" => TokenType::E,
+                        _ => TokenType::UNDEF,
+                    }
                 }
 
-                // If last token was not an end-of-line token, insert one
-                if let Some(last_token) = tokens.last() {
-                    if last_token.t != TokenType::EOL {
-                        tokens.push(Token {
-                            t: TokenType::EOL,
-                            line: lineno as u32 + 1,
-                            pos: line.len() as u32 + 1,
-                            len: 1,
-                        });
+                // Numeric tokens (Floats or Ints). The whole literal is
+                // consumed, including any stray letters or dots, so that
+                // malformed numbers are reported as a single token.
+                ch if ch.is_ascii_digit()
+                    || (ch == '.' && matches!(iter.peek(), Some((_, c)) if c.is_ascii_digit())) =>
+                {
+                    let mut ident = ch.to_string();
+
+                    while let Some((_, peek_ch)) = iter.peek() {
+                        let exponent_sign = (*peek_ch == '+' || *peek_ch == '-')
+                            && ident.ends_with(['e', 'E'])
+                            && !ident.starts_with("0x")
+                            && !ident.starts_with("0X");
+                        if !(peek_ch.is_alphanumeric()
+                            || *peek_ch == '.'
+                            || *peek_ch == '_'
+                            || exponent_sign)
+                        {
+                            break;
+                        }
+
+                        let (_, next_ch) = iter.next().unwrap();
+                        new_token.len += 1;
+                        ident.push(next_ch);
+                    }
+
+                    match parse_number(&ident) {
+                        Ok(t) => t,
+                        Err(err) => {
+                            return Err(CompileError::lexical(
+                                format!(
+                                    "{} at {}:{} \"{}\"",
+                                    err.message(),
+                                    lineno + 1,
+                                    pos + 1,
+                                    ident
+                                ),
+                                Span::from(&new_token),
+                            )
+                            .with_hint(err.hint()));
+                        }
                     }
                 }
+
+                // Misc Tokens
+                '(' => TokenType::LParen,
+                ')' => TokenType::RParen,
+                '-' => TokenType::Negative,
+                '+' => TokenType::Plus,
+                '*' => TokenType::Star,
+                '/' => TokenType::Slash,
+                ';' => TokenType::EOL,
+                _ => TokenType::UNDEF,
+            };
+
+            if new_token.t == TokenType::UNDEF {
+                return Err(CompileError::lexical(
+                    format!(
+                        "Undefined token at {}:{} \"{}\"",
+                        lineno + 1,
+                        pos + 1,
+                        line.chars()
+                            .skip(pos)
+                            .take(new_token.len)
+                            .collect::<String>()
+                    ),
+                    Span::from(&new_token),
+                )
+                .with_hint(if ch.is_alphabetic() {
+                    "instructions are RX, RZ, CZ and MEASURE, in upper case"
+                } else {
+                    "expected an instruction, number, '(', ')', '+', '-', '*', '/' or ';'"
+                }));
             }
 
-            return Ok(tokens);
+            tokens.push(new_token);
+        }
+
+        // A line break inside a block comment still ends the instruction
+        if let Some(comment) = block_comment.as_mut() {
+            comment.text.push('\n');
+        }
+
+        // If last token was not an end-of-line token, insert one
+        let last_token = tokens
+            .iter()
+            .rev()
+            .find(|token| !matches!(token.t, TokenType::Comment(_)));
+        if let Some(last_token) = last_token {
+            if last_token.t != TokenType::EOL {
+                tokens.push(Token {
+                    t: TokenType::EOL,
+                    line: lineno as u32 + 1,
+                    pos: line.chars().count() as u32 + 1,
+                    len: 1,
+                });
+            }
+        }
+    }
+
+    if let Some(comment) = block_comment {
+        return Err(CompileError::lexical(
+            format!(
+                "Unterminated block comment at {}:{}",
+                comment.line, comment.pos
+            ),
+            Span::new(comment.line, comment.pos, 2),
+        )
+        .with_hint("close the comment with '*/'"));
+    }
+
+    Ok(tokens)
+}
+
+// Reason a numeric literal was rejected, with a hint on how to fix it
+pub(crate) enum NumberError {
+    Malformed(&'static str),
+    OutOfRange(&'static str),
+}
+
+impl NumberError {
+    pub(crate) fn message(&self) -> &'static str {
+        match self {
+            NumberError::Malformed(_) => "Malformed numeric literal",
+            NumberError::OutOfRange(_) => "Numeric literal out of range",
         }
-        Err(e) => {
-            return Err(format!("Error reading file: {}", e));
+    }
+
+    pub(crate) fn hint(&self) -> &'static str {
+        match self {
+            NumberError::Malformed(hint) | NumberError::OutOfRange(hint) => hint,
         }
     }
 }
 
+// Convert the text of a numeric literal into a token. Accepts decimal
+// integers, hex integers (0x1F), floats with an optional leading or
+// trailing dot (.5, 5.), scientific notation (2.5E+4) and '_' between
+// digits (1_000).
+pub(crate) fn parse_number(text: &str) -> Result<TokenType, NumberError> {
+    // Underscores may only separate two digits
+    let separated = |digits: &str, is_digit: fn(&char) -> bool| {
+        let chars: Vec<char> = digits.chars().collect();
+        chars.iter().enumerate().all(|(i, c)| {
+            *c != '_'
+                || (i > 0
+                    && i + 1 < chars.len()
+                    && is_digit(&chars[i - 1])
+                    && is_digit(&chars[i + 1]))
+        })
+    };
+
+    if let Some(digits) = text.strip_prefix("0x").or_else(|| text.strip_prefix("0X")) {
+        if digits.is_empty() {
+            return Err(NumberError::Malformed("expected hex digits after '0x'"));
+        }
+        if !digits.chars().all(|c| c.is_ascii_hexdigit() || c == '_') {
+            return Err(NumberError::Malformed(
+                "hex literals may only contain the digits 0-9, a-f and A-F",
+            ));
+        }
+        if !separated(digits, char::is_ascii_hexdigit) {
+            return Err(NumberError::Malformed("'_' may only separate two digits"));
+        }
+
+        return u32::from_str_radix(&digits.replace('_', ""), 16)
+            .map(TokenType::Integer)
+            .map_err(|_| NumberError::OutOfRange("integer literals must be at most 0xFFFFFFFF"));
+    }
+
+    let (mantissa, exponent) = match text.find(['e', 'E']) {
+        Some(i) => (&text[..i], Some(&text[i + 1..])),
+        None => (text, None),
+    };
+
+    if !mantissa
+        .chars()
+        .all(|c| c.is_ascii_digit() || c == '.' || c == '_')
+    {
+        return Err(NumberError::Malformed(
+            "numbers may only contain digits, '.', '_' and an exponent",
+        ));
+    }
+    if mantissa.matches('.').count() > 1 {
+        return Err(NumberError::Malformed(
+            "a number may contain at most one '.'",
+        ));
+    }
+    if !separated(mantissa, char::is_ascii_digit) {
+        return Err(NumberError::Malformed("'_' may only separate two digits"));
+    }
+
+    if let Some(exponent) = exponent {
+        let digits = exponent.strip_prefix(['+', '-']).unwrap_or(exponent);
+        if digits.is_empty() || !digits.chars().all(|c| c.is_ascii_digit() || c == '_') {
+            return Err(NumberError::Malformed(
+                "expected an integer exponent, e.g. 1e-3 or 2.5E+4",
+            ));
+        }
+        if !separated(digits, char::is_ascii_digit) {
+            return Err(NumberError::Malformed("'_' may only separate two digits"));
+        }
+    }
+
+    let cleaned = text.replace('_', "");
+    if mantissa.contains('.') || exponent.is_some() {
+        match cleaned.parse::<f64>() {
+            Ok(f) if f.is_finite() => Ok(TokenType::Float(f)),
+            Ok(_) => Err(NumberError::OutOfRange(
+                "floating point literals must be finite",
+            )),
+            Err(_) => Err(NumberError::Malformed("expected digits around '.'")),
+        }
+    } else {
+        cleaned.parse::<u32>().map(TokenType::Integer).map_err(|_| {
+            NumberError::OutOfRange(
+                "integer literals must be at most 4294967295, write large angles with a '.'",
+            )
+        })
+    }
+}
+
 #[rustfmt::skip]
+#[allow(clippy::approx_constant)]
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -176,25 +419,27 @@ mod tests {
 
     #[test]
     fn invalid_numeric_tokens() {
-        // .123456
+        // 1.2.3
         let mut test_filename = format!("{TESTDATA_DIR}/invalid_numeric_tokens1.testdata");
         let mut err = tokenize(&test_filename).unwrap_err();
-        assert_eq!(err, "Undefined token at 1:1 \".\"");
-        
-        // 0.a132
+        assert_eq!(err.message(), "Malformed numeric literal at 1:1 \"1.2.3\"");
+        assert_eq!(err.span(), Some(Span::new(1, 1, 5)));
+        assert_eq!(err.hint(), Some("a number may contain at most one '.'"));
+
+        // 0.a123
         test_filename = format!("{TESTDATA_DIR}/invalid_numeric_tokens2.testdata");
         err = tokenize(&test_filename).unwrap_err();
-        assert_eq!(err, "Undefined token at 1:3 \"a\"");
+        assert_eq!(err.message(), "Malformed numeric literal at 1:1 \"0.a123\"");
 
         // 1,000,000
         test_filename = format!("{TESTDATA_DIR}/invalid_numeric_tokens3.testdata");
         err = tokenize(&test_filename).unwrap_err();
-        assert_eq!(err, "Undefined token at 1:2 \",\"");
+        assert_eq!(err.message(), "Undefined token at 1:2 \",\"");
     }
 
     #[test]
     fn get_function_tokens() {
-        let expected_tokens = vec![
+        let expected_tokens = [
             Token{t: TokenType::RX, line: 1, pos: 1, len: 2},
             Token{t: TokenType::EOL, line: 1, pos: 3, len: 1},
             Token{t: TokenType::RZ, line: 2, pos: 1, len: 2},
@@ -220,12 +465,22 @@ mod tests {
         // rx(0.0) 0
         let mut test_filename = format!("{TESTDATA_DIR}/invalid_function_tokens1.testdata");
         let mut err = tokenize(&test_filename).unwrap_err();
-        assert_eq!(err, "Undefined token at 1:1 \"rx\"");
+        assert_eq!(err.message(), "Undefined token at 1:1 \"rx\"");
+        assert_eq!(err.hint(), Some("instructions are RX, RZ, CZ and MEASURE, in upper case"));
         
         // RAX(1.0) 1
         test_filename = format!("{TESTDATA_DIR}/invalid_function_tokens2.testdata");
         err = tokenize(&test_filename).unwrap_err();
-        assert_eq!(err, "Undefined token at 1:1 \"RAX\"");
+        assert_eq!(err.message(), "Undefined token at 1:1 \"RAX\"");
+        assert_eq!(err.span(), Some(Span::new(1, 1, 3)));
+
+        // Positions count chars, not bytes
+        err = tokenize_str("RZ(π) 0").unwrap_err();
+        assert_eq!(err.message(), "Undefined token at 1:4 \"π\"");
+        assert_eq!(err.span(), Some(Span::new(1, 4, 1)));
+
+        err = tokenize_str("RZ(0.5) 0 → 1").unwrap_err();
+        assert_eq!(err.message(), "Undefined token at 1:11 \"→\"");
     }
 
     #[test]
@@ -257,7 +512,7 @@ mod tests {
 
     #[test]
     fn tokenize_sample_1() {
-        let expected_tokens = vec![
+        let expected_tokens = [
             Token{t: TokenType::RX, line: 1, pos: 1, len: 2},
             Token{t: TokenType::LParen, line: 1, pos: 3, len: 1},
             Token{t: TokenType::Float(0.45), line: 1, pos: 4, len: 4},
@@ -386,4 +641,140 @@ mod tests {
             assert_eq!(ex_token, &actual_tokens[i]);
         }
     }
+
+    // In-memory sources
+
+    #[test]
+    fn tokenize_from_string() {
+        let test_filename = format!("{TESTDATA_DIR}/sample_2.inq");
+        let file_tokens = tokenize(&test_filename).unwrap();
+
+        let source = std::fs::read_to_string(&test_filename).unwrap();
+        assert_eq!(file_tokens, tokenize_str(&source).unwrap());
+        assert_eq!(file_tokens, tokenize_reader(io::Cursor::new(source.as_bytes())).unwrap());
+
+        let err = tokenize_str("RX(0.45) 0\nrx(0.0) 0").unwrap_err();
+        assert_eq!(err.message(), "Undefined token at 2:1 \"rx\"");
+        assert_eq!(err.span(), Some(Span::new(2, 1, 2)));
+
+        assert!(tokenize_str("").unwrap().is_empty());
+    }
+
+    #[test]
+    fn tokenize_missing_file() {
+        let err = tokenize(&format!("{TESTDATA_DIR}/does_not_exist.inq")).unwrap_err();
+        assert!(matches!(err, CompileError::Io { .. }));
+        assert!(err.message().starts_with("Error reading file: "));
+    }
+
+    #[test]
+    fn extended_numeric_tokens() {
+        let cases = [
+            (".5", TokenType::Float(0.5)),
+            ("5.", TokenType::Float(5.0)),
+            ("1e-3", TokenType::Float(1e-3)),
+            ("2.5E+4", TokenType::Float(2.5e4)),
+            ("1E3", TokenType::Float(1000.0)),
+            ("1_000", TokenType::Integer(1000)),
+            ("0.000_001", TokenType::Float(1e-6)),
+            ("0x1F", TokenType::Integer(31)),
+            ("0XdEaD_bEeF", TokenType::Integer(0xDEADBEEF)),
+            ("4294967295", TokenType::Integer(u32::MAX)),
+        ];
+
+        for (source, expected) in cases {
+            let tokens = tokenize_str(source).unwrap();
+            assert_eq!(tokens[0].t, expected, "{}", source);
+            assert_eq!(tokens[0].len, source.len(), "{}", source);
+        }
+
+        // An exponent sign is part of the literal, other signs are not
+        let tokens = tokenize_str("1e-3-2").unwrap();
+        assert_eq!(tokens[0].t, TokenType::Float(1e-3));
+        assert_eq!(tokens[1].t, TokenType::Negative);
+        assert_eq!(tokens[2].t, TokenType::Integer(2));
+    }
+
+    #[test]
+    fn malformed_numeric_tokens() {
+        let cases = [
+            ("1e", "Malformed numeric literal at 1:1 \"1e\"", "expected an integer exponent, e.g. 1e-3 or 2.5E+4"),
+            ("1e+", "Malformed numeric literal at 1:1 \"1e+\"", "expected an integer exponent, e.g. 1e-3 or 2.5E+4"),
+            ("1e2.5", "Malformed numeric literal at 1:1 \"1e2.5\"", "expected an integer exponent, e.g. 1e-3 or 2.5E+4"),
+            ("1__0", "Malformed numeric literal at 1:1 \"1__0\"", "'_' may only separate two digits"),
+            ("1_", "Malformed numeric literal at 1:1 \"1_\"", "'_' may only separate two digits"),
+            ("1_.5", "Malformed numeric literal at 1:1 \"1_.5\"", "'_' may only separate two digits"),
+            ("0x", "Malformed numeric literal at 1:1 \"0x\"", "expected hex digits after '0x'"),
+            ("0x1G", "Malformed numeric literal at 1:1 \"0x1G\"", "hex literals may only contain the digits 0-9, a-f and A-F"),
+            ("2pi", "Malformed numeric literal at 1:1 \"2pi\"", "numbers may only contain digits, '.', '_' and an exponent"),
+            ("4294967296", "Numeric literal out of range at 1:1 \"4294967296\"", "integer literals must be at most 4294967295, write large angles with a '.'"),
+            ("0x1_0000_0000", "Numeric literal out of range at 1:1 \"0x1_0000_0000\"", "integer literals must be at most 0xFFFFFFFF"),
+            ("1e999", "Numeric literal out of range at 1:1 \"1e999\"", "floating point literals must be finite"),
+        ];
+
+        for (source, message, hint) in cases {
+            let err = tokenize_str(source).unwrap_err();
+            assert_eq!(err.message(), message);
+            assert_eq!(err.hint(), Some(hint), "{}", source);
+            assert_eq!(err.span(), Some(Span::new(1, 1, source.len())));
+        }
+
+        let err = tokenize_str("RX(0.1) 0\nRZ(1.2.3) 0").unwrap_err();
+        assert_eq!(err.message(), "Malformed numeric literal at 2:4 \"1.2.3\"");
+    }
+
+    // Comments
+
+    #[test]
+    fn tokenize_line_comments() {
+        let tokens = tokenize_str("# header\nRX(0.45) 0 // trailing\n// RZ(1.0) 0\nMEASURE 0").unwrap();
+        let types: Vec<TokenType> = tokens.iter().map(|token| token.t.clone()).collect();
+
+        assert_eq!(types, vec![
+            TokenType::RX, TokenType::LParen, TokenType::Float(0.45), TokenType::RParen, TokenType::Integer(0), TokenType::EOL,
+            TokenType::MEASURE, TokenType::Integer(0), TokenType::EOL,
+        ]);
+        assert_eq!((tokens[6].line, tokens[6].pos), (4, 1));
+    }
+
+    #[test]
+    fn tokenize_block_comments() {
+        let tokens = tokenize_str("RX(0.45) /* qbit */ 0\n/* several\nlines */ MEASURE 0").unwrap();
+
+        assert_eq!(tokens[4].t, TokenType::Integer(0));
+        assert_eq!((tokens[4].line, tokens[4].pos), (1, 21));
+        assert_eq!(tokens[6].t, TokenType::MEASURE);
+        assert_eq!((tokens[6].line, tokens[6].pos), (3, 10));
+        assert_eq!(tokens.len(), 9);
+
+        // A comment across a line break ends the instruction before it
+        let tokens = tokenize_str("RX(0.1) 0 /*\n*/ RZ(0.2) 1\nMEASURE 1").unwrap();
+        assert_eq!(tokens[5].t, TokenType::EOL);
+        assert_eq!((tokens[5].line, tokens[5].pos), (1, 13));
+        assert_eq!(tokens[6].t, TokenType::RZ);
+        assert_eq!(tokens.len(), 15);
+
+        let err = tokenize_str("RX(0.45) 0\n  /* never closed\nMEASURE 0").unwrap_err();
+        assert_eq!(err.message(), "Unterminated block comment at 2:3");
+        assert_eq!(err.span(), Some(Span::new(2, 3, 2)));
+        assert_eq!(err.hint(), Some("close the comment with '*/'"));
+    }
+
+    #[test]
+    fn tokenize_keep_comments() {
+        let options = LexerOptions { keep_comments: true };
+        let source = "RX(0.45) /* a\nb */ 0 # end";
+        let tokens = tokenize_with_options(io::Cursor::new(source.as_bytes()), &options).unwrap();
+
+        assert_eq!(tokens[4].t, TokenType::Comment(String::from("/* a\nb */")));
+        assert_eq!((tokens[4].line, tokens[4].pos, tokens[4].len), (1, 10, 9));
+        assert_eq!(tokens[5].t, TokenType::EOL);
+        assert_eq!(tokens[6].t, TokenType::Integer(0));
+        assert_eq!(tokens[7].t, TokenType::Comment(String::from("# end")));
+        assert_eq!((tokens[7].line, tokens[7].pos), (2, 8));
+        assert_eq!(tokens[8].t, TokenType::EOL);
+
+        let tokens = tokenize_with_options(io::Cursor::new("// only".as_bytes()), &options).unwrap();
+        assert_eq!(tokens.len(), 1);
+    }
 }
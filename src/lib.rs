@@ -1,5 +1,63 @@
+pub mod angle;
+pub mod backend;
+pub mod complex;
+pub mod cost;
+pub mod dag;
+pub mod diagnostic;
+pub mod equivalence;
+pub mod error;
+pub mod expr;
+pub mod frontend;
 pub mod instruction;
+pub mod json;
 pub mod lexer;
 pub mod optimize;
 pub mod parser;
+pub mod pipeline;
+pub mod render;
+pub mod simulate;
 pub mod token;
+
+use error::CompileError;
+use instruction::Instruction;
+use pipeline::Pipeline;
+
+// Compile an in-memory .inq program with the default pipeline
+pub fn compile_str(source: &str) -> Result<Vec<Instruction>, CompileError> {
+    Ok(Pipeline::default().compile_str(source)?.program)
+}
+
+// Compile the .inq program stored in `filename` with the default pipeline
+pub fn compile_file(filename: &str) -> Result<Vec<Instruction>, CompileError> {
+    Ok(Pipeline::default().compile_file(filename)?.program)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn compile_from_string() {
+        let program = compile_str("RZ(0.5) 0; RZ(0.25) 0\nCZ 0 1; CZ 1 0\nMEASURE 0").unwrap();
+        assert_eq!(
+            program,
+            vec![Instruction::RZ(0.75, 0), Instruction::MEASURE(0)]
+        );
+
+        // Sparse qubit indices do not allocate a wire per unused qubit
+        let program = compile_str("RZ(0.5) 4000000000\nRZ(0.5) 4000000000").unwrap();
+        assert_eq!(program, vec![Instruction::RZ(1.0, 4000000000)]);
+
+        let program = compile_str("MEASURE 4294967295").unwrap();
+        assert_eq!(program, vec![Instruction::MEASURE(u32::MAX)]);
+
+        let err = compile_str("RX(0.45) 0\nRY(1.0) 0").unwrap_err();
+        assert_eq!(err.to_string(), "lexer: Undefined token at 2:1 \"RY\"");
+
+        let err = compile_str("RX(0.45)").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "parser: Missing qbit index after instruction at 1:1"
+        );
+    }
+}
@@ -1,118 +1,319 @@
 use std::env;
+use std::fs;
+use std::io::{self, IsTerminal, Write};
 use std::process;
 
+use dustinw_qc::backend::binary::{self, AngleEncoding};
+use dustinw_qc::backend::inq::prog_to_string;
+use dustinw_qc::backend::json::prog_to_json;
+use dustinw_qc::backend::qasm::{prog_to_qasm, QasmVersion};
+use dustinw_qc::backend::quil::prog_to_quil;
+use dustinw_qc::diagnostic;
 use dustinw_qc::instruction::Instruction;
-use dustinw_qc::lexer;
-use dustinw_qc::parser;
+use dustinw_qc::pipeline::{Pass, Pipeline};
+use dustinw_qc::render::ascii::prog_to_ascii;
+use dustinw_qc::render::dot::{changed_instructions, prog_to_dot};
+use dustinw_qc::render::latex::prog_to_latex;
+use dustinw_qc::render::svg::prog_to_svg;
 
-use dustinw_qc::optimize::cz_cancel;
-use dustinw_qc::optimize::deadcode;
-use dustinw_qc::optimize::native_translation;
-use dustinw_qc::optimize::reorder;
-use dustinw_qc::optimize::rotation_merge;
+const USAGE: &str =
+    "Usage: dustinw-qc [--format inq|qasm2|qasm3|quil|json|binary|binary-quantized|ascii|latex|svg|dot] \
+[--input inq|qasm|json|binary] [--highlight PASS] filename.inq|filename.qasm|filename.json|filename.inqb";
 
-const MAX_OP_PASSES: u32 = 100;
+// Output format of the compiled program
+#[derive(Debug, PartialEq, Clone, Copy)]
+enum OutputFormat {
+    Inq,
+    Qasm(QasmVersion),
+    Quil,
+    Json,
+    Binary(AngleEncoding),
+    Ascii,
+    Latex,
+    Svg,
+    Dot,
+}
+
+// Format of the source program
+#[derive(Debug, PartialEq, Clone, Copy)]
+enum InputFormat {
+    Inq,
+    Qasm,
+    Json,
+    Binary,
+}
+
+impl InputFormat {
+    // Guess the format from the file extension, defaulting to .inq
+    fn from_filename(filename: &str) -> InputFormat {
+        if filename.ends_with(".qasm") {
+            InputFormat::Qasm
+        } else if filename.ends_with(".json") {
+            InputFormat::Json
+        } else if filename.ends_with(".inqb") {
+            InputFormat::Binary
+        } else {
+            InputFormat::Inq
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+struct Options {
+    filename: String,
+    format: OutputFormat,
+    input: InputFormat,
+    // Optimization pass whose changes are highlighted in the DOT graph
+    highlight: Option<&'static str>,
+}
 
 fn main() {
     // Parse Args
-    let args: Vec<String> = env::args().collect();
-    if args.len() < 2 {
-        println!("No filename provided.\nUsage: '{} filename.inq'", args[0]);
-        process::exit(1)
+    let args: Vec<String> = env::args().skip(1).collect();
+    let options = match parse_args(&args) {
+        Ok(options) => options,
+        Err(err) => {
+            println!("{}\n{}", err, USAGE);
+            process::exit(1)
+        }
+    };
+
+    // Show what a single run of the highlighted pass does to the program
+    // after native translation
+    if let Some(name) = options.highlight {
+        let pipeline = Pipeline {
+            passes: Vec::new(),
+            ..Pipeline::default()
+        };
+        let before = compile_as(&options.filename, options.input, &pipeline);
+        let pass = default_pass(name).unwrap();
+        let after = match (pass.func)(before.clone(), pipeline.tolerance) {
+            Ok(after) => after,
+            Err(err) => {
+                print!("{}", diagnostic::render(&err, "", None, false));
+                process::exit(1);
+            }
+        };
+        println!(
+            "{}",
+            prog_to_dot(&after, &changed_instructions(&before, &after))
+        );
+        return;
     }
-    let filename = &args[1];
 
     // Compile entry point
-    let program = compile(filename);
+    let program = compile_as(&options.filename, options.input, &Pipeline::default());
 
     // Print program to stdout
-    println!("{}", prog_to_string(program));
-}
-
-fn compile(filename: &str) -> Vec<Instruction> {
-    // Tokenize File
-    let tokens_result = lexer::tokenize(filename);
-    if let Err(err) = tokens_result {
-        println!("lexer: {}", err);
-        process::exit(1);
-    }
-    let tokens = tokens_result.as_ref().unwrap();
-
-    // Parse Tokens
-    let program_result = parser::parse(tokens);
-    if let Err(err) = program_result {
-        println!("parser: {}", err);
-        process::exit(1);
-    }
-    let mut program: Vec<Instruction> = program_result.unwrap();
-
-    // Translate program to native instructions only
-    let translation_result = native_translation::native_translation_pass(program);
-    if let Err(err) = translation_result {
-        println!("native instruction translation: {}", err);
-        process::exit(1);
-    }
-    program = translation_result.unwrap();
-
-    // Code passes
-    let code_passes: Vec<(
-        &str,
-        fn(Vec<Instruction>) -> Result<Vec<Instruction>, String>,
-    )> = vec![
-        ("reorder", reorder::reorder_pass),
-        ("rotation_merge", rotation_merge::rotation_merge_pass),
-        ("cz_cancel", cz_cancel::cz_cancel_pass),
-        ("deadcode", deadcode::deadcode_pass),
-    ];
-
-    let mut prog_len = program.len();
-    for _round in 0..MAX_OP_PASSES {
-        // Perform optimization passes
-        for (name, pass_func) in &code_passes {
-            match pass_func(program) {
-                Ok(new_prog) => program = new_prog,
-                Err(err) => {
-                    println!("{}: {}", name, err);
-                    process::exit(1);
-                }
+    match options.format {
+        OutputFormat::Inq => println!("{}", prog_to_string(&program)),
+        OutputFormat::Qasm(version) => match prog_to_qasm(&program, version) {
+            Ok(qasm) => println!("{}", qasm),
+            Err(err) => {
+                println!("Error writing output: {}", err);
+                process::exit(1);
+            }
+        },
+        OutputFormat::Quil => println!("{}", prog_to_quil(&program)),
+        OutputFormat::Json => println!("{}", prog_to_json(&program)),
+        OutputFormat::Binary(angles) => {
+            let bytes = binary::encode(&program, angles);
+            if let Err(err) = io::stdout().write_all(&bytes) {
+                println!("Error writing output: {}", err);
+                process::exit(1);
             }
         }
+        OutputFormat::Ascii => println!("{}", prog_to_ascii(&program)),
+        OutputFormat::Latex => println!("{}", prog_to_latex(&program)),
+        OutputFormat::Svg => println!("{}", prog_to_svg(&program)),
+        OutputFormat::Dot => println!("{}", prog_to_dot(&program, &[])),
+    }
+}
 
-        // Terminate optimization if program length is not changing
-        if program.len() == prog_len {
-            break;
+fn parse_args(args: &[String]) -> Result<Options, String> {
+    let mut filename: Option<String> = None;
+    let mut format = OutputFormat::Inq;
+    let mut input: Option<InputFormat> = None;
+    let mut highlight: Option<&'static str> = None;
+
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        if arg == "--format" {
+            let value = iter.next().ok_or("Missing value for --format")?;
+            format = parse_format(value)?;
+        } else if let Some(value) = arg.strip_prefix("--format=") {
+            format = parse_format(value)?;
+        } else if arg == "--input" {
+            let value = iter.next().ok_or("Missing value for --input")?;
+            input = Some(parse_input(value)?);
+        } else if let Some(value) = arg.strip_prefix("--input=") {
+            input = Some(parse_input(value)?);
+        } else if arg == "--highlight" {
+            let value = iter.next().ok_or("Missing value for --highlight")?;
+            highlight = Some(parse_pass(value)?);
+        } else if let Some(value) = arg.strip_prefix("--highlight=") {
+            highlight = Some(parse_pass(value)?);
+        } else if arg.starts_with("--") {
+            return Err(format!("Unknown option '{}'", arg));
+        } else if filename.is_some() {
+            return Err(format!("Unexpected argument '{}'", arg));
         } else {
-            prog_len = program.len();
+            filename = Some(arg.clone());
         }
     }
 
-    return program;
+    if highlight.is_some() && format != OutputFormat::Dot {
+        return Err(String::from("--highlight requires --format dot"));
+    }
+
+    match filename {
+        Some(filename) => {
+            let input = input.unwrap_or_else(|| InputFormat::from_filename(&filename));
+            Ok(Options {
+                filename,
+                format,
+                input,
+                highlight,
+            })
+        }
+        None => Err(String::from("No filename provided.")),
+    }
 }
 
-fn prog_to_string(program: Vec<Instruction>) -> String {
-    let mut output = String::new();
+fn parse_format(value: &str) -> Result<OutputFormat, String> {
+    match value {
+        "inq" => Ok(OutputFormat::Inq),
+        "qasm" | "qasm2" => Ok(OutputFormat::Qasm(QasmVersion::V2)),
+        "qasm3" => Ok(OutputFormat::Qasm(QasmVersion::V3)),
+        "quil" => Ok(OutputFormat::Quil),
+        "json" => Ok(OutputFormat::Json),
+        "binary" => Ok(OutputFormat::Binary(AngleEncoding::F64)),
+        "binary-quantized" => Ok(OutputFormat::Binary(AngleEncoding::Quantized)),
+        "ascii" => Ok(OutputFormat::Ascii),
+        "latex" => Ok(OutputFormat::Latex),
+        "svg" => Ok(OutputFormat::Svg),
+        "dot" => Ok(OutputFormat::Dot),
+        _ => Err(format!("Unknown output format '{}'", value)),
+    }
+}
 
-    for instr in program {
-        output.push_str(&format!("{instr}\n"));
+fn parse_input(value: &str) -> Result<InputFormat, String> {
+    match value {
+        "inq" => Ok(InputFormat::Inq),
+        "qasm" => Ok(InputFormat::Qasm),
+        "json" => Ok(InputFormat::Json),
+        "binary" => Ok(InputFormat::Binary),
+        _ => Err(format!("Unknown input format '{}'", value)),
     }
-    output.pop();
+}
+
+// Optimization pass of the default pipeline called `name`
+fn default_pass(name: &str) -> Option<Pass> {
+    Pipeline::default()
+        .passes
+        .into_iter()
+        .find(|pass| pass.name == name)
+}
 
-    return output;
+fn parse_pass(value: &str) -> Result<&'static str, String> {
+    match default_pass(value) {
+        Some(pass) => Ok(pass.name),
+        None => {
+            let names: Vec<&str> = Pipeline::default()
+                .passes
+                .iter()
+                .map(|pass| pass.name)
+                .collect();
+            Err(format!(
+                "Unknown pass '{}', expected one of: {}",
+                value,
+                names.join(", ")
+            ))
+        }
+    }
+}
+
+#[cfg(test)]
+fn compile(filename: &str) -> Vec<Instruction> {
+    compile_as(
+        filename,
+        InputFormat::from_filename(filename),
+        &Pipeline::default(),
+    )
+}
+
+fn compile_as(filename: &str, input: InputFormat, pipeline: &Pipeline) -> Vec<Instruction> {
+    let bytes = match fs::read(filename) {
+        Ok(bytes) => bytes,
+        Err(err) => {
+            println!("Error reading file: {}", err);
+            process::exit(1);
+        }
+    };
+
+    // Binary programs have no text to point diagnostics at
+    let source = match input {
+        InputFormat::Binary => String::new(),
+        _ => match String::from_utf8(bytes.clone()) {
+            Ok(source) => source,
+            Err(_) => {
+                println!("Error reading file: stream did not contain valid UTF-8");
+                process::exit(1);
+            }
+        },
+    };
+
+    let result = match input {
+        InputFormat::Inq => pipeline.compile_str(&source),
+        InputFormat::Qasm => pipeline.compile_qasm(&source),
+        InputFormat::Json => pipeline.compile_json(&source),
+        InputFormat::Binary => pipeline.compile_binary(&bytes),
+    };
+
+    match result {
+        Ok(output) => output.program,
+        Err(err) => {
+            let color = io::stdout().is_terminal();
+            print!(
+                "{}",
+                diagnostic::render(&err, &source, Some(filename), color)
+            );
+            process::exit(1);
+        }
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use dustinw_qc::equivalence;
+    use dustinw_qc::lexer;
+    use dustinw_qc::parser;
+    use dustinw_qc::simulate;
     use std::fs;
     const TESTDATA_DIR: &str = "examples/testdata";
 
+    // Compiled program must match the source program up to global phase
+    fn assert_semantics_preserved(filename: &str) {
+        let tokens = lexer::tokenize(filename).unwrap();
+        let source_program = parser::parse(&tokens).unwrap();
+        let compiled_program = compile(filename);
+
+        assert!(simulate::equivalent_programs(
+            &source_program,
+            &compiled_program,
+            simulate::DEFAULT_TOLERANCE
+        )
+        .unwrap());
+    }
+
     // Provided sample input
 
     #[test]
     fn test_sample_input1() {
         let test_filename = format!("{TESTDATA_DIR}/sample_1.inq");
         let actual_program = compile(&test_filename);
-        let actual_string = prog_to_string(actual_program);
+        let actual_string = prog_to_string(&actual_program);
         let actual_iter = actual_string.lines();
 
         let compiled_filename = format!("{TESTDATA_DIR}/sample_1_compiled.inq");
@@ -126,7 +327,7 @@ mod tests {
     fn test_sample_input2() {
         let test_filename = format!("{TESTDATA_DIR}/sample_2.inq");
         let actual_program = compile(&test_filename);
-        let actual_string = prog_to_string(actual_program);
+        let actual_string = prog_to_string(&actual_program);
         let actual_iter = actual_string.lines();
 
         let compiled_filename = format!("{TESTDATA_DIR}/sample_2_compiled.inq");
@@ -140,7 +341,7 @@ mod tests {
     fn test_sample_input3() {
         let test_filename = format!("{TESTDATA_DIR}/sample_3.inq");
         let actual_program = compile(&test_filename);
-        let actual_string = prog_to_string(actual_program);
+        let actual_string = prog_to_string(&actual_program);
         let actual_iter = actual_string.lines();
 
         let compiled_filename = format!("{TESTDATA_DIR}/sample_3_compiled.inq");
@@ -149,4 +350,126 @@ mod tests {
 
         assert!(actual_iter.eq(expected_iter));
     }
+
+    // Command line arguments
+
+    #[test]
+    fn test_parse_args() {
+        let args =
+            |args: &[&str]| -> Vec<String> { args.iter().map(|arg| arg.to_string()).collect() };
+
+        let options = parse_args(&args(&["prog.inq"])).unwrap();
+        assert_eq!(
+            options,
+            Options {
+                filename: String::from("prog.inq"),
+                format: OutputFormat::Inq,
+                input: InputFormat::Inq,
+                highlight: None
+            }
+        );
+
+        let options = parse_args(&args(&["--format", "qasm3", "prog.inq"])).unwrap();
+        assert_eq!(options.format, OutputFormat::Qasm(QasmVersion::V3));
+
+        let options = parse_args(&args(&["prog.qasm", "--format=qasm2"])).unwrap();
+        assert_eq!(options.format, OutputFormat::Qasm(QasmVersion::V2));
+
+        let options = parse_args(&args(&["--format", "quil", "prog.inq"])).unwrap();
+        assert_eq!(options.format, OutputFormat::Quil);
+
+        let options = parse_args(&args(&["--format=json", "prog.inq"])).unwrap();
+        assert_eq!(options.format, OutputFormat::Json);
+
+        let options = parse_args(&args(&["--format", "binary-quantized", "prog.inq"])).unwrap();
+        assert_eq!(
+            options.format,
+            OutputFormat::Binary(AngleEncoding::Quantized)
+        );
+
+        let options = parse_args(&args(&["--format=ascii", "prog.inq"])).unwrap();
+        assert_eq!(options.format, OutputFormat::Ascii);
+
+        let options = parse_args(&args(&["--format", "svg", "prog.inq"])).unwrap();
+        assert_eq!(options.format, OutputFormat::Svg);
+
+        let options = parse_args(&args(&[
+            "--format",
+            "dot",
+            "--highlight",
+            "reorder",
+            "prog.inq",
+        ]))
+        .unwrap();
+        assert_eq!(options.format, OutputFormat::Dot);
+        assert_eq!(options.highlight, Some("reorder"));
+
+        // Input format follows the extension unless given explicitly
+        let options = parse_args(&args(&["prog.inqb"])).unwrap();
+        assert_eq!(options.input, InputFormat::Binary);
+        let options = parse_args(&args(&["--input=binary", "prog.bin"])).unwrap();
+        assert_eq!(options.input, InputFormat::Binary);
+        let options = parse_args(&args(&["prog.txt", "--input", "qasm"])).unwrap();
+        assert_eq!(options.input, InputFormat::Qasm);
+
+        assert_eq!(parse_args(&args(&[])).unwrap_err(), "No filename provided.");
+        assert_eq!(
+            parse_args(&args(&["--format"])).unwrap_err(),
+            "Missing value for --format"
+        );
+        assert_eq!(
+            parse_args(&args(&["--format", "png", "a.inq"])).unwrap_err(),
+            "Unknown output format 'png'"
+        );
+        assert_eq!(
+            parse_args(&args(&["--input", "svg", "a.inq"])).unwrap_err(),
+            "Unknown input format 'svg'"
+        );
+        assert_eq!(
+            parse_args(&args(&["--format=dot", "--highlight=fuse", "a.inq"])).unwrap_err(),
+            "Unknown pass 'fuse', expected one of: reorder, rotation_merge, euler_resynthesis, cz_cancel, deadcode"
+        );
+        assert_eq!(
+            parse_args(&args(&["--highlight", "reorder", "a.inq"])).unwrap_err(),
+            "--highlight requires --format dot"
+        );
+        assert_eq!(
+            parse_args(&args(&["--verbose", "a.inq"])).unwrap_err(),
+            "Unknown option '--verbose'"
+        );
+        assert_eq!(
+            parse_args(&args(&["a.inq", "b.inq"])).unwrap_err(),
+            "Unexpected argument 'b.inq'"
+        );
+    }
+
+    // Semantic equivalence of compiled output
+
+    #[test]
+    fn test_sample_semantics() {
+        for sample in ["sample_1", "sample_2", "sample_3"] {
+            assert_semantics_preserved(&format!("{TESTDATA_DIR}/{sample}.inq"));
+        }
+    }
+
+    #[test]
+    fn test_example_semantics() {
+        for example in ["ex1", "ex2", "ex3", "ex4", "ex6"] {
+            assert_semantics_preserved(&format!("examples/valid/{example}.inq"));
+        }
+
+        // Example 5 uses qubit 77, too many to simulate densely, so compare
+        // unitaries over the compacted qubits instead
+        let filename = "examples/valid/ex5.inq";
+        let tokens = lexer::tokenize(filename).unwrap();
+        let source_program = parser::parse(&tokens).unwrap();
+        let compiled_program = compile(filename);
+        assert!(equivalence::check_equivalence(
+            &source_program,
+            &compiled_program,
+            simulate::DEFAULT_TOLERANCE
+        )
+        .unwrap()
+        .is_equivalent());
+    }
 }
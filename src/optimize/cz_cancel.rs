@@ -1,3 +1,6 @@
+use crate::angle::Tolerance;
+use crate::dag::{CircuitDag, NodeId};
+use crate::error::CompileError;
 use crate::instruction::Instruction;
 
 pub fn cancellable(cz1: &Instruction, cz2: &Instruction) -> bool {
@@ -11,28 +14,80 @@ pub fn cancellable(cz1: &Instruction, cz2: &Instruction) -> bool {
     false
 }
 
-pub fn cz_cancel_pass(program: Vec<Instruction>) -> Result<Vec<Instruction>, String> {
-    let mut new_prog: Vec<Instruction> = Vec::new();
+// Cancel pairs of identical CZs that can be brought together by commuting
+// gates. CZ commutes with RZ on either operand, with other CZs and with
+// anything on unrelated qubits, so in CZ 0 1; RZ(0.2) 0; RZ(0.4) 3; CZ 1 0
+// both CZs are dropped. An RX or MEASURE on either operand keeps the pair.
+pub fn cz_cancel_pass(
+    program: Vec<Instruction>,
+    _tolerance: Tolerance,
+) -> Result<Vec<Instruction>, CompileError> {
+    let mut dag = CircuitDag::from_program(&program);
+
+    for id in dag.topological_order() {
+        if !dag.contains(id) {
+            continue;
+        }
+
+        // CZ is self-inverse, drop both instructions of a cancellable pair
+        if let Some(partner) = commuting_partner(&dag, id) {
+            dag.remove(id);
+            dag.remove(partner);
+        }
+    }
 
-    let mut i = 0;
-    while i < program.len() {
-        if i < program.len() - 1 {
-            if cancellable(&program[i], &program[i + 1]) {
-                i += 1;
+    Ok(dag.to_program())
+}
+
+// Nearest later CZ on the same qubits as CZ `id` with only gates commuting
+// with CZ between them on both wires
+fn commuting_partner(dag: &CircuitDag, id: NodeId) -> Option<NodeId> {
+    let Instruction::CZ(q1, q2) = *dag.instruction(id) else {
+        return None;
+    };
+
+    let mut current = dag.successor(id, q1);
+    while let Some(next) = current {
+        match dag.instruction(next) {
+            instr if cancellable(dag.instruction(id), instr) => {
+                // The first candidate is also the first on the other wire, so
+                // if that wire is blocked every later candidate is too
+                return commutes_until(dag, id, q2, next).then_some(next);
             }
+            Instruction::RZ(..) | Instruction::CZ(..) => {}
+            _ => return None,
         }
+        current = dag.successor(next, q1);
+    }
 
-        new_prog.push(program[i]);
-        i += 1
+    None
+}
+
+// True if every node between `from` and `to` on the wire of `q` commutes
+// with CZ
+fn commutes_until(dag: &CircuitDag, from: NodeId, q: u32, to: NodeId) -> bool {
+    let mut current = dag.successor(from, q);
+    while let Some(next) = current {
+        if next == to {
+            return true;
+        }
+        if !matches!(
+            dag.instruction(next),
+            Instruction::RZ(..) | Instruction::CZ(..)
+        ) {
+            return false;
+        }
+        current = dag.successor(next, q);
     }
 
-    return Ok(new_prog);
+    false
 }
 
 #[rustfmt::skip]
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::equivalence::testing::assert_pass_preserves_unitary;
     use std::f64::consts::PI;
 
     #[test]
@@ -56,7 +111,7 @@ mod tests {
 
         let expected_instr = init_instr.clone();
 
-        let actual_instr = cz_cancel_pass(init_instr).unwrap();
+        let actual_instr = cz_cancel_pass(init_instr, Tolerance::default()).unwrap();
 
         assert_eq!(expected_instr.len(), actual_instr.len());
 
@@ -80,17 +135,14 @@ mod tests {
             Instruction::CZ(1, 3),
         ];
 
-        let expected_instr = vec![
-            Instruction::CZ(1, 2),
+        let expected_instr = [
             Instruction::RZ(0.0, 77),
             Instruction::RX(-PI, 0),
-            Instruction::CZ(0, 1),
             Instruction::MEASURE(1),
             Instruction::RX(0.0, 77),
-            Instruction::CZ(1, 3),
         ];
 
-        let actual_instr = cz_cancel_pass(init_instr).unwrap();
+        let actual_instr = cz_cancel_pass(init_instr, Tolerance::default()).unwrap();
 
         assert_eq!(expected_instr.len(), actual_instr.len());
 
@@ -98,4 +150,84 @@ mod tests {
             assert_eq!(instr, &actual_instr[i]);
         }
     }
+
+    #[test]
+    fn cancel_across_unrelated_qubits() {
+        let init_instr = vec![
+            Instruction::CZ(0, 1),
+            Instruction::RZ(0.3, 5),
+            Instruction::RX(0.2, 2),
+            Instruction::CZ(1, 0),
+            Instruction::CZ(2, 3),
+            Instruction::RZ(0.3, 2),
+            Instruction::CZ(2, 3),
+        ];
+
+        let expected_instr = vec![
+            Instruction::RZ(0.3, 5),
+            Instruction::RX(0.2, 2),
+            Instruction::RZ(0.3, 2),
+        ];
+
+        assert_eq!(cz_cancel_pass(init_instr, Tolerance::default()).unwrap(), expected_instr);
+    }
+
+    #[test]
+    fn cancel_across_commuting_gates() {
+        let init_instr = vec![
+            Instruction::CZ(0, 1),
+            Instruction::RZ(0.2, 0),
+            Instruction::RZ(0.4, 3),
+            Instruction::CZ(1, 0),
+        ];
+        assert_eq!(cz_cancel_pass(init_instr, Tolerance::default()).unwrap(), vec![
+            Instruction::RZ(0.2, 0),
+            Instruction::RZ(0.4, 3),
+        ]);
+
+        // Other CZs commute too, even on a shared qubit
+        let init_instr = vec![
+            Instruction::CZ(0, 1),
+            Instruction::CZ(0, 2),
+            Instruction::RZ(0.5, 1),
+            Instruction::CZ(1, 2),
+            Instruction::CZ(0, 1),
+        ];
+        assert_eq!(cz_cancel_pass(init_instr, Tolerance::default()).unwrap(), vec![
+            Instruction::CZ(0, 2),
+            Instruction::RZ(0.5, 1),
+            Instruction::CZ(1, 2),
+        ]);
+    }
+
+    #[test]
+    fn stop_at_non_commuting_gates() {
+        // RX or MEASURE on either operand keeps the pair
+        for blocker in [Instruction::RX(0.2, 0), Instruction::RX(0.2, 1), Instruction::MEASURE(1)] {
+            let init_instr = vec![
+                Instruction::CZ(0, 1),
+                Instruction::RZ(0.2, 0),
+                blocker,
+                Instruction::CZ(0, 1),
+            ];
+            assert_eq!(cz_cancel_pass(init_instr.clone(), Tolerance::default()).unwrap(), init_instr);
+        }
+
+        // The nearest candidate is blocked, so the pair is kept
+        let init_instr = vec![
+            Instruction::CZ(0, 1),
+            Instruction::RX(0.2, 1),
+            Instruction::CZ(0, 1),
+            Instruction::CZ(0, 1),
+        ];
+        assert_eq!(cz_cancel_pass(init_instr, Tolerance::default()).unwrap(), vec![
+            Instruction::CZ(0, 1),
+            Instruction::RX(0.2, 1),
+        ]);
+    }
+
+    #[test]
+    fn random_circuit_equivalence() {
+        assert_pass_preserves_unitary(cz_cancel_pass);
+    }
 }
@@ -1,27 +1,30 @@
+use crate::angle::Tolerance;
+use crate::error::CompileError;
 use crate::instruction::Instruction;
 
-pub fn deadcode_pass(program: Vec<Instruction>) -> Result<Vec<Instruction>, String> {
+// Drop rotations that are the identity up to global phase, i.e. angles within
+// the tolerance of a multiple of 2π
+pub fn deadcode_pass(
+    program: Vec<Instruction>,
+    tolerance: Tolerance,
+) -> Result<Vec<Instruction>, CompileError> {
     let new_prog = program
         .into_iter()
         .filter(|instr| match instr {
-            Instruction::RZ(val, _) | Instruction::RX(val, _) => {
-                if *val == 0.0 {
-                    false
-                } else {
-                    true
-                }
-            }
+            Instruction::RZ(val, _) | Instruction::RX(val, _) => !tolerance.is_zero(*val),
             _ => true,
         })
         .collect();
 
-    return Ok(new_prog);
+    Ok(new_prog)
 }
 
 #[rustfmt::skip]
+#[allow(clippy::approx_constant)]
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::equivalence::testing::assert_pass_preserves_unitary;
     use std::f64::consts::PI;
 
     #[test]
@@ -38,7 +41,7 @@ mod tests {
 
         let expected_instr = init_instr.clone();
 
-        let actual_instr = deadcode_pass(init_instr).unwrap();
+        let actual_instr = deadcode_pass(init_instr, Tolerance::default()).unwrap();
 
         assert_eq!(expected_instr.len(), actual_instr.len());
 
@@ -62,7 +65,7 @@ mod tests {
             Instruction::RX(0.0, 77),
         ];
 
-        let expected_instr = vec![
+        let expected_instr = [
             Instruction::RZ(0.45, 0),
             Instruction::RX(PI/2.0, 0),
             Instruction::RX(-PI/2.0, 0),
@@ -72,7 +75,7 @@ mod tests {
             Instruction::MEASURE(1),
         ];
 
-        let actual_instr = deadcode_pass(init_instr).unwrap();
+        let actual_instr = deadcode_pass(init_instr, Tolerance::default()).unwrap();
 
         assert_eq!(expected_instr.len(), actual_instr.len());
 
@@ -80,4 +83,25 @@ mod tests {
             assert_eq!(instr, &actual_instr[i]);
         }
     }
+
+    #[test]
+    fn remove_near_zero_rotations() {
+        let init_instr = vec![
+            Instruction::RZ(6.283185307179586, 0),
+            Instruction::RX(1e-17, 1),
+            Instruction::RX(-4.0 * PI, 2),
+            Instruction::RZ(1e-6, 3),
+        ];
+        assert_eq!(deadcode_pass(init_instr.clone(), Tolerance::default()).unwrap(), vec![
+            Instruction::RZ(1e-6, 3),
+        ]);
+
+        // A looser epsilon also drops the small rotation
+        assert_eq!(deadcode_pass(init_instr, Tolerance::new(1e-3)).unwrap(), vec![]);
+    }
+
+    #[test]
+    fn random_circuit_equivalence() {
+        assert_pass_preserves_unitary(deadcode_pass);
+    }
 }
@@ -0,0 +1,271 @@
+use std::f64::consts::PI;
+
+use crate::angle::{self, Tolerance};
+use crate::complex::Complex;
+use crate::dag::{CircuitDag, NodeId};
+use crate::equivalence;
+use crate::error::CompileError;
+use crate::instruction::Instruction;
+
+use super::native_translation::native_rx_angle;
+
+// Fuse every maximal run of RX/RZ on a wire into its 2x2 unitary and
+// resynthesize it in native gates, at most
+// RZ(c) RX(π/2) RZ(θ) RX(-π/2) RZ(a). The run is only replaced when the
+// result is shorter, so chains never exceed five gates and already minimal
+// runs are left as written.
+pub fn euler_resynthesis_pass(
+    program: Vec<Instruction>,
+    tolerance: Tolerance,
+) -> Result<Vec<Instruction>, CompileError> {
+    let mut dag = CircuitDag::from_program(&program);
+
+    for q in dag.qubits() {
+        for run in single_qubit_runs(&dag, q) {
+            let gates: Vec<Instruction> = run.iter().map(|&id| *dag.instruction(id)).collect();
+            let synthesized = resynthesize(&gates, q, tolerance)?;
+            if synthesized.len() >= run.len() {
+                continue;
+            }
+
+            // The run is contiguous on the wire, so its nodes can be reused
+            // in order
+            for (i, &id) in run.iter().enumerate() {
+                match synthesized.get(i) {
+                    Some(&instr) => dag.set_instruction(id, instr),
+                    None => {
+                        dag.remove(id);
+                    }
+                }
+            }
+        }
+    }
+
+    Ok(dag.to_program())
+}
+
+// Maximal runs of at least two consecutive RX/RZ on the wire of `q`
+fn single_qubit_runs(dag: &CircuitDag, q: u32) -> Vec<Vec<NodeId>> {
+    let mut runs: Vec<Vec<NodeId>> = Vec::new();
+    let mut current: Vec<NodeId> = Vec::new();
+
+    for id in dag.wire(q) {
+        if matches!(
+            dag.instruction(id),
+            Instruction::RX(..) | Instruction::RZ(..)
+        ) {
+            current.push(id);
+            continue;
+        }
+        if current.len() > 1 {
+            runs.push(current);
+        }
+        current = Vec::new();
+    }
+    if current.len() > 1 {
+        runs.push(current);
+    }
+
+    runs
+}
+
+// Shortest native sequence on qubit `q` equal to `gates` up to global phase
+fn resynthesize(
+    gates: &[Instruction],
+    q: u32,
+    tolerance: Tolerance,
+) -> Result<Vec<Instruction>, CompileError> {
+    let on_zero: Vec<Instruction> = gates
+        .iter()
+        .map(|instr| match *instr {
+            Instruction::RX(f, _) => Instruction::RX(f, 0),
+            Instruction::RZ(f, _) => Instruction::RZ(f, 0),
+            _ => unreachable!(),
+        })
+        .collect();
+    let u = equivalence::unitary(&on_zero, 1)
+        .map_err(|message| CompileError::pass("euler_resynthesis", &message))?;
+    let (a, theta, c) = zyz_angles([[u.get(0, 0), u.get(0, 1)], [u.get(1, 0), u.get(1, 1)]]);
+
+    // RZ(a) RY(θ) RZ(c) is also RZ(a + π) RY(-θ) RZ(c + π), which may leave
+    // different rotations at zero
+    // The run itself comes first so plain merges win ties and keep their
+    // exact angles
+    let mut candidates = vec![gates.to_vec()];
+    for (a, theta, c) in [(a, theta, c), (a + PI, -theta, c + PI)] {
+        // RY(θ) = RX(-π/2) RZ(θ) RX(π/2)
+        candidates.push(vec![
+            Instruction::RZ(c, q),
+            Instruction::RX(PI / 2.0, q),
+            Instruction::RZ(theta, q),
+            Instruction::RX(-PI / 2.0, q),
+            Instruction::RZ(a, q),
+        ]);
+
+        // RY(θ) = RZ(π/2) RX(θ) RZ(-π/2), only native for some θ
+        if let Some(native) = native_rx_angle(theta, tolerance) {
+            candidates.push(vec![
+                Instruction::RZ(c - PI / 2.0, q),
+                Instruction::RX(native, q),
+                Instruction::RZ(a + PI / 2.0, q),
+            ]);
+        }
+    }
+
+    let best = candidates
+        .into_iter()
+        .map(|candidate| simplify(candidate, tolerance))
+        .min_by_key(Vec::len)
+        .unwrap();
+
+    Ok(best)
+}
+
+// Drop rotations that are the identity, merge neighbouring rotations of the
+// same kind and wrap angles
+fn simplify(gates: Vec<Instruction>, tolerance: Tolerance) -> Vec<Instruction> {
+    let mut simplified: Vec<Instruction> = Vec::new();
+    for instr in gates {
+        let merged = match (simplified.last().copied(), instr) {
+            (Some(Instruction::RX(f1, _)), Instruction::RX(f2, q)) => {
+                simplified.pop();
+                Instruction::RX(angle::normalize(f1 + f2), q)
+            }
+            (Some(Instruction::RZ(f1, _)), Instruction::RZ(f2, q)) => {
+                simplified.pop();
+                Instruction::RZ(angle::normalize(f1 + f2), q)
+            }
+            (_, Instruction::RX(f, q)) => Instruction::RX(angle::normalize(f), q),
+            (_, Instruction::RZ(f, q)) => Instruction::RZ(angle::normalize(f), q),
+            _ => instr,
+        };
+
+        match merged {
+            Instruction::RX(f, _) | Instruction::RZ(f, _) if tolerance.is_zero(f) => {}
+            _ => simplified.push(merged),
+        }
+    }
+
+    simplified
+}
+
+// Angles (a, θ, c) with u = RZ(a) RY(θ) RZ(c) up to global phase
+fn zyz_angles(u: [[Complex; 2]; 2]) -> (f64, f64, f64) {
+    // Remove the global phase so that det(v) = 1, then
+    // v = [[e^(-i(a+c)/2) cos(θ/2), ...], [e^(i(a-c)/2) sin(θ/2), ...]]
+    let det = u[0][0] * u[1][1] - u[0][1] * u[1][0];
+    let phase = Complex::cis(-det.arg() / 2.0);
+    let (v00, v10) = (u[0][0] * phase, u[1][0] * phase);
+
+    let (cos, sin) = (v00.abs(), v10.abs());
+    let theta = 2.0 * sin.atan2(cos);
+
+    // Either half is arbitrary when its magnitude vanishes
+    let sum = if cos > 1e-12 { -v00.arg() } else { 0.0 };
+    let diff = if sin > 1e-12 { v10.arg() } else { 0.0 };
+
+    (sum + diff, theta, sum - diff)
+}
+
+#[rustfmt::skip]
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::equivalence::testing::{assert_equivalent, assert_pass_preserves_unitary, random_program, Rng};
+    use crate::optimize::native_translation::native_translation_pass;
+
+    fn resynthesized(program: &[Instruction]) -> Vec<Instruction> {
+        let optimized = euler_resynthesis_pass(program.to_vec(), Tolerance::default()).unwrap();
+        assert_equivalent(program, &optimized);
+        optimized
+    }
+
+    #[test]
+    fn fuse_translated_rotations() {
+        // Two translated RXs on one wire fuse into a single RX
+        let program = native_translation_pass(vec![
+            Instruction::RX(0.45, 0),
+            Instruction::RX(0.3, 0),
+        ], Tolerance::default()).unwrap();
+        assert_eq!(program.len(), 10);
+
+        let optimized = resynthesized(&program);
+        assert_eq!(optimized.len(), 5);
+        assert!(matches!(optimized[1], Instruction::RX(f, 0) if f.abs() == PI/2.0));
+        assert!(matches!(optimized[3], Instruction::RX(f, 0) if f.abs() == PI/2.0));
+    }
+
+    #[test]
+    fn native_minimum() {
+        // Chains that reduce to fewer than five gates
+        let program = [
+            Instruction::RZ(0.3, 0),
+            Instruction::RX(PI/2.0, 0),
+            Instruction::RX(PI/2.0, 0),
+            Instruction::RZ(-0.3, 0),
+            Instruction::RX(PI/2.0, 0),
+            Instruction::RX(PI/2.0, 0),
+        ];
+        assert_eq!(resynthesized(&program).len(), 1);
+
+        let program = [
+            Instruction::RX(PI/2.0, 1),
+            Instruction::RZ(0.4, 1),
+            Instruction::RX(-PI/2.0, 1),
+            Instruction::RX(PI/2.0, 1),
+            Instruction::RZ(0.1, 1),
+            Instruction::RX(-PI/2.0, 1),
+        ];
+        assert_eq!(resynthesized(&program).len(), 3);
+
+        // Already minimal runs are left alone
+        let program = vec![
+            Instruction::RZ(0.3, 0),
+            Instruction::RX(PI/2.0, 0),
+            Instruction::RZ(0.3, 0),
+        ];
+        assert_eq!(resynthesized(&program), program);
+    }
+
+    #[test]
+    fn runs_end_at_other_gates() {
+        let program = vec![
+            Instruction::RX(PI/2.0, 0),
+            Instruction::RX(PI/2.0, 0),
+            Instruction::RZ(0.4, 1),
+            Instruction::RX(PI/2.0, 0),
+            Instruction::CZ(0, 1),
+            Instruction::RX(PI/2.0, 0),
+            Instruction::MEASURE(0),
+            Instruction::RX(PI/2.0, 0),
+        ];
+
+        let optimized = resynthesized(&program);
+        assert_eq!(optimized.len(), 6);
+        assert_eq!(&optimized[2..], &program[4..]);
+    }
+
+    #[test]
+    fn random_chains_fit_native_minimum() {
+        let mut rng = Rng::new(24);
+        for _ in 0..100 {
+            // One qubit, so no CZs
+            let len = 1 + rng.below(16) as usize;
+            let program = random_program(&mut rng, 1, len);
+            let translated = native_translation_pass(program, Tolerance::default()).unwrap();
+
+            let optimized = resynthesized(&translated);
+            assert!(optimized.len() <= 5);
+            for instr in &optimized {
+                if let Instruction::RX(f, _) = instr {
+                    assert!(native_rx_angle(*f, Tolerance::new(0.0)).is_some());
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn random_circuit_equivalence() {
+        assert_pass_preserves_unitary(euler_resynthesis_pass);
+    }
+}
@@ -1,5 +1,6 @@
 pub mod cz_cancel;
 pub mod deadcode;
+pub mod euler;
 pub mod native_translation;
 pub mod reorder;
 pub mod rotation_merge;
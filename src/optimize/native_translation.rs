@@ -1,51 +1,77 @@
 use std::f64::consts::PI;
 
+use crate::angle::Tolerance;
+use crate::error::CompileError;
 use crate::instruction::Instruction;
 
-pub fn is_native_instruction(instr: &Instruction) -> bool {
+// Angles supported natively by RX
+const NATIVE_RX_ANGLES: [f64; 5] = [0.0, PI / 2.0, -PI / 2.0, PI, -PI];
+
+pub fn is_native_instruction(instr: &Instruction, tolerance: Tolerance) -> bool {
     match instr {
-        Instruction::RX(val, _) => *val == 0.0 || val.abs() == PI || val.abs() == PI / 2.0,
+        Instruction::RX(val, _) => native_rx_angle(*val, tolerance).is_some(),
         _ => true,
     }
 }
 
-pub fn native_translation_pass(program: Vec<Instruction>) -> Result<Vec<Instruction>, String> {
+// Native angle equal to `val` within the tolerance, modulo 2π
+pub fn native_rx_angle(val: f64, tolerance: Tolerance) -> Option<f64> {
+    if NATIVE_RX_ANGLES.contains(&val) {
+        return Some(val);
+    }
+
+    NATIVE_RX_ANGLES
+        .into_iter()
+        .find(|&native| tolerance.approx_eq(val, native))
+}
+
+pub fn native_translation_pass(
+    program: Vec<Instruction>,
+    tolerance: Tolerance,
+) -> Result<Vec<Instruction>, CompileError> {
     let mut new_prog: Vec<Instruction> = Vec::new();
 
     for instr in program {
-        if !is_native_instruction(&instr) {
-            if let Instruction::RX(val, q) = instr {
-                // Use provided identity to translate non-native RX
-                new_prog.push(Instruction::RZ(PI / 2.0, q));
-                new_prog.push(Instruction::RX(PI / 2.0, q));
-                new_prog.push(Instruction::RZ(val, q));
-                new_prog.push(Instruction::RX(-PI / 2.0, q));
-                new_prog.push(Instruction::RZ(-PI / 2.0, q));
-            } else {
-                unreachable!()
-            }
-        } else {
-            new_prog.push(instr);
+        match instr {
+            Instruction::RX(val, q) => match native_rx_angle(val, tolerance) {
+                // Snap to the native angle, e.g. RX(3π/2) becomes RX(-π/2)
+                Some(native) => new_prog.push(Instruction::RX(native, q)),
+                None => {
+                    // Use provided identity to translate non-native RX
+                    new_prog.push(Instruction::RZ(PI / 2.0, q));
+                    new_prog.push(Instruction::RX(PI / 2.0, q));
+                    new_prog.push(Instruction::RZ(val, q));
+                    new_prog.push(Instruction::RX(-PI / 2.0, q));
+                    new_prog.push(Instruction::RZ(-PI / 2.0, q));
+                }
+            },
+            _ => new_prog.push(instr),
         }
     }
 
-    return Ok(new_prog);
+    Ok(new_prog)
 }
 
 #[rustfmt::skip]
+#[allow(clippy::approx_constant)]
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::equivalence::testing::assert_pass_preserves_unitary;
 
     #[test]
     fn test_is_native_instruction() {
-        assert!(is_native_instruction(&Instruction::RZ(1.11, 0)));
-        assert!(is_native_instruction(&Instruction::RX(0.0, 1)));
-        assert!(is_native_instruction(&Instruction::RX(PI/2.0, 2)));
-        assert!(is_native_instruction(&Instruction::RX(-PI/2.0, 3)));
-        assert!(is_native_instruction(&Instruction::RX(PI, 4)));
-        assert!(is_native_instruction(&Instruction::RX(-PI, 5)));
-        assert!(!is_native_instruction(&Instruction::RX(1.11, 6)));
+        let tolerance = Tolerance::default();
+        assert!(is_native_instruction(&Instruction::RZ(1.11, 0), tolerance));
+        assert!(is_native_instruction(&Instruction::RX(0.0, 1), tolerance));
+        assert!(is_native_instruction(&Instruction::RX(PI/2.0, 2), tolerance));
+        assert!(is_native_instruction(&Instruction::RX(-PI/2.0, 3), tolerance));
+        assert!(is_native_instruction(&Instruction::RX(PI, 4), tolerance));
+        assert!(is_native_instruction(&Instruction::RX(-PI, 5), tolerance));
+        assert!(!is_native_instruction(&Instruction::RX(1.11, 6), tolerance));
+        assert!(is_native_instruction(&Instruction::RX(3.0*PI/2.0, 7), tolerance));
+        assert!(is_native_instruction(&Instruction::RX(6.283185307179586, 8), tolerance));
+        assert!(!is_native_instruction(&Instruction::RX(PI/2.0 + 1e-6, 9), tolerance));
     }
 
     #[test]
@@ -63,7 +89,7 @@ mod tests {
 
         let expected_instr = init_instr.clone();
 
-        let actual_instr = native_translation_pass(init_instr).unwrap();
+        let actual_instr = native_translation_pass(init_instr, Tolerance::default()).unwrap();
 
         assert_eq!(expected_instr.len(), actual_instr.len());
 
@@ -78,7 +104,7 @@ mod tests {
             Instruction::RX(0.45, 0),
         ];
 
-        let expected_instr = vec![
+        let expected_instr = [
             Instruction::RZ(PI/2.0, 0),
             Instruction::RX(PI/2.0, 0),
             Instruction::RZ(0.45, 0),
@@ -86,7 +112,7 @@ mod tests {
             Instruction::RZ(-PI/2.0, 0),
         ];
 
-        let actual_instr = native_translation_pass(init_instr).unwrap();
+        let actual_instr = native_translation_pass(init_instr, Tolerance::default()).unwrap();
 
         assert_eq!(expected_instr.len(), actual_instr.len());
 
@@ -98,33 +124,40 @@ mod tests {
     #[test]
     fn non_native_fp_precision() {
         let init_instr = vec![
-            // Enough decimal places to be considered "equal" to PI/2
-            // Just going with the default Rust equality. Could
-            // implement an 'epsilon' value for varying degrees of
-            // accuracy.
+            // Exactly PI/2
             Instruction::RX(1.5707963267948966, 0),
 
-            // One decimal place too short
+            // One decimal place too short, but within the default epsilon
             Instruction::RX(1.570796326794896, 1),
+
+            // Whole turns are wrapped
+            Instruction::RX(3.0*PI/2.0, 2),
         ];
 
         let expected_instr = vec![
-            Instruction::RX(1.5707963267948966, 0),
+            Instruction::RX(PI/2.0, 0),
+            Instruction::RX(PI/2.0, 1),
+            Instruction::RX(-PI/2.0, 2),
+        ];
+
+        let actual_instr = native_translation_pass(init_instr.clone(), Tolerance::default()).unwrap();
+        assert_eq!(actual_instr, expected_instr);
+
+        // With a zero epsilon only exact native angles are kept
+        let actual_instr = native_translation_pass(init_instr[..2].to_vec(), Tolerance::new(0.0)).unwrap();
+        assert_eq!(actual_instr, vec![
+            Instruction::RX(PI/2.0, 0),
 
             Instruction::RZ(PI/2.0, 1),
             Instruction::RX(PI/2.0, 1),
             Instruction::RZ(1.570796326794896, 1),
             Instruction::RX(-PI/2.0, 1),
             Instruction::RZ(-PI/2.0, 1),
+        ]);
+    }
 
-        ];
-
-        let actual_instr = native_translation_pass(init_instr).unwrap();
-
-        assert_eq!(expected_instr.len(), actual_instr.len());
-
-        for (i, instr) in expected_instr.iter().enumerate() {
-            assert_eq!(instr, &actual_instr[i]);
-        }
-    }   
+    #[test]
+    fn random_circuit_equivalence() {
+        assert_pass_preserves_unitary(native_translation_pass);
+    }
 }
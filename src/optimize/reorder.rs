@@ -1,83 +1,88 @@
-use std::cmp::Ordering;
-
+use crate::angle::Tolerance;
+use crate::dag::{CircuitDag, NodeId};
+use crate::error::CompileError;
 use crate::instruction::Instruction;
 
 use super::cz_cancel::cancellable;
 
-pub fn reorder_pass(program: Vec<Instruction>) -> Result<Vec<Instruction>, String> {
-    let mut new_prog: Vec<Instruction> = Vec::new();
+pub fn reorder_pass(
+    program: Vec<Instruction>,
+    _tolerance: Tolerance,
+) -> Result<Vec<Instruction>, CompileError> {
+    let mut dag = CircuitDag::from_program(&program);
 
     // Assuming That Reording can only take place after
     // native instruction translation...
-    // For a given range of swappable instructions:
+    // For a given run of swappable instructions on a wire, RZs on the wire
+    // and CZs whose first operand is the wire:
     // - Separate all RZs and CZ
     //   - No reason to have RZ-CZ-RZ-CZ, instead of RZ-RZ-CZ-CZ etc.
-    // - Determine if CZ or RZ should go first.
-    //   - Could determine number of optimizations that can be made,
-    //     then compare with reverse. Take max
-    //   - I dont think it will matter? Any combinable / cancellable combination
-    //     will be contained within the range for RZ
-
-    let mut i = 0;
-    while i < program.len() {
-        match program[i] {
-            Instruction::RZ(_, q1) | Instruction::CZ(q1, _) => {
-                let mut range_instrs: Vec<Instruction> = Vec::new();
-                range_instrs.push(program[i]);
-                i += 1;
+    // - Put cancellable CZs next to each other
+    // Gates on other wires never interrupt a run.
+    for q in dag.qubits() {
+        for run in swappable_runs(&dag, q) {
+            // Prefer the order RZ, CZ, CZ1, CZ2, where CZ1 & CZ2
+            // are cancellable
+            let (rzs, mut czs): (Vec<NodeId>, Vec<NodeId>) = run
+                .into_iter()
+                .partition(|&id| matches!(dag.instruction(id), Instruction::RZ(..)));
+            if let Some(&first_cz) = czs.first() {
+                for &rz in &rzs {
+                    dag.move_before(rz, first_cz);
+                }
+            }
 
-                // Find range of swappable instructions
-                while i < program.len() {
-                    match program[i] {
-                        Instruction::RZ(_, q2) | Instruction::CZ(q2, _) => {
-                            if q1 == q2 {
-                                range_instrs.push(program[i]);
-                                i += 1;
-                                continue;
-                            }
-                        }
-                        _ => {}
+            let mut i = 0;
+            while i < czs.len() {
+                let partner = (i + 1..czs.len())
+                    .find(|&j| cancellable(dag.instruction(czs[i]), dag.instruction(czs[j])));
+
+                // Moving a CZ along one wire is refused if its other wire
+                // orders it the opposite way
+                if let Some(j) = partner {
+                    if dag.move_after(czs[j], q, czs[i]) {
+                        let cz = czs.remove(j);
+                        czs.insert(i + 1, cz);
+                        i += 1;
                     }
-
-                    i -= 1;
-                    break;
                 }
-
-                // Prefer the order RZ, CZ, CZ1, CZ2, where CZ1 & CZ2
-                // are cancellable
-                range_instrs.sort_by(|a, b| match a {
-                    Instruction::RZ(_, _) => Ordering::Less,
-                    Instruction::CZ(_, _) => match b {
-                        Instruction::RZ(_, _) => Ordering::Greater,
-                        Instruction::CZ(_, _) => {
-                            if cancellable(a, b) {
-                                Ordering::Equal
-                            } else {
-                                Ordering::Less
-                            }
-                        }
-                        _ => unreachable!(),
-                    },
-                    _ => unreachable!(),
-                });
-
-                new_prog.append(&mut range_instrs);
+                i += 1;
             }
+        }
+    }
+
+    Ok(dag.to_program())
+}
+
+// Maximal runs of consecutive RZs and CZs with first operand `q` on the wire
+// of `q`
+fn swappable_runs(dag: &CircuitDag, q: u32) -> Vec<Vec<NodeId>> {
+    let mut runs: Vec<Vec<NodeId>> = Vec::new();
+    let mut run: Vec<NodeId> = Vec::new();
+
+    for id in dag.wire(q) {
+        match *dag.instruction(id) {
+            Instruction::RZ(_, q1) | Instruction::CZ(q1, _) if q1 == q => run.push(id),
             _ => {
-                new_prog.push(program[i]);
+                if run.len() > 1 {
+                    runs.push(run);
+                }
+                run = Vec::new();
             }
         }
-
-        i += 1;
+    }
+    if run.len() > 1 {
+        runs.push(run);
     }
 
-    return Ok(new_prog);
+    runs
 }
 
 #[rustfmt::skip]
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::equivalence::testing::assert_pass_preserves_unitary;
 
     #[test]
     fn no_reordering_pass() {
@@ -96,7 +101,7 @@ mod tests {
 
         let expected_instrs = init_instrs.clone();
 
-        let actual_instrs = reorder_pass(init_instrs).unwrap();
+        let actual_instrs = reorder_pass(init_instrs, Tolerance::default()).unwrap();
 
         println!("Expected: {:?}", expected_instrs);
         println!("Actual:   {:?}", actual_instrs);
@@ -123,20 +128,22 @@ mod tests {
             Instruction::RZ(1.4, 1),
         ];
 
+        // RZ(1.4) 1 moves past the CZs even though RZ(0.45) 2 sits between
+        // them in the program
         let expected_instrs = vec![
             Instruction::RX(0.0, 1),
             Instruction::RZ(1.1, 1),
             Instruction::RZ(1.1, 1),
             Instruction::RZ(1.1, 1),
-            Instruction::CZ(1, 0),
+            Instruction::RZ(1.4, 1),
             Instruction::CZ(1, 2),
             Instruction::CZ(1, 2),
+            Instruction::CZ(1, 0),
             Instruction::RZ(0.45, 2),
-            Instruction::RZ(1.4, 1),
             Instruction::CZ(1, 2),
         ];
 
-        let actual_instrs = reorder_pass(init_instrs).unwrap();
+        let actual_instrs = reorder_pass(init_instrs, Tolerance::default()).unwrap();
 
         println!("Expected: {:?}", expected_instrs);
         println!("Actual:   {:?}", actual_instrs);
@@ -147,4 +154,9 @@ mod tests {
             assert_eq!(instr, &actual_instrs[i]);
         }
     }
+
+    #[test]
+    fn random_circuit_equivalence() {
+        assert_pass_preserves_unitary(reorder_pass);
+    }
 }
@@ -1,41 +1,61 @@
+use crate::angle::{self, Tolerance};
+use crate::dag::{CircuitDag, NodeId};
+use crate::error::CompileError;
 use crate::instruction::Instruction;
 
-pub fn rotation_merge_pass(mut program: Vec<Instruction>) -> Result<Vec<Instruction>, String> {
-    let mut new_prog: Vec<Instruction> = Vec::new();
-
-    let mut i = 0;
-    while i < program.len() {
-        if i < program.len() - 1 {
-            if let Instruction::RX(f1, q1) = program[i] {
-                if let Instruction::RX(ref mut f2, q2) = program[i + 1] {
-                    if q1 == q2 {
-                        // Add to next instruction and skip
-                        *f2 += f1;
-                        i += 1;
-                    }
-                }
+// Merge RX or RZ rotations on a wire into the next one of the same kind. Gates
+// on other qubits do not separate them, e.g. RZ(a) 0; RX(b) 1; RZ(c) 0 becomes
+// RX(b) 1; RZ(c + a) 0. RZ commutes with CZ, so RZ rotations also merge
+// across CZs on their qubit, leaving one RZ per run between RX and MEASURE.
+// Merged angles are wrapped into (-π, π].
+pub fn rotation_merge_pass(
+    program: Vec<Instruction>,
+    _tolerance: Tolerance,
+) -> Result<Vec<Instruction>, CompileError> {
+    let mut dag = CircuitDag::from_program(&program);
+
+    for id in dag.topological_order() {
+        let Some(next) = merge_target(&dag, id) else {
+            continue;
+        };
+
+        let merged = match (*dag.instruction(id), *dag.instruction(next)) {
+            (Instruction::RX(f1, q), Instruction::RX(f2, _)) => {
+                Instruction::RX(angle::normalize(f2 + f1), q)
             }
-            if let Instruction::RZ(f1, q1) = program[i] {
-                if let Instruction::RZ(ref mut f2, q2) = program[i + 1] {
-                    if q1 == q2 {
-                        *f2 += f1;
-                        i += 1;
-                    }
-                }
+            (Instruction::RZ(f1, q), Instruction::RZ(f2, _)) => {
+                Instruction::RZ(angle::normalize(f2 + f1), q)
             }
-        }
-
-        new_prog.push(program[i]);
-        i += 1
+            _ => continue,
+        };
+        dag.set_instruction(next, merged);
+        dag.remove(id);
     }
 
-    return Ok(new_prog);
+    Ok(dag.to_program())
+}
+
+// Next rotation on the wire of `id` it can be merged into, looking past CZs
+// for RZ
+fn merge_target(dag: &CircuitDag, id: NodeId) -> Option<NodeId> {
+    match *dag.instruction(id) {
+        Instruction::RX(_, q) => dag.successor(id, q),
+        Instruction::RZ(_, q) => {
+            let mut next = dag.successor(id, q)?;
+            while let Instruction::CZ(..) = dag.instruction(next) {
+                next = dag.successor(next, q)?;
+            }
+            Some(next)
+        }
+        _ => None,
+    }
 }
 
 #[rustfmt::skip]
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::equivalence::testing::assert_pass_preserves_unitary;
     use std::f64::consts::PI;
 
     #[test]
@@ -54,7 +74,7 @@ mod tests {
 
         let expected_instr = init_instr.clone();
 
-        let actual_instr = rotation_merge_pass(init_instr).unwrap();
+        let actual_instr = rotation_merge_pass(init_instr, Tolerance::default()).unwrap();
 
         assert_eq!(expected_instr.len(), actual_instr.len());
 
@@ -77,7 +97,7 @@ mod tests {
             Instruction::RX(-PI/2.0, 0),
         ];
 
-        let expected_instr = vec![
+        let expected_instr = [
             // Floating point shenanigans. Leaving it as is for now...
             Instruction::RZ(0.30000000000000004, 77),
             Instruction::RX(1.123, 1),
@@ -88,7 +108,7 @@ mod tests {
             Instruction::RX(0.0, 0),
         ];
 
-        let actual_instr = rotation_merge_pass(init_instr).unwrap();
+        let actual_instr = rotation_merge_pass(init_instr, Tolerance::default()).unwrap();
 
         assert_eq!(expected_instr.len(), actual_instr.len());
 
@@ -96,4 +116,65 @@ mod tests {
             assert_eq!(instr, &actual_instr[i]);
         }
     }
+
+    #[test]
+    fn merge_across_unrelated_qubits() {
+        let init_instr = vec![
+            Instruction::RZ(0.1, 0),
+            Instruction::RX(0.2, 1),
+            Instruction::RZ(0.2, 0),
+            Instruction::CZ(1, 2),
+            Instruction::RZ(0.4, 0),
+            Instruction::RX(0.3, 1),
+        ];
+
+        let expected_instr = vec![
+            Instruction::RX(0.2, 1),
+            Instruction::CZ(1, 2),
+            Instruction::RZ(0.7000000000000001, 0),
+            Instruction::RX(0.3, 1),
+        ];
+
+        assert_eq!(rotation_merge_pass(init_instr, Tolerance::default()).unwrap(), expected_instr);
+    }
+
+    #[test]
+    fn merge_rz_across_cz() {
+        let init_instr = vec![
+            Instruction::RZ(0.1, 0),
+            Instruction::CZ(0, 1),
+            Instruction::RZ(0.2, 1),
+            Instruction::CZ(2, 0),
+            Instruction::RZ(0.4, 0),
+            Instruction::RX(0.3, 0),
+            Instruction::CZ(0, 1),
+            Instruction::RZ(0.5, 0),
+        ];
+
+        // The RZs on qubit 0 before the RX merge, the one after it stays
+        let expected_instr = vec![
+            Instruction::CZ(0, 1),
+            Instruction::RZ(0.2, 1),
+            Instruction::CZ(2, 0),
+            Instruction::RZ(0.5, 0),
+            Instruction::RX(0.3, 0),
+            Instruction::CZ(0, 1),
+            Instruction::RZ(0.5, 0),
+        ];
+
+        assert_eq!(rotation_merge_pass(init_instr, Tolerance::default()).unwrap(), expected_instr);
+
+        // RX does not commute with CZ
+        let init_instr = vec![
+            Instruction::RX(0.1, 0),
+            Instruction::CZ(0, 1),
+            Instruction::RX(0.2, 0),
+        ];
+        assert_eq!(rotation_merge_pass(init_instr.clone(), Tolerance::default()).unwrap(), init_instr);
+    }
+
+    #[test]
+    fn random_circuit_equivalence() {
+        assert_pass_preserves_unitary(rotation_merge_pass);
+    }
 }
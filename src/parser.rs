@@ -1,231 +1,380 @@
+use crate::error::{CompileError, Span};
+use crate::expr::{self, Constant, Expected, Expr, ExprToken, ExprTokens};
 use crate::instruction::Instruction;
 use crate::token::Token;
 use crate::token::TokenType;
 use std::collections::VecDeque;
 
-pub fn parse(tokens: &Vec<Token>) -> Result<Vec<Instruction>, String> {
+// Program recovered from the token stream along with every error found
+#[derive(Debug, PartialEq)]
+pub struct ParseOutput {
+    pub program: Vec<Instruction>,
+    // Span of each instruction's first token, parallel to `program`
+    pub spans: Vec<Span>,
+    pub errors: Vec<CompileError>,
+}
+
+// Parse tokens, stopping at the first error
+pub fn parse(tokens: &[Token]) -> Result<Vec<Instruction>, CompileError> {
+    let output = parse_all(tokens);
+
+    match output.errors.into_iter().next() {
+        Some(err) => Err(err),
+        None => Ok(output.program),
+    }
+}
+
+// Parse tokens, resynchronizing at the next EOL after a malformed line so
+// that every error is reported. Lines that parse cleanly are kept in the
+// returned program.
+pub fn parse_all(tokens: &[Token]) -> ParseOutput {
     let mut program: Vec<Instruction> = Vec::new();
+    let mut spans: Vec<Span> = Vec::new();
+    let mut errors: Vec<CompileError> = Vec::new();
 
-    let mut iter = tokens.iter();
+    // Comments carry no meaning for the program
+    let mut iter = tokens
+        .iter()
+        .filter(|token| !matches!(token.t, TokenType::Comment(_)));
     while let Some(inst_token) = iter.next() {
-        // First inst_token in line should match function tokens.
-        // (RX, RZ, etc.)
-        let mut new_inst = match inst_token.t {
-            TokenType::RX => Instruction::RX(0.0, 0),
-            TokenType::RZ => Instruction::RZ(0.0, 0),
-            TokenType::CZ => Instruction::CZ(0, 0),
-            TokenType::MEASURE => Instruction::MEASURE(0),
-            _ => Instruction::INVALID,
-        };
-        if new_inst == Instruction::INVALID {
-            return Err(format!(
-                "Invalid inst_token at {}:{}, expected instruction. (RX, RZ, etc.)",
-                inst_token.line, inst_token.pos
-            ));
-        }
-
         // Match the rest of the tokens up to EOL
         let mut rem_tokens: VecDeque<&Token> = VecDeque::new();
-        while let Some(next_token) = iter.next() {
-            if next_token.t == TokenType::EOL {
-                break;
+        if inst_token.t != TokenType::EOL {
+            for next_token in iter.by_ref() {
+                if next_token.t == TokenType::EOL {
+                    break;
+                }
+                rem_tokens.push_back(next_token);
             }
-            rem_tokens.push_back(next_token);
         }
-        if rem_tokens.len() == 0 {
-            return Err(format!(
+
+        match parse_instruction(inst_token, rem_tokens) {
+            Ok(new_inst) => {
+                program.push(new_inst);
+                spans.push(Span::from(inst_token));
+            }
+            Err(err) => errors.push(err),
+        }
+    }
+
+    ParseOutput {
+        program,
+        spans,
+        errors,
+    }
+}
+
+// Parse a single line given its first token and the tokens following it
+fn parse_instruction(
+    inst_token: &Token,
+    mut rem_tokens: VecDeque<&Token>,
+) -> Result<Instruction, CompileError> {
+    // First inst_token in line should match function tokens.
+    // (RX, RZ, etc.)
+    let mut new_inst = match inst_token.t {
+        TokenType::RX => Instruction::RX(0.0, 0),
+        TokenType::RZ => Instruction::RZ(0.0, 0),
+        TokenType::CZ => Instruction::CZ(0, 0),
+        TokenType::MEASURE => Instruction::MEASURE(0),
+        _ => Instruction::INVALID,
+    };
+    if new_inst == Instruction::INVALID {
+        return Err(CompileError::syntax(
+            format!(
+                "Invalid inst_token at {}:{}, expected instruction. (RX, RZ, etc.)",
+                inst_token.line, inst_token.pos
+            ),
+            Span::from(inst_token),
+        )
+        .with_hint("each line must start with RX, RZ, CZ or MEASURE"));
+    }
+
+    if rem_tokens.is_empty() {
+        return Err(CompileError::syntax(
+            format!(
                 "Invalid or missing token sequence after instruction at {}:{}",
                 inst_token.line, inst_token.pos
-            ));
-        }
+            ),
+            Span::from(inst_token),
+        )
+        .with_hint(format!("usage: {}", usage(&inst_token.t))));
+    }
 
-        match new_inst {
-            Instruction::RX(_, _) | Instruction::RZ(_, _) => {
-                // Remaining tokens in the form '(', optional '-', Float|Int, ')', Int
-                let mut f_val;
-                let q_val;
+    match new_inst {
+        Instruction::RX(_, _) | Instruction::RZ(_, _) => {
+            // Remaining tokens in the form '(', angle expression, ')', Int
 
-                // Left paren token
-                if let Some(token) = rem_tokens.pop_front() {
-                    if token.t != TokenType::LParen {
-                        return Err(format!(
+            // Left paren token
+            if let Some(token) = rem_tokens.pop_front() {
+                if token.t != TokenType::LParen {
+                    return Err(CompileError::syntax(
+                        format!(
                             "Unexpected token at {}:{}, expected '('",
                             token.line, token.pos
-                        ));
-                    }
-                } else {
-                    return Err(format!(
+                        ),
+                        Span::from(token),
+                    )
+                    .with_hint(format!(
+                        "expected '(' after {}",
+                        instruction_name(&inst_token.t)
+                    )));
+                }
+            } else {
+                return Err(CompileError::syntax(
+                    format!(
                         "Missing '(' after instruction at {}:{}",
                         inst_token.line, inst_token.pos
-                    ));
-                }
+                    ),
+                    Span::from(inst_token),
+                )
+                .with_hint(format!(
+                    "expected '(' after {}",
+                    instruction_name(&inst_token.t)
+                )));
+            }
 
-                // Float value
-                if let Some(mut token) = rem_tokens.pop_front() {
-                    // Handle possible negative sign
-                    let mut negative = false;
-                    if token.t == TokenType::Negative {
-                        negative = true;
-                        let res = rem_tokens.pop_front();
-                        if res == None {
-                            return Err(format!(
-                                "Missing parameter for instruction at {}:{}",
-                                inst_token.line, inst_token.pos
-                            ));
-                        } else {
-                            token = res.unwrap();
-                        }
-                    }
-
-                    // Convert possible integer to float
-                    match token.t {
-                        TokenType::Float(f) => f_val = f,
-                        TokenType::Integer(u) => f_val = u as f64,
-                        _ => {
-                            return Err(format!(
-                                "Invalid token at {}:{}, exepected floating point value",
-                                token.line, token.pos
-                            ))
-                        }
-                    }
-                    if negative {
-                        f_val = -f_val;
-                    }
-                } else {
-                    return Err(format!(
-                        "Missing parameter for instruction at {}:{}",
-                        inst_token.line, inst_token.pos
-                    ));
-                }
+            // Rotation angle, folded to a single value
+            let expr = expr::parse(&mut AngleTokens {
+                inst_token,
+                rem_tokens: &mut rem_tokens,
+            })?;
+            let f_val = expr.eval();
+            if !f_val.is_finite() {
+                return Err(CompileError::semantic(
+                    format!(
+                        "Rotation angle {} is not a finite number at {}:{}",
+                        expr, inst_token.line, inst_token.pos
+                    ),
+                    Span::from(inst_token),
+                )
+                .with_hint("check the angle for a division by zero"));
+            }
 
-                // Right paren token
-                if let Some(token) = rem_tokens.pop_front() {
-                    if token.t != TokenType::RParen {
-                        return Err(format!(
-                            "Unexpected token at {}:{}, expected ')'",
-                            token.line, token.pos
-                        ));
-                    }
-                } else {
-                    return Err(format!(
-                        "Missing ')' after instruction at {}:{}",
-                        inst_token.line, inst_token.pos
-                    ));
-                }
+            expect_rparen(inst_token, &mut rem_tokens)?;
 
-                // QBit index
-                if let Some(token) = rem_tokens.pop_front() {
-                    match token.t {
-                        TokenType::Integer(u) => q_val = u,
-                        _ => {
-                            return Err(format!(
-                                "Unexpected token at {}:{}, expected qbit index",
-                                token.line, token.pos
-                            ))
-                        }
-                    }
-                } else {
-                    return Err(format!(
-                        "Missing qbit index after instruction at {}:{}",
-                        inst_token.line, inst_token.pos
-                    ));
-                }
+            // QBit index
+            let q_val = expect_qbit(inst_token, &mut rem_tokens)?;
 
-                new_inst = match new_inst {
-                    Instruction::RX(_, _) => Instruction::RX(f_val, q_val),
-                    Instruction::RZ(_, _) => Instruction::RZ(f_val, q_val),
-                    _ => Instruction::INVALID,
-                }
+            new_inst = match new_inst {
+                Instruction::RX(_, _) => Instruction::RX(f_val, q_val),
+                Instruction::RZ(_, _) => Instruction::RZ(f_val, q_val),
+                _ => Instruction::INVALID,
             }
+        }
 
-            Instruction::CZ(_, _) => {
-                let q1_val;
-                let q2_val;
-
-                // QBit index 1
-                if let Some(token) = rem_tokens.pop_front() {
-                    match token.t {
-                        TokenType::Integer(u) => q1_val = u,
-                        _ => {
-                            return Err(format!(
-                                "Unexpected token at {}:{}, expected qbit index",
-                                token.line, token.pos
-                            ))
-                        }
-                    }
-                } else {
-                    return Err(format!(
-                        "Missing qbit index after instruction at {}:{}",
-                        inst_token.line, inst_token.pos
-                    ));
-                }
+        Instruction::CZ(_, _) => {
+            // QBit index 1
+            let q1_val = expect_qbit(inst_token, &mut rem_tokens)?;
 
-                // QBit index 2
-                if let Some(token) = rem_tokens.pop_front() {
-                    match token.t {
-                        TokenType::Integer(u) => q2_val = u,
-                        _ => {
-                            return Err(format!(
-                                "Unexpected token at {}:{}, expected qbit index",
-                                token.line, token.pos
-                            ))
-                        }
-                    }
-                } else {
-                    return Err(format!(
-                        "Missing qbit index after instruction at {}:{}",
+            // QBit index 2
+            let q2_val = expect_qbit(inst_token, &mut rem_tokens)?;
+
+            // CZ acts on a pair of distinct qbits
+            if q1_val == q2_val {
+                return Err(CompileError::semantic(
+                    format!(
+                        "CZ requires two distinct qbits at {}:{}",
                         inst_token.line, inst_token.pos
-                    ));
-                }
+                    ),
+                    Span::from(inst_token),
+                )
+                .with_hint("use two different qbits, e.g. CZ 0 1"));
+            }
 
-                new_inst = match new_inst {
-                    Instruction::CZ(_, _) => Instruction::CZ(q1_val, q2_val),
-                    _ => Instruction::INVALID,
-                }
+            new_inst = match new_inst {
+                Instruction::CZ(_, _) => Instruction::CZ(q1_val, q2_val),
+                _ => Instruction::INVALID,
             }
+        }
 
-            Instruction::MEASURE(_) => {
-                let q_val;
-
-                // QBit index
-                if let Some(token) = rem_tokens.pop_front() {
-                    match token.t {
-                        TokenType::Integer(u) => q_val = u,
-                        _ => {
-                            return Err(format!(
-                                "Unexpected token at {}:{}, expected qbit index",
-                                token.line, token.pos
-                            ))
-                        }
-                    }
-                } else {
-                    return Err(format!(
-                        "Missing qbit index after instruction at {}:{}",
-                        inst_token.line, inst_token.pos
-                    ));
-                }
+        Instruction::MEASURE(_) => {
+            // QBit index
+            let q_val = expect_qbit(inst_token, &mut rem_tokens)?;
 
-                new_inst = match new_inst {
-                    Instruction::MEASURE(_) => Instruction::MEASURE(q_val),
-                    _ => Instruction::INVALID,
-                }
+            new_inst = match new_inst {
+                Instruction::MEASURE(_) => Instruction::MEASURE(q_val),
+                _ => Instruction::INVALID,
             }
+        }
+
+        Instruction::INVALID => unreachable!(),
+    };
+
+    // Anything left before the EOL would otherwise be silently dropped
+    if let Some(token) = rem_tokens.pop_front() {
+        return Err(CompileError::syntax(
+            format!(
+                "Unexpected token at {}:{}, expected end of line",
+                token.line, token.pos
+            ),
+            Span::from(token),
+        )
+        .with_hint(format!(
+            "write one instruction per line or separate them with ';', usage: {}",
+            usage(&inst_token.t)
+        )));
+    }
+
+    Ok(new_inst)
+}
+
+// Tokens of the rotation angle of an RX or RZ instruction
+struct AngleTokens<'t, 'i, 'a> {
+    inst_token: &'i Token,
+    rem_tokens: &'t mut VecDeque<&'a Token>,
+}
 
-            Instruction::INVALID => unreachable!(),
-        };
+impl ExprTokens for AngleTokens<'_, '_, '_> {
+    fn peek(&self) -> Option<ExprToken> {
+        self.rem_tokens.front().map(|token| match token.t {
+            TokenType::Plus => ExprToken::Plus,
+            TokenType::Negative => ExprToken::Minus,
+            TokenType::Star => ExprToken::Star,
+            TokenType::Slash => ExprToken::Slash,
+            TokenType::LParen => ExprToken::LParen,
+            TokenType::RParen => ExprToken::RParen,
+            TokenType::Float(f) => ExprToken::Operand(Expr::Number(f)),
+            TokenType::Integer(u) => ExprToken::Operand(Expr::Number(u as f64)),
+            TokenType::PI => ExprToken::Operand(Expr::Constant(Constant::Pi)),
+            TokenType::TAU => ExprToken::Operand(Expr::Constant(Constant::Tau)),
+            TokenType::E => ExprToken::Operand(Expr::Constant(Constant::E)),
+            _ => ExprToken::Other,
+        })
+    }
 
-        program.push(new_inst);
+    fn bump(&mut self) {
+        self.rem_tokens.pop_front();
+    }
+
+    fn expected(&self, expected: Expected) -> CompileError {
+        let inst_token = self.inst_token;
+        match (expected, self.rem_tokens.front()) {
+            (Expected::Operand, Some(token)) => CompileError::syntax(
+                format!(
+                    "Invalid token at {}:{}, expected floating point value",
+                    token.line, token.pos
+                ),
+                Span::from(*token),
+            )
+            .with_hint(format!(
+                "expected a rotation angle, usage: {}",
+                usage(&inst_token.t)
+            )),
+            (Expected::Operand, None) => CompileError::syntax(
+                format!(
+                    "Missing parameter for instruction at {}:{}",
+                    inst_token.line, inst_token.pos
+                ),
+                Span::from(inst_token),
+            )
+            .with_hint(format!(
+                "expected a rotation angle, usage: {}",
+                usage(&inst_token.t)
+            )),
+            (Expected::RParen, token) => rparen_error(inst_token, token.copied()),
+        }
     }
 
-    return Ok(program);
+    fn span(&self) -> Span {
+        self.rem_tokens
+            .front()
+            .map_or(Span::from(self.inst_token), |token| Span::from(*token))
+    }
+}
+
+fn expect_rparen(
+    inst_token: &Token,
+    rem_tokens: &mut VecDeque<&Token>,
+) -> Result<(), CompileError> {
+    match rem_tokens.pop_front() {
+        Some(token) if token.t == TokenType::RParen => Ok(()),
+        token => Err(rparen_error(inst_token, token)),
+    }
+}
+
+// Qbit index following an instruction
+fn expect_qbit(inst_token: &Token, rem_tokens: &mut VecDeque<&Token>) -> Result<u32, CompileError> {
+    match rem_tokens.pop_front() {
+        Some(token) => match token.t {
+            TokenType::Integer(u) => Ok(u),
+            _ => Err(CompileError::syntax(
+                format!(
+                    "Unexpected token at {}:{}, expected qbit index",
+                    token.line, token.pos
+                ),
+                Span::from(token),
+            )
+            .with_hint(format!(
+                "expected a qbit index, usage: {}",
+                usage(&inst_token.t)
+            ))),
+        },
+        None => Err(CompileError::syntax(
+            format!(
+                "Missing qbit index after instruction at {}:{}",
+                inst_token.line, inst_token.pos
+            ),
+            Span::from(inst_token),
+        )
+        .with_hint(format!(
+            "expected a qbit index, usage: {}",
+            usage(&inst_token.t)
+        ))),
+    }
+}
+
+fn rparen_error(inst_token: &Token, token: Option<&Token>) -> CompileError {
+    match token {
+        Some(token) => CompileError::syntax(
+            format!(
+                "Unexpected token at {}:{}, expected ')'",
+                token.line, token.pos
+            ),
+            Span::from(token),
+        )
+        .with_hint("expected ')' to close the rotation angle"),
+        None => CompileError::syntax(
+            format!(
+                "Missing ')' after instruction at {}:{}",
+                inst_token.line, inst_token.pos
+            ),
+            Span::from(inst_token),
+        )
+        .with_hint("expected ')' to close the rotation angle"),
+    }
+}
+
+fn instruction_name(t: &TokenType) -> &'static str {
+    match t {
+        TokenType::RX => "RX",
+        TokenType::RZ => "RZ",
+        TokenType::CZ => "CZ",
+        TokenType::MEASURE => "MEASURE",
+        _ => "instruction",
+    }
+}
+
+// Expected form of an instruction, used in error hints
+fn usage(t: &TokenType) -> &'static str {
+    match t {
+        TokenType::RX => "RX(angle) qbit",
+        TokenType::RZ => "RZ(angle) qbit",
+        TokenType::CZ => "CZ qbit qbit",
+        TokenType::MEASURE => "MEASURE qbit",
+        _ => "RX(angle) qbit",
+    }
 }
 
 #[rustfmt::skip]
+#[allow(clippy::approx_constant)]
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::token::Token;
     use crate::lexer;
+    use crate::angle::Tolerance;
+    use crate::optimize::native_translation;
+    use std::f64::consts::PI;
 
     const TESTDATA_DIR: &str = "examples/testdata";
 
@@ -241,7 +390,7 @@ mod tests {
             Token{t: TokenType::Integer(0), line: 1, pos: 7, len: 1},
             Token{t: TokenType::EOL, line: 1, pos: 8, len: 1},
         ];
-        let expected_instr = vec![
+        let expected_instr = [
             Instruction::RZ(0.0, 0),
         ];
 
@@ -281,7 +430,7 @@ mod tests {
             Token{t: TokenType::EOL, line: 3, pos: 11, len: 1},
         ];
 
-        let expected_instr = vec![
+        let expected_instr = [
             Instruction::RZ(-0.1, 0),
             Instruction::RX(-1.0, 1),
             Instruction::RZ(0.0, 0),
@@ -302,7 +451,7 @@ mod tests {
         ];
 
         let err = parse(&tokens).unwrap_err();
-        assert_eq!(err, "Missing parameter for instruction at 1:1")
+        assert_eq!(err.message(), "Missing parameter for instruction at 1:1")
     }
     
     #[test]
@@ -314,7 +463,7 @@ mod tests {
         ];
 
         let err = parse(&tokens).unwrap_err();
-        assert_eq!(err, "Invalid inst_token at 1:1, expected instruction. (RX, RZ, etc.)");
+        assert_eq!(err.message(), "Invalid inst_token at 1:1, expected instruction. (RX, RZ, etc.)");
     }
 
     #[test]
@@ -329,7 +478,7 @@ mod tests {
         ];
 
         let err = parse(&tokens).unwrap_err();
-        assert_eq!(err, "Invalid or missing token sequence after instruction at 1:1");
+        assert_eq!(err.message(), "Invalid or missing token sequence after instruction at 1:1");
     }
 
     #[test]
@@ -342,7 +491,8 @@ mod tests {
         ];
 
         let mut err = parse(&tokens).unwrap_err();
-        assert_eq!(err, "Unexpected token at 1:2, expected '('");
+        assert_eq!(err.message(), "Unexpected token at 1:2, expected '('");
+        assert_eq!(err.hint(), Some("expected '(' after RZ"));
 
         tokens = vec![
             Token{t: TokenType::RZ, line: 1, pos: 1, len: 2},
@@ -352,7 +502,7 @@ mod tests {
         ];
 
         err = parse(&tokens).unwrap_err();
-        assert_eq!(err, "Unexpected token at 1:9, expected ')'");
+        assert_eq!(err.message(), "Unexpected token at 1:9, expected ')'");
         
         tokens = vec![
             Token{t: TokenType::RZ, line: 1, pos: 1, len: 2},
@@ -362,7 +512,7 @@ mod tests {
         ];
 
         err = parse(&tokens).unwrap_err();
-        assert_eq!(err, "Missing ')' after instruction at 1:1");
+        assert_eq!(err.message(), "Missing ')' after instruction at 1:1");
     }
 
     #[test]
@@ -375,7 +525,7 @@ mod tests {
         ];
 
         let mut err = parse(&tokens).unwrap_err();
-        assert_eq!(err, "Invalid token at 1:3, exepected floating point value");
+        assert_eq!(err.message(), "Invalid token at 1:3, expected floating point value");
 
         tokens = vec![
             Token{t: TokenType::RZ, line: 1, pos: 1, len: 2},
@@ -384,7 +534,7 @@ mod tests {
         ];
 
         err = parse(&tokens).unwrap_err();
-        assert_eq!(err, "Missing parameter for instruction at 1:1");
+        assert_eq!(err.message(), "Missing parameter for instruction at 1:1");
     }
 
     #[test]
@@ -398,7 +548,7 @@ mod tests {
         ];
 
         let mut err = parse(&tokens).unwrap_err();
-        assert_eq!(err, "Missing qbit index after instruction at 1:1");
+        assert_eq!(err.message(), "Missing qbit index after instruction at 1:1");
 
         tokens = vec![
             Token{t: TokenType::RX, line: 1, pos: 1, len: 2},
@@ -410,7 +560,7 @@ mod tests {
         ];
 
         err = parse(&tokens).unwrap_err();
-        assert_eq!(err, "Unexpected token at 1:8, expected qbit index");
+        assert_eq!(err.message(), "Unexpected token at 1:8, expected qbit index");
 
         tokens = vec![
             Token{t: TokenType::CZ, line: 1, pos: 1, len: 2},
@@ -419,7 +569,7 @@ mod tests {
         ];
 
         err = parse(&tokens).unwrap_err();
-        assert_eq!(err, "Unexpected token at 1:3, expected qbit index");
+        assert_eq!(err.message(), "Unexpected token at 1:3, expected qbit index");
 
         tokens = vec![
             Token{t: TokenType::CZ, line: 1, pos: 1, len: 2},
@@ -429,7 +579,7 @@ mod tests {
         ];
 
         err = parse(&tokens).unwrap_err();
-        assert_eq!(err, "Unexpected token at 1:5, expected qbit index");
+        assert_eq!(err.message(), "Unexpected token at 1:5, expected qbit index");
 
         tokens = vec![
             Token{t: TokenType::MEASURE, line: 1, pos: 1, len: 7},
@@ -438,14 +588,152 @@ mod tests {
         ];
 
         err = parse(&tokens).unwrap_err();
-        assert_eq!(err, "Unexpected token at 1:9, expected qbit index");
+        assert_eq!(err.message(), "Unexpected token at 1:9, expected qbit index");
+        assert_eq!(err.span(), Some(Span::new(1, 9, 3)));
+    }
+
+    #[test]
+    fn parse_cz_same_qbit() {
+        let tokens = [
+            Token{t: TokenType::CZ, line: 2, pos: 1, len: 2},
+            Token{t: TokenType::Integer(1), line: 2, pos: 4, len: 1},
+            Token{t: TokenType::Integer(1), line: 2, pos: 6, len: 1},
+            Token{t: TokenType::EOL, line: 2, pos: 7, len: 1},
+        ];
+
+        let err = parse(&tokens).unwrap_err();
+        assert!(matches!(err, CompileError::Semantic { .. }));
+        assert_eq!(err.hint(), Some("use two different qbits, e.g. CZ 0 1"));
+        assert_eq!(err.message(), "CZ requires two distinct qbits at 2:1");
+        assert_eq!(err.span(), Some(Span::new(2, 1, 2)));
+    }
+
+    #[test]
+    fn parse_all_recovers_at_eol() {
+        let tokens = lexer::tokenize_str("RZ 0\nRX(0.5) 1\n(0.1) 0; MEASURE 1\nCZ 0 0\nRZ(-) 2\nCZ 1 2").unwrap();
+        let output = parse_all(&tokens);
+
+        assert_eq!(output.program, vec![
+            Instruction::RX(0.5, 1),
+            Instruction::MEASURE(1),
+            Instruction::CZ(1, 2),
+        ]);
+        assert_eq!(output.spans, vec![Span::new(2, 1, 2), Span::new(3, 10, 7), Span::new(6, 1, 2)]);
+
+        let messages: Vec<&str> = output.errors.iter().map(|err| err.message()).collect();
+        assert_eq!(messages, vec![
+            "Unexpected token at 1:4, expected '('",
+            "Invalid inst_token at 3:1, expected instruction. (RX, RZ, etc.)",
+            "CZ requires two distinct qbits at 4:1",
+            "Invalid token at 5:5, expected floating point value",
+        ]);
+
+        // parse stops at the first error
+        assert_eq!(parse(&tokens).unwrap_err(), output.errors[0]);
+    }
+
+    #[test]
+    fn parse_all_stray_eol() {
+        let tokens = lexer::tokenize_str(";\nMEASURE 0").unwrap();
+        let output = parse_all(&tokens);
+
+        assert_eq!(output.program, vec![Instruction::MEASURE(0)]);
+        assert_eq!(output.errors.len(), 1);
+        assert_eq!(output.errors[0].span(), Some(Span::new(1, 1, 1)));
+    }
+
+    #[test]
+    fn parse_trailing_tokens() {
+        let tokens = lexer::tokenize_str("RX(0.1) 0 /*\n*/ RZ(0.2) 1\nMEASURE 1").unwrap();
+        assert_eq!(parse(&tokens).unwrap(), vec![
+            Instruction::RX(0.1, 0),
+            Instruction::RZ(0.2, 1),
+            Instruction::MEASURE(1),
+        ]);
+
+        let tokens = lexer::tokenize_str("RX(0.1) 0 RZ(0.2) 1\nMEASURE 1").unwrap();
+        let output = parse_all(&tokens);
+        assert_eq!(output.errors.len(), 1);
+        assert_eq!(output.errors[0].message(), "Unexpected token at 1:11, expected end of line");
+        assert_eq!(output.errors[0].span(), Some(Span::new(1, 11, 2)));
+        assert_eq!(output.program, vec![Instruction::MEASURE(1)]);
+    }
+
+    #[test]
+    fn parse_all_skips_comments() {
+        let options = lexer::LexerOptions { keep_comments: true };
+        let source = "# prepare\nRX(0.45) /* qbit */ 0\nMEASURE 0 // done";
+        let tokens = lexer::tokenize_with_options(std::io::Cursor::new(source.as_bytes()), &options).unwrap();
+        let output = parse_all(&tokens);
+
+        assert!(output.errors.is_empty());
+        assert_eq!(output.program, vec![Instruction::RX(0.45, 0), Instruction::MEASURE(0)]);
+    }
+
+    #[test]
+    fn parse_angle_expressions() {
+        let tokens = lexer::tokenize_str("RZ(pi/2) 0\nRX(-3*pi/4) 1\nRZ((pi+0.1)/2) 0\nRX(tau - e) 2\nRZ(-pi) 3").unwrap();
+        let program = parse(&tokens).unwrap();
+
+        assert_eq!(program, vec![
+            Instruction::RZ(PI/2.0, 0),
+            Instruction::RX(-3.0*PI/4.0, 1),
+            Instruction::RZ((PI + 0.1)/2.0, 0),
+            Instruction::RX(2.0*PI - std::f64::consts::E, 2),
+            Instruction::RZ(-PI, 3),
+        ]);
+        assert!(native_translation::is_native_instruction(&program[0], Tolerance::default()));
+        assert!(native_translation::is_native_instruction(&program[4], Tolerance::default()));
+
+        // Precedence and associativity
+        let tokens = lexer::tokenize_str("RZ(1 + 2*3 - 8/4/2) 0").unwrap();
+        assert_eq!(parse(&tokens).unwrap(), vec![Instruction::RZ(6.0, 0)]);
+    }
+
+    #[test]
+    fn parse_invalid_angle_expressions() {
+        let tokens = lexer::tokenize_str("RZ(pi/) 0").unwrap();
+        assert_eq!(parse(&tokens).unwrap_err().message(), "Invalid token at 1:7, expected floating point value");
+
+        let tokens = lexer::tokenize_str("RZ((pi 0").unwrap();
+        assert_eq!(parse(&tokens).unwrap_err().message(), "Unexpected token at 1:8, expected ')'");
+
+        let tokens = lexer::tokenize_str("RZ(pi pi) 0").unwrap();
+        assert_eq!(parse(&tokens).unwrap_err().message(), "Unexpected token at 1:7, expected ')'");
+
+        let tokens = lexer::tokenize_str("RZ(pi*").unwrap();
+        assert_eq!(parse(&tokens).unwrap_err().message(), "Missing parameter for instruction at 1:1");
+
+        let tokens = lexer::tokenize_str("RX(1/0) 0").unwrap();
+        let err = parse(&tokens).unwrap_err();
+        assert!(matches!(err, CompileError::Semantic { .. }));
+        assert_eq!(err.message(), "Rotation angle (1/0) is not a finite number at 1:1");
+    }
+
+    #[test]
+    fn parse_deeply_nested_angle() {
+        let nested = |depth: usize| format!("RZ({}0{}) 0", "(".repeat(depth), ")".repeat(depth));
+        let tokens = lexer::tokenize_str(&nested(128)).unwrap();
+        assert_eq!(parse(&tokens).unwrap(), vec![Instruction::RZ(0.0, 0)]);
+
+        let tokens = lexer::tokenize_str(&nested(200000)).unwrap();
+        let err = parse(&tokens).unwrap_err();
+        assert!(matches!(err, CompileError::Syntax { .. }));
+        assert_eq!(err.message(), "Angle nested deeper than 128 levels at 1:132");
+        assert_eq!(err.span(), Some(Span::new(1, 132, 1)));
+
+        let tokens = lexer::tokenize_str(&format!("RZ({}1) 0", "-".repeat(200000))).unwrap();
+        assert_eq!(parse(&tokens).unwrap_err().message(), "Angle nested deeper than 128 levels at 1:132");
+
+        let tokens = lexer::tokenize_str(&format!("RZ({}1) 0", "1+".repeat(200000))).unwrap();
+        assert_eq!(parse(&tokens).unwrap_err().message(), "Angle nested deeper than 128 levels at 1:261");
     }
 
     // Sample input tests
 
     #[test]
     fn parse_sample_1() {
-        let expected_instr = vec![
+        let expected_instr = [
             Instruction::RX(0.45, 0),
         ];
 
@@ -462,7 +750,7 @@ mod tests {
 
     #[test]
     fn parse_sample_2() {
-        let expected_instr = vec![
+        let expected_instr = [
             Instruction::RX(0.45, 0),
             Instruction::RZ(-1.0, 0),
             Instruction::RZ(1.0, 1),
@@ -485,7 +773,7 @@ mod tests {
 
     #[test]
     fn parse_sample_3() {
-        let expected_instr = vec![
+        let expected_instr = [
             Instruction::RX(0.45, 0),
             Instruction::RZ(-1.0, 0),
             Instruction::RZ(1.0, 1),
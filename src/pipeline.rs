@@ -0,0 +1,395 @@
+use std::time::{Duration, Instant};
+
+use crate::angle::Tolerance;
+use crate::backend::binary;
+use crate::backend::json;
+use crate::cost::CostModel;
+use crate::error::CompileError;
+use crate::frontend;
+use crate::instruction::Instruction;
+use crate::lexer;
+use crate::parser;
+use crate::token::Token;
+
+use crate::optimize::cz_cancel;
+use crate::optimize::deadcode;
+use crate::optimize::euler;
+use crate::optimize::native_translation;
+use crate::optimize::reorder;
+use crate::optimize::rotation_merge;
+
+pub const DEFAULT_MAX_ROUNDS: u32 = 100;
+
+// Passes receive the pipeline's angle tolerance for comparing rotations
+pub type PassFn = fn(Vec<Instruction>, Tolerance) -> Result<Vec<Instruction>, CompileError>;
+
+// Named optimization pass
+#[derive(Debug, Clone, Copy)]
+pub struct Pass {
+    pub name: &'static str,
+    pub func: PassFn,
+}
+
+impl Pass {
+    pub fn new(name: &'static str, func: PassFn) -> Pass {
+        Pass { name, func }
+    }
+}
+
+// Accumulated statistics for one pass over every round it ran in
+#[derive(Debug, PartialEq, Clone)]
+pub struct PassStats {
+    pub name: &'static str,
+    pub runs: u32,
+    // Number of runs where the pass changed the program
+    pub changed_runs: u32,
+    pub instructions_added: usize,
+    pub instructions_removed: usize,
+    pub duration: Duration,
+}
+
+impl PassStats {
+    fn new(name: &'static str) -> PassStats {
+        PassStats {
+            name,
+            runs: 0,
+            changed_runs: 0,
+            instructions_added: 0,
+            instructions_removed: 0,
+            duration: Duration::ZERO,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct CompileOutput {
+    pub program: Vec<Instruction>,
+    // Number of optimization rounds performed
+    pub rounds: u32,
+    // Statistics for the setup passes followed by the optimization passes
+    pub stats: Vec<PassStats>,
+    // Cost of the program under the pipeline's cost model
+    pub cost: f64,
+    // Optimization pass that last lowered the cost, if any did
+    pub last_improvement: Option<&'static str>,
+}
+
+// Compilation pipeline. Setup passes run once, in order, before the
+// optimization passes are repeated until a round no longer lowers the cost
+// of the program under `cost_model`, or `max_rounds` is reached. Angles are
+// compared with `tolerance`.
+#[derive(Debug, Clone)]
+pub struct Pipeline {
+    pub setup_passes: Vec<Pass>,
+    pub passes: Vec<Pass>,
+    pub max_rounds: u32,
+    pub tolerance: Tolerance,
+    pub cost_model: CostModel,
+}
+
+impl Default for Pipeline {
+    fn default() -> Pipeline {
+        Pipeline {
+            setup_passes: vec![Pass::new(
+                "native instruction translation",
+                native_translation::native_translation_pass,
+            )],
+            passes: vec![
+                Pass::new("reorder", reorder::reorder_pass),
+                Pass::new("rotation_merge", rotation_merge::rotation_merge_pass),
+                Pass::new("euler_resynthesis", euler::euler_resynthesis_pass),
+                Pass::new("cz_cancel", cz_cancel::cz_cancel_pass),
+                Pass::new("deadcode", deadcode::deadcode_pass),
+            ],
+            max_rounds: DEFAULT_MAX_ROUNDS,
+            tolerance: Tolerance::default(),
+            cost_model: CostModel::default(),
+        }
+    }
+}
+
+impl Pipeline {
+    // Pipeline without any passes, the program is returned as parsed
+    pub fn empty() -> Pipeline {
+        Pipeline {
+            setup_passes: Vec::new(),
+            passes: Vec::new(),
+            max_rounds: DEFAULT_MAX_ROUNDS,
+            tolerance: Tolerance::default(),
+            cost_model: CostModel::default(),
+        }
+    }
+
+    pub fn with_setup_pass(mut self, name: &'static str, func: PassFn) -> Pipeline {
+        self.setup_passes.push(Pass::new(name, func));
+        self
+    }
+
+    pub fn with_pass(mut self, name: &'static str, func: PassFn) -> Pipeline {
+        self.passes.push(Pass::new(name, func));
+        self
+    }
+
+    pub fn with_max_rounds(mut self, max_rounds: u32) -> Pipeline {
+        self.max_rounds = max_rounds;
+        self
+    }
+
+    // Treat angles within `epsilon` of each other, modulo 2π, as equal
+    pub fn with_epsilon(mut self, epsilon: f64) -> Pipeline {
+        self.tolerance = Tolerance::new(epsilon);
+        self
+    }
+
+    pub fn with_cost_model(mut self, cost_model: CostModel) -> Pipeline {
+        self.cost_model = cost_model;
+        self
+    }
+
+    // Compile an in-memory .inq program
+    pub fn compile_str(&self, source: &str) -> Result<CompileOutput, CompileError> {
+        let tokens = lexer::tokenize_str(source)?;
+        self.run(parse_tokens(&tokens)?)
+    }
+
+    // Compile the .inq program stored in `filename`
+    pub fn compile_file(&self, filename: &str) -> Result<CompileOutput, CompileError> {
+        let tokens = lexer::tokenize(filename)?;
+        self.run(parse_tokens(&tokens)?)
+    }
+
+    // Compile an in-memory OpenQASM 2.0 or 3 program
+    pub fn compile_qasm(&self, source: &str) -> Result<CompileOutput, CompileError> {
+        self.run(frontend::parse_qasm(source)?)
+    }
+
+    // Compile a program stored in the JSON program format
+    pub fn compile_json(&self, source: &str) -> Result<CompileOutput, CompileError> {
+        self.run(json::json_to_prog(source)?)
+    }
+
+    // Compile a program stored in the binary program format
+    pub fn compile_binary(&self, bytes: &[u8]) -> Result<CompileOutput, CompileError> {
+        self.run(binary::decode(bytes)?)
+    }
+
+    // Run the passes over an already parsed program
+    pub fn run(&self, mut program: Vec<Instruction>) -> Result<CompileOutput, CompileError> {
+        let mut stats: Vec<PassStats> = self
+            .setup_passes
+            .iter()
+            .chain(self.passes.iter())
+            .map(|pass| PassStats::new(pass.name))
+            .collect();
+
+        for (i, pass) in self.setup_passes.iter().enumerate() {
+            program = run_pass(pass, program, self.tolerance, &mut stats[i])?;
+        }
+
+        // Cheapest program seen so far. Passes may trade one cost for
+        // another, so a round can end more expensive than it started.
+        let mut best_cost = self.cost_model.cost(&program);
+        let mut best_program = program.clone();
+        let mut last_improvement = None;
+
+        let mut rounds = 0;
+        while rounds < self.max_rounds {
+            rounds += 1;
+            let round_cost = best_cost;
+
+            // Perform optimization passes
+            for (i, pass) in self.passes.iter().enumerate() {
+                let pass_stats = &mut stats[self.setup_passes.len() + i];
+                program = run_pass(pass, program, self.tolerance, pass_stats)?;
+
+                let cost = self.cost_model.cost(&program);
+                if cost < best_cost {
+                    best_cost = cost;
+                    best_program = program.clone();
+                    last_improvement = Some(pass.name);
+                }
+            }
+
+            // Terminate optimization once a round stops lowering the cost
+            if best_cost >= round_cost {
+                break;
+            }
+        }
+
+        // Keep the final program unless it is worse than the best one, so
+        // changes that leave the cost unchanged, like reorders, are kept
+        if self.cost_model.cost(&program) > best_cost {
+            program = best_program;
+        }
+
+        Ok(CompileOutput {
+            cost: self.cost_model.cost(&program),
+            program,
+            rounds,
+            stats,
+            last_improvement,
+        })
+    }
+}
+
+// Parse tokens, reporting every malformed line at once
+fn parse_tokens(tokens: &[Token]) -> Result<Vec<Instruction>, CompileError> {
+    let output = parser::parse_all(tokens);
+    if !output.errors.is_empty() {
+        return Err(CompileError::from_errors(output.errors));
+    }
+
+    Ok(output.program)
+}
+
+fn run_pass(
+    pass: &Pass,
+    program: Vec<Instruction>,
+    tolerance: Tolerance,
+    stats: &mut PassStats,
+) -> Result<Vec<Instruction>, CompileError> {
+    let before = program.clone();
+    let start = Instant::now();
+
+    let new_prog = (pass.func)(program, tolerance)?;
+
+    stats.duration += start.elapsed();
+    stats.runs += 1;
+    if new_prog != before {
+        stats.changed_runs += 1;
+    }
+    if new_prog.len() > before.len() {
+        stats.instructions_added += new_prog.len() - before.len();
+    } else {
+        stats.instructions_removed += before.len() - new_prog.len();
+    }
+
+    Ok(new_prog)
+}
+
+#[rustfmt::skip]
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::Span;
+
+    fn failing_pass(_program: Vec<Instruction>, _tolerance: Tolerance) -> Result<Vec<Instruction>, CompileError> {
+        Err(CompileError::pass("failing", "something went wrong"))
+    }
+
+    fn growing_pass(mut program: Vec<Instruction>, _tolerance: Tolerance) -> Result<Vec<Instruction>, CompileError> {
+        program.push(Instruction::RZ(0.1, 0));
+        Ok(program)
+    }
+
+    fn stats_for<'a>(output: &'a CompileOutput, name: &str) -> &'a PassStats {
+        output.stats.iter().find(|stats| stats.name == name).unwrap()
+    }
+
+    #[test]
+    fn default_pipeline() {
+        let output = Pipeline::default().compile_str("RX(0.45) 0").unwrap();
+
+        assert_eq!(output.program.len(), 5);
+        assert_eq!(output.stats.len(), 6);
+
+        let translation = stats_for(&output, "native instruction translation");
+        assert_eq!(translation.runs, 1);
+        assert_eq!(translation.instructions_added, 4);
+
+        let reorder = stats_for(&output, "reorder");
+        assert_eq!(reorder.runs, output.rounds);
+        assert_eq!(reorder.changed_runs, 0);
+    }
+
+    #[test]
+    fn custom_pass_list() {
+        let pipeline = Pipeline::empty().with_pass("deadcode", deadcode::deadcode_pass);
+        let output = pipeline.compile_str("RX(0.0) 0\nRX(0.45) 1").unwrap();
+
+        assert_eq!(output.program, vec![Instruction::RX(0.45, 1)]);
+        assert_eq!(output.rounds, 2);
+
+        let deadcode = stats_for(&output, "deadcode");
+        assert_eq!(deadcode.runs, 2);
+        assert_eq!(deadcode.changed_runs, 1);
+        assert_eq!(deadcode.instructions_removed, 1);
+    }
+
+    #[test]
+    fn iteration_cap() {
+        let pipeline = Pipeline::default().with_max_rounds(1);
+        let output = pipeline.compile_str("RX(0.45) 0\nRX(0.45) 0").unwrap();
+        assert_eq!(output.rounds, 1);
+
+        let output = Pipeline::default().with_max_rounds(0).compile_str("RZ(0.0) 0").unwrap();
+        assert_eq!(output.rounds, 0);
+        assert_eq!(output.program, vec![Instruction::RZ(0.0, 0)]);
+    }
+
+    #[test]
+    fn cost_model_termination() {
+        // Reordering lowers the depth without changing the length
+        let source = "RX(0.5) 1; CZ 0 1; RZ(0.5) 0";
+        let pipeline = Pipeline::empty().with_pass("reorder", reorder::reorder_pass);
+
+        let output = pipeline.clone().compile_str(source).unwrap();
+        assert_eq!(output.rounds, 1);
+        assert_eq!(output.cost, 3.0);
+        assert_eq!(output.last_improvement, None);
+
+        let output = pipeline.with_cost_model(CostModel::depth()).compile_str(source).unwrap();
+        assert_eq!(output.rounds, 2);
+        assert_eq!(output.cost, 2.0);
+        assert_eq!(output.last_improvement, Some("reorder"));
+        assert_eq!(output.program, vec![Instruction::RX(0.5, 1), Instruction::RZ(0.5, 0), Instruction::CZ(0, 1)]);
+
+        let output = Pipeline::default().compile_str("RZ(0.5) 0; RZ(0.25) 0; CZ 0 1; CZ 1 0").unwrap();
+        assert_eq!(output.program, vec![Instruction::RZ(0.75, 0)]);
+        assert_eq!(output.last_improvement, Some("cz_cancel"));
+    }
+
+    #[test]
+    fn cost_never_increases() {
+        let pipeline = Pipeline::empty().with_pass("growing", growing_pass);
+        let output = pipeline.compile_str("RX(0.5) 0").unwrap();
+
+        assert_eq!(output.program, vec![Instruction::RX(0.5, 0)]);
+        assert_eq!(output.rounds, 1);
+        assert_eq!(output.cost, 1.0);
+        assert_eq!(stats_for(&output, "growing").instructions_added, 1);
+    }
+
+    #[test]
+    fn angle_epsilon() {
+        let source = "RZ(6.283185307179586) 0\nRX(0.3) 1\nRX(-0.29999999999) 1";
+        let output = Pipeline::default().compile_str(source).unwrap();
+        assert_eq!(output.program, vec![]);
+
+        let output = Pipeline::default().with_epsilon(0.0).compile_str(source).unwrap();
+        assert_eq!(output.program.len(), 5);
+        assert!(matches!(output.program[2], Instruction::RZ(f, 1) if f.abs() < 1e-10));
+    }
+
+    #[test]
+    fn typed_errors() {
+        let err = Pipeline::default().compile_str("RY(1.0) 0").unwrap_err();
+        assert!(matches!(err, CompileError::Lexical { .. }));
+        assert_eq!(err.span(), Some(Span::new(1, 1, 2)));
+
+        let err = Pipeline::default().compile_str("RX 0").unwrap_err();
+        assert!(matches!(err, CompileError::Syntax { .. }));
+        assert_eq!(err.message(), "Unexpected token at 1:4, expected '('");
+        assert_eq!(err.span(), Some(Span::new(1, 4, 1)));
+
+        let err = Pipeline::default().compile_str("RX 0\nRZ(0.1) 1\nCZ 2 2\nMEASURE").unwrap_err();
+        assert_eq!(err.errors().len(), 3);
+        assert_eq!(err.errors()[2].span(), Some(Span::new(4, 1, 7)));
+
+        let err = Pipeline::empty()
+            .with_pass("failing", failing_pass)
+            .compile_str("MEASURE 0")
+            .unwrap_err();
+        assert_eq!(err.to_string(), "failing: something went wrong");
+    }
+}
@@ -0,0 +1,152 @@
+use crate::instruction::Instruction;
+use crate::render::label;
+use crate::render::layout::layout;
+
+// Draw program as a text circuit diagram with one wire per qubit and one
+// column per moment. RX/RZ are boxes labelled with their angle, CZ is a
+// vertical connector between two '*' and MEASURE is a [M] meter:
+//
+// q0: --[RX(0.45)]--*--[M]--
+//                   |
+// q1: --------------*--[M]--
+//
+// Only qubits the program uses get a wire.
+pub fn prog_to_ascii(program: &[Instruction]) -> String {
+    let layout = layout(program);
+    if layout.qubits.is_empty() {
+        return String::new();
+    }
+
+    // Even rows are wires, odd rows the gaps between them
+    let labels: Vec<String> = layout.qubits.iter().map(|q| format!("q{}: ", q)).collect();
+    let label_width = labels.iter().map(|label| label.len()).max().unwrap();
+    let mut rows: Vec<Vec<char>> = (0..2 * layout.qubits.len() - 1)
+        .map(|row| match row % 2 {
+            0 => format!("{:<label_width$}-", labels[row / 2])
+                .chars()
+                .collect(),
+            _ => vec![' '; label_width + 1],
+        })
+        .collect();
+
+    for moment in &layout.moments {
+        let gates: Vec<(&Instruction, String)> = moment
+            .iter()
+            .map(|&i| (&program[i], gate_label(&program[i])))
+            .collect();
+        let width = gates
+            .iter()
+            .map(|(_, label)| label.chars().count())
+            .max()
+            .unwrap();
+
+        // Column of width + 2 with a wire character either side of the gates
+        let start = rows[0].len();
+        for (row, chars) in rows.iter_mut().enumerate() {
+            let fill = if row % 2 == 0 { '-' } else { ' ' };
+            chars.extend(std::iter::repeat_n(fill, width + 2));
+        }
+        let center = start + 1 + (width - 1) / 2;
+
+        for (instr, label) in gates {
+            let (low, high) = layout.occupied(instr).unwrap();
+            let (low, high) = (2 * low, 2 * high);
+            for row in rows.iter_mut().take(high).skip(low + 1) {
+                row[center] = '|';
+            }
+
+            let len = label.chars().count();
+            let offset = start + 1 + (width - len) / 2;
+            for row in [low, high] {
+                for (i, ch) in label.chars().enumerate() {
+                    rows[row][offset + i] = ch;
+                }
+            }
+        }
+    }
+
+    let lines: Vec<String> = rows
+        .iter()
+        .enumerate()
+        .map(|(row, chars)| {
+            let line: String = chars.iter().collect();
+            match row % 2 {
+                0 => line + "-",
+                _ => line.trim_end().to_string(),
+            }
+        })
+        .collect();
+    lines.join("\n")
+}
+
+fn gate_label(instr: &Instruction) -> String {
+    match *instr {
+        Instruction::RX(f, _) => format!("[RX({})]", label::decimal(f)),
+        Instruction::RZ(f, _) => format!("[RZ({})]", label::decimal(f)),
+        Instruction::CZ(_, _) => String::from("*"),
+        Instruction::MEASURE(_) => String::from("[M]"),
+        Instruction::INVALID => panic!("invalid instruction"),
+    }
+}
+
+#[rustfmt::skip]
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f64::consts::PI;
+
+    #[test]
+    fn program_to_ascii() {
+        let program = [
+            Instruction::RX(0.45, 0),
+            Instruction::CZ(0, 1),
+            Instruction::MEASURE(0),
+            Instruction::MEASURE(1),
+        ];
+
+        let expected = "\
+q0: --[RX(0.45)]--*--[M]--
+                  |
+q1: --------------*--[M]--";
+        assert_eq!(prog_to_ascii(&program), expected);
+    }
+
+    #[test]
+    fn connector_crosses_wires() {
+        let program = [
+            Instruction::RZ(-PI/2.0, 1),
+            Instruction::CZ(2, 0),
+            Instruction::RX(1.0, 0),
+        ];
+
+        let expected = "\
+q0: -----------------*--[RX(1)]--
+                     |
+q1: --[RZ(-1.5708)]--|-----------
+                     |
+q2: -----------------*-----------";
+        assert_eq!(prog_to_ascii(&program), expected);
+    }
+
+    #[test]
+    fn wide_labels() {
+        let program: Vec<Instruction> = (0..11).map(Instruction::MEASURE).collect();
+        let ascii = prog_to_ascii(&program);
+        let lines: Vec<&str> = ascii.lines().collect();
+
+        assert_eq!(lines[0], "q0:  --[M]--");
+        assert_eq!(lines[20], "q10: --[M]--");
+        assert_eq!(prog_to_ascii(&[]), "");
+    }
+
+    #[test]
+    fn sparse_qubits() {
+        let program = [Instruction::CZ(4000000000, 2), Instruction::MEASURE(2)];
+
+        let expected = "\
+q2:          --*--[M]--
+               |
+q4000000000: --*-------";
+        assert_eq!(prog_to_ascii(&program), expected);
+    }
+}
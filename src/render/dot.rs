@@ -0,0 +1,179 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::instruction::Instruction;
+
+// Largest LCS table changed_instructions builds, in cells
+const MAX_DIFF_CELLS: usize = 1 << 22;
+
+// Render the dependency graph of program in Graphviz DOT. Each instruction
+// is a node and an edge, labelled with the qubit, joins consecutive
+// instructions on the same qubit. Nodes whose index is in `highlight` are
+// filled, e.g. the output of `changed_instructions`.
+pub fn prog_to_dot(program: &[Instruction], highlight: &[usize]) -> String {
+    let highlight: BTreeSet<usize> = highlight.iter().copied().collect();
+    let mut output = String::from("digraph program {\n");
+    output.push_str("  node [shape=box, fontname=\"monospace\"];\n");
+
+    for (i, instr) in program.iter().enumerate() {
+        let style = if highlight.contains(&i) {
+            ", style=filled, fillcolor=\"lightblue\""
+        } else {
+            ""
+        };
+        output.push_str(&format!(
+            "  n{} [label=\"{}: {}\"{}];\n",
+            i, i, instr, style
+        ));
+    }
+
+    // Last instruction seen on each qubit
+    let mut last: BTreeMap<u32, usize> = BTreeMap::new();
+    for (i, instr) in program.iter().enumerate() {
+        // Operands sharing a predecessor get a single edge, e.g. CZ 0 1; CZ 0 1
+        let mut edges: Vec<(usize, Vec<u32>)> = Vec::new();
+        for q in instr.qubits() {
+            if let Some(prev) = last.insert(q, i) {
+                match edges.iter_mut().find(|(from, _)| *from == prev) {
+                    Some((_, qubits)) => qubits.push(q),
+                    None => edges.push((prev, vec![q])),
+                }
+            }
+        }
+
+        for (from, qubits) in edges {
+            let label: Vec<String> = qubits.iter().map(|q| format!("q{}", q)).collect();
+            output.push_str(&format!(
+                "  n{} -> n{} [label=\"{}\"];\n",
+                from,
+                i,
+                label.join(", ")
+            ));
+        }
+    }
+
+    output.push('}');
+    output
+}
+
+// Indices of instructions in `after` that are not in a longest common
+// subsequence with `before`, i.e. the ones a pass added or moved. Only the
+// part between the common prefix and suffix is diffed, and if that is still
+// too large all of it is reported as changed.
+pub fn changed_instructions(before: &[Instruction], after: &[Instruction]) -> Vec<usize> {
+    let prefix = before.iter().zip(after).take_while(|(a, b)| a == b).count();
+    let suffix = before[prefix..]
+        .iter()
+        .rev()
+        .zip(after[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let before = &before[prefix..before.len() - suffix];
+    let after = &after[prefix..after.len() - suffix];
+
+    let cells = (before.len() + 1).saturating_mul(after.len() + 1);
+    if cells > MAX_DIFF_CELLS {
+        return (prefix..prefix + after.len()).collect();
+    }
+
+    // lcs[i][j] is the LCS length of before[i..] and after[j..]
+    let mut lcs = vec![vec![0usize; after.len() + 1]; before.len() + 1];
+    for i in (0..before.len()).rev() {
+        for j in (0..after.len()).rev() {
+            lcs[i][j] = if before[i] == after[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+
+    let mut changed = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while j < after.len() {
+        if i < before.len() && before[i] == after[j] {
+            i += 1;
+            j += 1;
+        } else if i < before.len() && lcs[i + 1][j] >= lcs[i][j + 1] {
+            i += 1;
+        } else {
+            changed.push(prefix + j);
+            j += 1;
+        }
+    }
+
+    changed
+}
+
+#[rustfmt::skip]
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::angle::Tolerance;
+    use crate::optimize::reorder::reorder_pass;
+
+    #[test]
+    fn program_to_dot() {
+        let program = [
+            Instruction::RX(0.45, 0),
+            Instruction::RZ(1.0, 1),
+            Instruction::CZ(0, 1),
+            Instruction::CZ(1, 0),
+            Instruction::MEASURE(1),
+        ];
+
+        let expected = "\
+digraph program {
+  node [shape=box, fontname=\"monospace\"];
+  n0 [label=\"0: RX(0.45) 0\"];
+  n1 [label=\"1: RZ(1) 1\"];
+  n2 [label=\"2: CZ 0 1\", style=filled, fillcolor=\"lightblue\"];
+  n3 [label=\"3: CZ 1 0\"];
+  n4 [label=\"4: MEASURE 1\"];
+  n0 -> n2 [label=\"q0\"];
+  n1 -> n2 [label=\"q1\"];
+  n2 -> n3 [label=\"q1, q0\"];
+  n3 -> n4 [label=\"q1\"];
+}";
+        assert_eq!(prog_to_dot(&program, &[2]), expected);
+        assert_eq!(prog_to_dot(&[], &[]), "digraph program {\n  node [shape=box, fontname=\"monospace\"];\n}");
+    }
+
+    #[test]
+    fn changed_by_pass() {
+        let before = vec![
+            Instruction::RX(0.5, 0),
+            Instruction::RZ(0.5, 1),
+            Instruction::MEASURE(0),
+        ];
+
+        // Unchanged program highlights nothing
+        assert_eq!(changed_instructions(&before, &before), Vec::<usize>::new());
+
+        let after = vec![
+            Instruction::RZ(0.5, 1),
+            Instruction::RX(0.5, 0),
+            Instruction::RX(0.25, 2),
+            Instruction::MEASURE(0),
+        ];
+        assert_eq!(changed_instructions(&before, &after), vec![1, 2]);
+        assert_eq!(changed_instructions(&[], &after), vec![0, 1, 2, 3]);
+        assert_eq!(changed_instructions(&before, &[]), Vec::<usize>::new());
+
+        // Only the middle of a large program is diffed
+        let mut large: Vec<Instruction> = (0..5000).map(|q| Instruction::MEASURE(q % 7)).collect();
+        let before_large = large.clone();
+        large[2500] = Instruction::RX(0.5, 0);
+        assert_eq!(changed_instructions(&before_large, &large), vec![2500]);
+
+        // Past the size limit the whole differing region is reported
+        let mut reversed = before_large.clone();
+        reversed.reverse();
+        assert_eq!(changed_instructions(&before_large, &reversed).len(), 5000);
+
+        // Moving the RZ ahead of the CZ marks one of the two as changed
+        let program = vec![Instruction::CZ(0, 1), Instruction::RZ(0.5, 0)];
+        let reordered = reorder_pass(program.clone(), Tolerance::default()).unwrap();
+        assert_eq!(reordered, vec![Instruction::RZ(0.5, 0), Instruction::CZ(0, 1)]);
+        assert_eq!(changed_instructions(&program, &reordered).len(), 1);
+    }
+}
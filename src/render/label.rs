@@ -0,0 +1,80 @@
+use std::f64::consts::PI;
+
+// Decimal places shown for angles that are not a simple multiple of π
+const ANGLE_PRECISION: usize = 4;
+
+// Largest denominator recognized in a multiple of π
+const MAX_DENOMINATOR: u64 = 16;
+
+// Fixed precision with trailing zeros dropped, e.g. 0.4500 -> 0.45
+pub fn decimal(f: f64) -> String {
+    let text = format!("{:.*}", ANGLE_PRECISION, f);
+    let text = text.trim_end_matches('0').trim_end_matches('.');
+    match text {
+        "-0" => String::from("0"),
+        _ => text.to_string(),
+    }
+}
+
+// Angle as a reduced fraction n/d of π, if it is one up to rounding error
+pub fn pi_fraction(f: f64) -> Option<(i64, u64)> {
+    let turns = f / PI;
+    (1..=MAX_DENOMINATOR).find_map(|d| {
+        let n = (turns * d as f64).round();
+        let exact = (n / d as f64 - turns).abs() <= 1e-12 * turns.abs().max(1.0);
+        (exact && n.abs() < i64::MAX as f64).then_some((n as i64, d))
+    })
+}
+
+// Angle written as a multiple of π when exact, e.g. 3π/4, otherwise as a
+// decimal. `pi` is the symbol to use, such as "π" or "\pi".
+pub fn angle(f: f64, pi: &str) -> String {
+    match pi_fraction(f) {
+        Some((0, _)) => String::from("0"),
+        Some((n, d)) => {
+            let sign = if n < 0 { "-" } else { "" };
+            let numerator = match n.unsigned_abs() {
+                1 => pi.to_string(),
+                n => format!("{}{}", n, pi),
+            };
+            match d {
+                1 => format!("{}{}", sign, numerator),
+                d => format!("{}{}/{}", sign, numerator, d),
+            }
+        }
+        None => decimal(f),
+    }
+}
+
+#[rustfmt::skip]
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decimal_angles() {
+        assert_eq!(decimal(0.45), "0.45");
+        assert_eq!(decimal(-1.0), "-1");
+        assert_eq!(decimal(PI), "3.1416");
+        assert_eq!(decimal(-0.00001), "0");
+    }
+
+    #[test]
+    fn pi_multiples() {
+        assert_eq!(pi_fraction(PI), Some((1, 1)));
+        assert_eq!(pi_fraction(-PI/2.0), Some((-1, 2)));
+        assert_eq!(pi_fraction(3.0*PI/4.0), Some((3, 4)));
+        assert_eq!(pi_fraction(0.0), Some((0, 1)));
+        assert_eq!(pi_fraction(0.45), None);
+        assert_eq!(pi_fraction(PI/2.0 + 1e-6), None);
+
+        assert_eq!(angle(PI, "π"), "π");
+        assert_eq!(angle(-PI, "π"), "-π");
+        assert_eq!(angle(4.0*PI, "π"), "4π");
+        assert_eq!(angle(-PI/2.0, "\\pi"), "-\\pi/2");
+        assert_eq!(angle(3.0*PI/4.0, "π"), "3π/4");
+        assert_eq!(angle(PI/16.0, "π"), "π/16");
+        assert_eq!(angle(0.0, "π"), "0");
+        assert_eq!(angle(0.45, "π"), "0.45");
+    }
+}
@@ -0,0 +1,90 @@
+use crate::instruction::Instruction;
+use crate::render::label;
+use crate::render::layout::layout;
+
+// Render program as a quantikz environment, one column per moment:
+//
+// \begin{quantikz}
+// \lstick{$q_{0}$} & \gate{R_X(\pi/2)} & \ctrl{1} & \meter{} & \qw \\
+// \lstick{$q_{1}$} & \qw & \control{} & \meter{} & \qw
+// \end{quantikz}
+//
+// The snippet needs \usepackage{tikz} and \usetikzlibrary{quantikz}
+pub fn prog_to_latex(program: &[Instruction]) -> String {
+    let layout = layout(program);
+    if layout.qubits.is_empty() {
+        return String::new();
+    }
+
+    // One row per qubit the program uses
+    let mut cells: Vec<Vec<String>> = layout
+        .qubits
+        .iter()
+        .map(|q| vec![format!("\\lstick{{$q_{{{}}}$}}", q)])
+        .collect();
+
+    for moment in &layout.moments {
+        for row in cells.iter_mut() {
+            row.push(String::from("\\qw"));
+        }
+        let column = cells[0].len() - 1;
+
+        for &i in moment {
+            match program[i] {
+                Instruction::RX(f, q) => {
+                    cells[layout.row(q)][column] =
+                        format!("\\gate{{R_X({})}}", label::angle(f, "\\pi"))
+                }
+                Instruction::RZ(f, q) => {
+                    cells[layout.row(q)][column] =
+                        format!("\\gate{{R_Z({})}}", label::angle(f, "\\pi"))
+                }
+                Instruction::CZ(..) => {
+                    let (low, high) = layout.occupied(&program[i]).unwrap();
+                    cells[low][column] = format!("\\ctrl{{{}}}", high - low);
+                    cells[high][column] = String::from("\\control{}");
+                }
+                Instruction::MEASURE(q) => cells[layout.row(q)][column] = String::from("\\meter{}"),
+                Instruction::INVALID => panic!("invalid instruction"),
+            }
+        }
+    }
+
+    let rows: Vec<String> = cells
+        .into_iter()
+        .map(|mut row| {
+            row.push(String::from("\\qw"));
+            row.join(" & ")
+        })
+        .collect();
+
+    format!(
+        "\\begin{{quantikz}}\n{}\n\\end{{quantikz}}",
+        rows.join(" \\\\\n")
+    )
+}
+
+#[rustfmt::skip]
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f64::consts::PI;
+
+    #[test]
+    fn program_to_latex() {
+        let program = [
+            Instruction::RX(PI/2.0, 0),
+            Instruction::RZ(0.45, 2),
+            Instruction::CZ(2, 0),
+            Instruction::MEASURE(0),
+        ];
+
+        let expected = "\
+\\begin{quantikz}
+\\lstick{$q_{0}$} & \\gate{R_X(\\pi/2)} & \\ctrl{1} & \\meter{} & \\qw \\\\
+\\lstick{$q_{2}$} & \\gate{R_Z(0.45)} & \\control{} & \\qw & \\qw
+\\end{quantikz}";
+        assert_eq!(prog_to_latex(&program), expected);
+        assert_eq!(prog_to_latex(&[]), "");
+    }
+}
@@ -0,0 +1,113 @@
+use crate::instruction::Instruction;
+
+// Program arranged into moments, sets of gates that can run at the same time
+#[derive(Debug, PartialEq, Clone)]
+pub struct Layout {
+    // Qubits drawn as rows, in ascending order. Qubits the program never
+    // uses get no row, so sparse indices stay cheap.
+    pub qubits: Vec<u32>,
+    // Indices into the program, in program order within each moment
+    pub moments: Vec<Vec<usize>>,
+}
+
+impl Layout {
+    // Row of qubit `q`, which the program must use
+    pub fn row(&self, q: u32) -> usize {
+        self.qubits.binary_search(&q).expect("qubit is not used")
+    }
+
+    // Rows an instruction occupies in a drawing. A CZ also blocks every row
+    // between its operands, since its connector is drawn across them.
+    pub fn occupied(&self, instr: &Instruction) -> Option<(usize, usize)> {
+        match *instr {
+            Instruction::RX(_, q) | Instruction::RZ(_, q) | Instruction::MEASURE(q) => {
+                Some((self.row(q), self.row(q)))
+            }
+            Instruction::CZ(q1, q2) => {
+                let (r1, r2) = (self.row(q1), self.row(q2));
+                Some((r1.min(r2), r1.max(r2)))
+            }
+            Instruction::INVALID => None,
+        }
+    }
+}
+
+// Place every instruction in the earliest moment after all earlier
+// instructions on the rows it occupies
+pub fn layout(program: &[Instruction]) -> Layout {
+    let mut qubits: Vec<u32> = program.iter().flat_map(Instruction::qubits).collect();
+    qubits.sort_unstable();
+    qubits.dedup();
+
+    let mut layout = Layout {
+        qubits,
+        moments: Vec::new(),
+    };
+
+    // Number of moments already used on each row
+    let mut depth = vec![0usize; layout.qubits.len()];
+    for (i, instr) in program.iter().enumerate() {
+        let Some((low, high)) = layout.occupied(instr) else {
+            continue;
+        };
+
+        let moment = depth[low..=high].iter().copied().max().unwrap_or(0);
+        if moment == layout.moments.len() {
+            layout.moments.push(Vec::new());
+        }
+        layout.moments[moment].push(i);
+        depth[low..=high].fill(moment + 1);
+    }
+
+    layout
+}
+
+#[rustfmt::skip]
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn layout_moments() {
+        let program = [
+            Instruction::RX(0.5, 0),
+            Instruction::RZ(0.5, 1),
+            Instruction::CZ(0, 1),
+            Instruction::RX(0.5, 2),
+            Instruction::MEASURE(0),
+            Instruction::RZ(0.5, 1),
+        ];
+
+        let layout = layout(&program);
+        assert_eq!(layout.qubits, vec![0, 1, 2]);
+        assert_eq!(layout.moments, vec![vec![0, 1, 3], vec![2], vec![4, 5]]);
+    }
+
+    #[test]
+    fn cz_blocks_wires_between_operands() {
+        let program = [
+            Instruction::CZ(0, 2),
+            Instruction::RX(0.5, 1),
+            Instruction::CZ(1, 3),
+            Instruction::RZ(0.5, 0),
+        ];
+
+        assert_eq!(layout(&program).moments, vec![vec![0], vec![1, 3], vec![2]]);
+        assert_eq!(layout(&[]).moments, Vec::<Vec<usize>>::new());
+    }
+
+    #[test]
+    fn unused_qubits_get_no_row() {
+        let program = [
+            Instruction::RX(0.5, 4_000_000_000),
+            Instruction::CZ(3, 4_000_000_000),
+            Instruction::MEASURE(u32::MAX),
+        ];
+
+        let layout = layout(&program);
+        assert_eq!(layout.qubits, vec![3, 4_000_000_000, u32::MAX]);
+        assert_eq!(layout.row(4_000_000_000), 1);
+        assert_eq!(layout.occupied(&program[1]), Some((0, 1)));
+        assert_eq!(layout.moments, vec![vec![0, 2], vec![1]]);
+    }
+}
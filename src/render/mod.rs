@@ -0,0 +1,6 @@
+pub mod ascii;
+pub mod dot;
+pub mod label;
+pub mod latex;
+pub mod layout;
+pub mod svg;
@@ -0,0 +1,201 @@
+use crate::instruction::Instruction;
+use crate::render::label;
+use crate::render::layout::layout;
+
+// Drawing dimensions in pixels. Coordinates are u64 so that long circuits
+// and many rows cannot overflow.
+const MARGIN: u64 = 20;
+const MIN_LABEL_WIDTH: u64 = 40;
+const WIRE_SPACING: u64 = 48;
+const GATE_HEIGHT: u64 = 28;
+const CHAR_WIDTH: u64 = 8;
+const COLUMN_PADDING: u64 = 10;
+const DOT_RADIUS: u64 = 4;
+
+// Render program as a standalone SVG document, laid out in the same moments
+// as the text diagram. RX/RZ are labelled boxes, CZ two dots joined by a
+// line and MEASURE a box with a meter.
+pub fn prog_to_svg(program: &[Instruction]) -> String {
+    let layout = layout(program);
+    let wire_y = |q: u32| MARGIN + GATE_HEIGHT / 2 + layout.row(q) as u64 * WIRE_SPACING;
+
+    // The label column fits the longest qubit label, e.g. q4294967295
+    let label_width = layout
+        .qubits
+        .iter()
+        .map(|q| (format!("q{}", q).len() as u64) * CHAR_WIDTH + COLUMN_PADDING)
+        .fold(MIN_LABEL_WIDTH, u64::max);
+
+    let mut elements: Vec<String> = Vec::new();
+    let mut x = MARGIN + label_width;
+    for moment in &layout.moments {
+        let width = moment
+            .iter()
+            .map(|&i| gate_width(&program[i]))
+            .max()
+            .unwrap();
+        let center = x + COLUMN_PADDING + width / 2;
+
+        for &i in moment {
+            match program[i] {
+                Instruction::RX(f, q) | Instruction::RZ(f, q) => {
+                    let name = match program[i] {
+                        Instruction::RX(..) => "RX",
+                        _ => "RZ",
+                    };
+                    let text = format!("{}({})", name, label::angle(f, "π"));
+                    let box_width = gate_width(&program[i]);
+                    elements.push(gate_box(center, wire_y(q), box_width));
+                    elements.push(format!(
+                        "<text x=\"{}\" y=\"{}\" text-anchor=\"middle\">{}</text>",
+                        center,
+                        wire_y(q) + 5,
+                        text
+                    ));
+                }
+                Instruction::CZ(q1, q2) => {
+                    elements.push(format!(
+                        "<line x1=\"{0}\" y1=\"{1}\" x2=\"{0}\" y2=\"{2}\" stroke=\"black\"/>",
+                        center,
+                        wire_y(q1.min(q2)),
+                        wire_y(q1.max(q2))
+                    ));
+                    for q in [q1, q2] {
+                        elements.push(format!(
+                            "<circle cx=\"{}\" cy=\"{}\" r=\"{}\" fill=\"black\"/>",
+                            center,
+                            wire_y(q),
+                            DOT_RADIUS
+                        ));
+                    }
+                }
+                Instruction::MEASURE(q) => {
+                    let y = wire_y(q);
+                    elements.push(gate_box(center, y, GATE_HEIGHT));
+                    elements.push(format!(
+                        "<path d=\"M {} {} A 10 10 0 0 1 {} {}\" fill=\"none\" stroke=\"black\"/>",
+                        center - 9,
+                        y + 6,
+                        center + 9,
+                        y + 6
+                    ));
+                    elements.push(format!(
+                        "<line x1=\"{}\" y1=\"{}\" x2=\"{}\" y2=\"{}\" stroke=\"black\"/>",
+                        center,
+                        y + 6,
+                        center + 7,
+                        y - 8
+                    ));
+                }
+                Instruction::INVALID => panic!("invalid instruction"),
+            }
+        }
+
+        x += width + 2 * COLUMN_PADDING;
+    }
+
+    let width = x + MARGIN;
+    let height =
+        2 * MARGIN + GATE_HEIGHT + (layout.qubits.len() as u64).saturating_sub(1) * WIRE_SPACING;
+
+    let mut output = format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{0}\" height=\"{1}\" viewBox=\"0 0 {0} {1}\" font-family=\"monospace\" font-size=\"14\">\n",
+        width, height
+    );
+    output.push_str("  <rect width=\"100%\" height=\"100%\" fill=\"white\"/>\n");
+
+    // Wires go first so gates are drawn on top of them
+    for &q in &layout.qubits {
+        output.push_str(&format!(
+            "  <text x=\"{}\" y=\"{}\">q{}</text>\n",
+            MARGIN,
+            wire_y(q) + 5,
+            q
+        ));
+        output.push_str(&format!(
+            "  <line x1=\"{0}\" y1=\"{1}\" x2=\"{2}\" y2=\"{1}\" stroke=\"black\"/>\n",
+            MARGIN + label_width,
+            wire_y(q),
+            width - MARGIN
+        ));
+    }
+    for element in elements {
+        output.push_str(&format!("  {}\n", element));
+    }
+    output.push_str("</svg>");
+
+    output
+}
+
+fn gate_width(instr: &Instruction) -> u64 {
+    match *instr {
+        Instruction::RX(f, _) | Instruction::RZ(f, _) => {
+            let chars = label::angle(f, "π").chars().count() as u64 + 4;
+            chars * CHAR_WIDTH + 12
+        }
+        Instruction::CZ(_, _) => 2 * DOT_RADIUS,
+        Instruction::MEASURE(_) => GATE_HEIGHT,
+        Instruction::INVALID => panic!("invalid instruction"),
+    }
+}
+
+fn gate_box(center: u64, y: u64, width: u64) -> String {
+    format!(
+        "<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" fill=\"white\" stroke=\"black\"/>",
+        center - width / 2,
+        y - GATE_HEIGHT / 2,
+        width,
+        GATE_HEIGHT
+    )
+}
+
+#[rustfmt::skip]
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f64::consts::PI;
+
+    #[test]
+    fn program_to_svg() {
+        let program = [
+            Instruction::RX(PI/2.0, 0),
+            Instruction::CZ(0, 1),
+            Instruction::MEASURE(1),
+        ];
+
+        let svg = prog_to_svg(&program);
+        assert!(svg.starts_with("<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"244\" height=\"116\""));
+        assert!(svg.ends_with("</svg>"));
+
+        // Gate box and label centered in the first column
+        assert!(svg.contains("<rect x=\"70\" y=\"20\" width=\"68\" height=\"28\" fill=\"white\" stroke=\"black\"/>"));
+        assert!(svg.contains("<text x=\"104\" y=\"39\" text-anchor=\"middle\">RX(π/2)</text>"));
+
+        // CZ connector in the second column, meter in the third
+        assert!(svg.contains("<line x1=\"162\" y1=\"34\" x2=\"162\" y2=\"82\" stroke=\"black\"/>"));
+        assert_eq!(svg.matches("<circle").count(), 2);
+        assert!(svg.contains("<path d=\"M 191 88 A 10 10 0 0 1 209 88\""));
+
+        // One label and wire per qubit
+        assert!(svg.contains("<text x=\"20\" y=\"87\">q1</text>"));
+        assert_eq!(svg.matches("<line").count(), 4);
+    }
+
+    #[test]
+    fn sparse_qubits() {
+        // Rows follow the qubits used, not their indices
+        let svg = prog_to_svg(&[Instruction::CZ(u32::MAX, 4000000000)]);
+        assert!(svg.contains("<line x1=\"132\" y1=\"34\" x2=\"132\" y2=\"82\" stroke=\"black\"/>"));
+        assert!(svg.contains("<text x=\"20\" y=\"87\">q4294967295</text>"));
+
+        // The label column widens to fit the longest label
+        assert!(svg.contains("<line x1=\"118\" y1=\"82\" x2=\"146\" y2=\"82\" stroke=\"black\"/>"));
+    }
+
+    #[test]
+    fn empty_program() {
+        let svg = prog_to_svg(&[]);
+        assert!(svg.starts_with("<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"80\" height=\"68\""));
+        assert!(!svg.contains("<line"));
+    }
+}
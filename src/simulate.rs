@@ -0,0 +1,371 @@
+use crate::complex::Complex;
+use crate::instruction::Instruction;
+
+// Dense statevectors grow as 2^n, so refuse anything that would not
+// comfortably fit in memory.
+pub const MAX_QUBITS: u32 = 24;
+
+// Default tolerance used when comparing simulated states
+pub const DEFAULT_TOLERANCE: f64 = 1e-9;
+
+// Probability of reading a 1 on `qubit` at the point a MEASURE was executed
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct Measurement {
+    pub qubit: u32,
+    pub probability_one: f64,
+}
+
+// Dense statevector over `num_qubits` qubits. Qubit `q` corresponds to bit
+// `q` of the basis state index (little-endian).
+#[derive(Debug, PartialEq, Clone)]
+pub struct StateVector {
+    num_qubits: u32,
+    amplitudes: Vec<Complex>,
+}
+
+impl StateVector {
+    // Initialize all qubits to |0>
+    pub fn new(num_qubits: u32) -> Result<StateVector, String> {
+        if num_qubits > MAX_QUBITS {
+            return Err(too_many_qubits(num_qubits as u64));
+        }
+
+        let mut amplitudes = vec![Complex::ZERO; 1 << num_qubits];
+        amplitudes[0] = Complex::ONE;
+
+        Ok(StateVector {
+            num_qubits,
+            amplitudes,
+        })
+    }
+
+    // Initialize to the computational basis state |index>
+    pub fn basis(num_qubits: u32, index: usize) -> Result<StateVector, String> {
+        let mut state = StateVector::new(num_qubits)?;
+        if index >= state.amplitudes.len() {
+            return Err(format!(
+                "Basis state {} out of range for {} qubit statevector",
+                index, num_qubits
+            ));
+        }
+
+        state.amplitudes[0] = Complex::ZERO;
+        state.amplitudes[index] = Complex::ONE;
+        Ok(state)
+    }
+
+    pub fn num_qubits(&self) -> u32 {
+        self.num_qubits
+    }
+
+    pub fn amplitudes(&self) -> &[Complex] {
+        &self.amplitudes
+    }
+
+    // Probability of each computational basis state
+    pub fn probabilities(&self) -> Vec<f64> {
+        self.amplitudes.iter().map(|amp| amp.norm_sqr()).collect()
+    }
+
+    // Marginal probability of reading a 1 on the given qubit
+    pub fn probability_one(&self, qubit: u32) -> f64 {
+        let mask = 1usize << qubit;
+        self.amplitudes
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| i & mask != 0)
+            .map(|(_, amp)| amp.norm_sqr())
+            .sum()
+    }
+
+    // Apply a single instruction. MEASURE does not collapse the state, it
+    // only reports the outcome distribution for that qubit.
+    pub fn apply(&mut self, instr: &Instruction) -> Result<Option<Measurement>, String> {
+        match *instr {
+            Instruction::RX(theta, q) => {
+                self.check_qubit(q)?;
+                let c = Complex::new((theta / 2.0).cos(), 0.0);
+                let s = Complex::new(0.0, -(theta / 2.0).sin());
+                self.apply_single([[c, s], [s, c]], q);
+            }
+            Instruction::RZ(theta, q) => {
+                self.check_qubit(q)?;
+                let zero = Complex::ZERO;
+                self.apply_single(
+                    [
+                        [Complex::cis(-theta / 2.0), zero],
+                        [zero, Complex::cis(theta / 2.0)],
+                    ],
+                    q,
+                );
+            }
+            Instruction::CZ(q1, q2) => {
+                self.check_qubit(q1)?;
+                self.check_qubit(q2)?;
+                if q1 == q2 {
+                    return Err(format!(
+                        "CZ requires two distinct qubits, got {} {}",
+                        q1, q2
+                    ));
+                }
+
+                let mask = (1usize << q1) | (1usize << q2);
+                for (i, amp) in self.amplitudes.iter_mut().enumerate() {
+                    if i & mask == mask {
+                        *amp = -*amp;
+                    }
+                }
+            }
+            Instruction::MEASURE(q) => {
+                self.check_qubit(q)?;
+                return Ok(Some(Measurement {
+                    qubit: q,
+                    probability_one: self.probability_one(q),
+                }));
+            }
+            Instruction::INVALID => {
+                return Err(String::from("Cannot simulate invalid instruction"));
+            }
+        }
+
+        Ok(None)
+    }
+
+    // Inner product <self|other>
+    pub fn inner(&self, other: &StateVector) -> Complex {
+        let mut acc = Complex::ZERO;
+        for (a, b) in self.amplitudes.iter().zip(other.amplitudes.iter()) {
+            acc += a.conj() * *b;
+        }
+        acc
+    }
+
+    // Two normalized states are equal up to global phase iff |<a|b>| == 1
+    pub fn equivalent(&self, other: &StateVector, tolerance: f64) -> bool {
+        self.num_qubits == other.num_qubits && (1.0 - self.inner(other).abs()).abs() <= tolerance
+    }
+
+    fn check_qubit(&self, q: u32) -> Result<(), String> {
+        if q >= self.num_qubits {
+            return Err(format!(
+                "Qubit {} out of range for {} qubit statevector",
+                q, self.num_qubits
+            ));
+        }
+        Ok(())
+    }
+
+    // Apply a 2x2 matrix to qubit q by pairing each amplitude whose q bit is
+    // 0 with its partner whose q bit is 1
+    fn apply_single(&mut self, m: [[Complex; 2]; 2], q: u32) {
+        let mask = 1usize << q;
+        for i in 0..self.amplitudes.len() {
+            if i & mask != 0 {
+                continue;
+            }
+            let a0 = self.amplitudes[i];
+            let a1 = self.amplitudes[i | mask];
+            self.amplitudes[i] = m[0][0] * a0 + m[0][1] * a1;
+            self.amplitudes[i | mask] = m[1][0] * a0 + m[1][1] * a1;
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Simulation {
+    pub state: StateVector,
+    pub measurements: Vec<Measurement>,
+}
+
+impl Simulation {
+    pub fn amplitudes(&self) -> &[Complex] {
+        self.state.amplitudes()
+    }
+
+    pub fn probabilities(&self) -> Vec<f64> {
+        self.state.probabilities()
+    }
+}
+
+// Number of qubits touched by a program (highest index + 1). This is a u64
+// since a program using qubit u32::MAX touches 2^32 qubits.
+pub fn num_qubits(program: &[Instruction]) -> u64 {
+    program
+        .iter()
+        .filter_map(|instr| match *instr {
+            Instruction::RX(_, q) | Instruction::RZ(_, q) | Instruction::MEASURE(q) => Some(q),
+            Instruction::CZ(q1, q2) => Some(q1.max(q2)),
+            Instruction::INVALID => None,
+        })
+        .max()
+        .map_or(0, |q| q as u64 + 1)
+}
+
+fn too_many_qubits(num_qubits: u64) -> String {
+    format!(
+        "Cannot simulate {} qubits, maximum is {}",
+        num_qubits, MAX_QUBITS
+    )
+}
+
+// Execute program on |0...0>, sized to the qubits it touches
+pub fn simulate(program: &[Instruction]) -> Result<Simulation, String> {
+    simulate_with_qubits(program, num_qubits(program))
+}
+
+pub fn simulate_with_qubits(
+    program: &[Instruction],
+    num_qubits: u64,
+) -> Result<Simulation, String> {
+    let num_qubits = u32::try_from(num_qubits).map_err(|_| too_many_qubits(num_qubits))?;
+    let mut state = StateVector::new(num_qubits)?;
+    let mut measurements: Vec<Measurement> = Vec::new();
+
+    for instr in program {
+        if let Some(measurement) = state.apply(instr)? {
+            measurements.push(measurement);
+        }
+    }
+
+    Ok(Simulation {
+        state,
+        measurements,
+    })
+}
+
+// Simulate both programs over the same number of qubits and compare the
+// final states up to global phase
+pub fn equivalent_programs(
+    a: &[Instruction],
+    b: &[Instruction],
+    tolerance: f64,
+) -> Result<bool, String> {
+    let n = num_qubits(a).max(num_qubits(b));
+    let sim_a = simulate_with_qubits(a, n)?;
+    let sim_b = simulate_with_qubits(b, n)?;
+
+    Ok(sim_a.state.equivalent(&sim_b.state, tolerance))
+}
+
+#[rustfmt::skip]
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f64::consts::PI;
+
+    const EPS: f64 = 1e-12;
+
+    #[test]
+    fn initial_state() {
+        let state = StateVector::new(2).unwrap();
+        assert_eq!(state.amplitudes(), &[Complex::ONE, Complex::ZERO, Complex::ZERO, Complex::ZERO]);
+
+        let err = StateVector::new(MAX_QUBITS + 1).unwrap_err();
+        assert_eq!(err, "Cannot simulate 25 qubits, maximum is 24");
+    }
+
+    #[test]
+    fn rx_pi_flips_qubit() {
+        let sim = simulate(&[Instruction::RX(PI, 1)]).unwrap();
+        let probs = sim.probabilities();
+
+        assert_eq!(sim.state.num_qubits(), 2);
+        assert!((probs[0b10] - 1.0).abs() < EPS);
+        assert!(sim.amplitudes()[0b10].approx_eq(Complex::new(0.0, -1.0), EPS));
+    }
+
+    #[test]
+    fn rx_half_pi_superposition() {
+        let sim = simulate(&[
+            Instruction::RX(PI/2.0, 0),
+            Instruction::MEASURE(0),
+        ]).unwrap();
+
+        assert_eq!(sim.measurements.len(), 1);
+        assert_eq!(sim.measurements[0].qubit, 0);
+        assert!((sim.measurements[0].probability_one - 0.5).abs() < EPS);
+    }
+
+    #[test]
+    fn rz_phase() {
+        let sim = simulate(&[
+            Instruction::RX(PI, 0),
+            Instruction::RZ(PI/2.0, 0),
+        ]).unwrap();
+
+        // RX(PI)|0> = -i|1>, then RZ applies e^(i*PI/4) to |1>
+        let expected = Complex::new(0.0, -1.0) * Complex::cis(PI/4.0);
+        assert!(sim.amplitudes()[1].approx_eq(expected, EPS));
+    }
+
+    #[test]
+    fn cz_phase() {
+        let sim = simulate(&[
+            Instruction::RX(PI, 0),
+            Instruction::RX(PI, 1),
+            Instruction::CZ(1, 0),
+        ]).unwrap();
+
+        // (-i)(-i) = -1, CZ flips sign back to 1
+        assert!(sim.amplitudes()[0b11].approx_eq(Complex::ONE, EPS));
+    }
+
+    #[test]
+    fn measure_without_collapse() {
+        let sim = simulate(&[
+            Instruction::RX(PI/2.0, 0),
+            Instruction::MEASURE(0),
+            Instruction::RX(-PI/2.0, 0),
+            Instruction::MEASURE(0),
+        ]).unwrap();
+
+        assert!((sim.measurements[0].probability_one - 0.5).abs() < EPS);
+        assert!(sim.measurements[1].probability_one.abs() < EPS);
+    }
+
+    #[test]
+    fn invalid_programs() {
+        let err = simulate(&[Instruction::CZ(1, 1)]).unwrap_err();
+        assert_eq!(err, "CZ requires two distinct qubits, got 1 1");
+
+        let err = simulate_with_qubits(&[Instruction::RZ(1.0, 3)], 2).unwrap_err();
+        assert_eq!(err, "Qubit 3 out of range for 2 qubit statevector");
+
+        let err = simulate(&[Instruction::RX(1.0, 77)]).unwrap_err();
+        assert_eq!(err, "Cannot simulate 78 qubits, maximum is 24");
+
+        assert_eq!(num_qubits(&[Instruction::MEASURE(u32::MAX)]), 1 << 32);
+        let err = simulate(&[Instruction::MEASURE(u32::MAX)]).unwrap_err();
+        assert_eq!(err, "Cannot simulate 4294967296 qubits, maximum is 24");
+    }
+
+    #[test]
+    fn global_phase_equivalence() {
+        // RZ(2*PI) is -I, equal to the identity up to global phase
+        assert!(equivalent_programs(
+            &[Instruction::RX(0.3, 0), Instruction::RZ(2.0*PI, 0)],
+            &[Instruction::RX(0.3, 0)],
+            DEFAULT_TOLERANCE,
+        ).unwrap());
+
+        assert!(!equivalent_programs(
+            &[Instruction::RX(0.3, 0)],
+            &[Instruction::RX(-0.3, 0)],
+            DEFAULT_TOLERANCE,
+        ).unwrap());
+    }
+
+    #[test]
+    fn native_translation_identity() {
+        // Identity used by native_translation_pass
+        let translated = [
+            Instruction::RZ(PI/2.0, 0),
+            Instruction::RX(PI/2.0, 0),
+            Instruction::RZ(0.45, 0),
+            Instruction::RX(-PI/2.0, 0),
+            Instruction::RZ(-PI/2.0, 0),
+        ];
+
+        assert!(equivalent_programs(&[Instruction::RX(0.45, 0)], &translated, DEFAULT_TOLERANCE).unwrap());
+    }
+}
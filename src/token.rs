@@ -1,4 +1,4 @@
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, Clone)]
 pub enum TokenType {
     UNDEF,
 
@@ -8,16 +8,27 @@ pub enum TokenType {
     LParen,
     RParen,
     Negative,
+    Plus,
+    Star,
+    Slash,
     EOL,
 
+    // Only produced when the lexer is asked to keep comments
+    Comment(String),
+
     RX,
     RZ,
     CZ,
     MEASURE,
+
+    // Angle constants
+    PI,
+    TAU,
+    E,
 }
 
 // Wrap token type with line+pos info
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, Clone)]
 pub struct Token {
     pub t: TokenType,
     pub line: u32,